@@ -23,9 +23,10 @@ pub fn controls_ui(
 
     let asset = assets.get(handle.id()).unwrap();
     let metadata = asset.metadata().unwrap();
-    let VectorFile::Lottie { composition } = &asset.data else {
+    let VectorFile::Lottie { composition, .. } = &asset.data else {
         return;
     };
+    let segment = options.segments.frames(asset);
 
     let window = egui::Window::new("Controls")
         .resizable(false)
@@ -40,8 +41,7 @@ pub fn controls_ui(
             if ui
                 .add(egui::Slider::new(
                     &mut frame,
-                    options.segments.start.max(composition.frames.start)
-                        ..=options.segments.end.min(composition.frames.end).prev(),
+                    segment.start..=segment.end.prev(),
                 ))
                 .changed()
             {
@@ -130,6 +130,19 @@ pub fn controls_ui(
                     Duration::from_secs_f32(intermission);
                 options.intermission = Duration::from_secs_f32(intermission);
             };
+            let mut jitter = options.intermission_jitter.as_secs_f32();
+            if ui
+                .add(egui::Slider::new(&mut jitter, 0.0..=5.0).text("jitter"))
+                .changed()
+            {
+                player
+                    .state_mut()
+                    .options
+                    .as_mut()
+                    .unwrap()
+                    .intermission_jitter = Duration::from_secs_f32(jitter);
+                options.intermission_jitter = Duration::from_secs_f32(jitter);
+            };
         });
         ui.vertical(|ui| {
             ui.label("Play Mode");
@@ -197,39 +210,33 @@ pub fn controls_ui(
             ui.horizontal(|ui| {
                 ui.separator();
                 ui.label("Start");
-                let mut start = options.segments.start;
+                let mut start = segment.start;
                 if ui
                     .add(
-                        egui::Slider::new(
-                            &mut start,
-                            composition.frames.start
-                                ..=options.segments.end.min(composition.frames.end),
-                        )
-                        .integer(),
+                        egui::Slider::new(&mut start, composition.frames.start..=segment.end)
+                            .integer(),
                     )
                     .changed()
                 {
-                    player.state_mut().options.as_mut().unwrap().segments.start = start;
-                    options.segments.start = start;
+                    let segments = Segment::Frames(start..segment.end);
+                    player.state_mut().options.as_mut().unwrap().segments = segments.clone();
+                    options.segments = segments;
                 };
             });
             ui.horizontal(|ui| {
                 ui.separator();
                 ui.label("End");
-                let mut end = options.segments.end;
+                let mut end = segment.end;
                 if ui
                     .add(
-                        egui::Slider::new(
-                            &mut end,
-                            options.segments.start.max(composition.frames.start)
-                                ..=composition.frames.end,
-                        )
-                        .integer(),
+                        egui::Slider::new(&mut end, segment.start..=composition.frames.end)
+                            .integer(),
                     )
                     .changed()
                 {
-                    player.state_mut().options.as_mut().unwrap().segments.end = end;
-                    options.segments.end = end;
+                    let segments = Segment::Frames(segment.start..end);
+                    player.state_mut().options.as_mut().unwrap().segments = segments.clone();
+                    options.segments = segments;
                 };
             });
         });
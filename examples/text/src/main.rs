@@ -14,7 +14,6 @@ fn main() {
             (setup_camera, setup_screenspace_text, setup_worldspace_text),
         );
     embedded_asset!(app, "src", "assets/Rubik-Medium.ttf");
-    embedded_asset!(app, "src", "assets/Rubik-Medium.vttf");
     app.run();
 }
 
@@ -24,7 +23,7 @@ fn setup_camera(mut commands: Commands) {
 
 fn setup_worldspace_text(mut commands: Commands, asset_server: ResMut<AssetServer>) {
     commands.spawn(VelloTextBundle {
-        font: asset_server.load("embedded://text/assets/Rubik-Medium.vttf"),
+        font: asset_server.load("embedded://text/assets/Rubik-Medium.ttf"),
         text: VelloText {
             content: "WHello vello\nwith multi-line support".to_string(),
             size: 50.0,
@@ -36,7 +35,7 @@ fn setup_worldspace_text(mut commands: Commands, asset_server: ResMut<AssetServe
     });
 
     commands.spawn(VelloTextBundle {
-        font: asset_server.load("embedded://text/assets/Rubik-Medium.vttf"),
+        font: asset_server.load("embedded://text/assets/Rubik-Medium.ttf"),
         text: VelloText {
             content: "WXYZ".to_string(),
             size: 100.0,
@@ -51,7 +50,7 @@ fn setup_worldspace_text(mut commands: Commands, asset_server: ResMut<AssetServe
 fn setup_screenspace_text(mut commands: Commands, asset_server: ResMut<AssetServer>) {
     // Vello text
     commands.spawn(VelloTextBundle {
-        font: asset_server.load("embedded://text/assets/Rubik-Medium.vttf"),
+        font: asset_server.load("embedded://text/assets/Rubik-Medium.ttf"),
         text: VelloText {
             content: "Text rendered by Vello!".to_string(),
             size: 15.0,
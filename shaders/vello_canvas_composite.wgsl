@@ -0,0 +1,34 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0)
+var texture: texture_2d<f32>;
+@group(0) @binding(1)
+var texture_sampler: sampler;
+
+fn sRGB_OETF(a: f32) -> f32 {
+    if .04045f < a {
+        return pow((a + .055f) / 1.055f, 2.4f);
+    } else {
+        return  a / 12.92f;
+    }
+}
+
+fn linear_from_srgba(srgba: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(
+        sRGB_OETF(srgba.r),
+        sRGB_OETF(srgba.g),
+        sRGB_OETF(srgba.b),
+        srgba.a);
+}
+
+// Drawn after post-processing, so emissive intensity doesn't apply. Also
+// decodes vello's output into float canvases.
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(texture, texture_sampler, in.uv);
+#ifdef DECODE_SRGB
+    return linear_from_srgba(color);
+#else
+    return color;
+#endif
+}
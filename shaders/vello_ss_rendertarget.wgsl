@@ -6,6 +6,11 @@ var<uniform> view: View;
 var texture: texture_2d<f32>;
 @group(2) @binding(1)
 var texture_sampler: sampler;
+@group(2) @binding(2)
+var<uniform> intensity: f32;
+// 1 for canvases holding sRGB encoded color, 0 for linear color
+@group(2) @binding(3)
+var<uniform> srgb: u32;
 
 // returns the (0-1, 0-1) position within the given viewport for the current buffer coords .
 // buffer coords can be obtained from `@builtin(position).xy`.
@@ -55,7 +60,9 @@ fn fragment(
     #import bevy_sprite::mesh2d_vertex_output
 ) -> @location(0) vec4<f32> {
     let uvs = coords_to_viewport_uv(position.xy, view.viewport);
-    let color = textureSample(texture, texture_sampler, uvs);
-    let color_converted = linear_from_srgba(color);
-    return color_converted;
+    var color_converted = textureSample(texture, texture_sampler, uvs);
+    if srgb != 0u {
+        color_converted = linear_from_srgba(color_converted);
+    }
+    return vec4<f32>(color_converted.rgb * intensity, color_converted.a);
 }
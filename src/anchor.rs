@@ -0,0 +1,76 @@
+use crate::VelloAsset;
+use bevy::prelude::*;
+
+/// The point of an asset placed at, and rotated around, its entity's
+/// transform, like [`Sprite::anchor`]. Entities without this component use
+/// the [`VelloAssetOrigin`](crate::prelude::VelloAssetOrigin) the asset was
+/// loaded with.
+///
+/// Ignored on UI nodes, which fill their node.
+#[derive(PartialEq, Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub enum VelloAnchor {
+    #[default]
+    Center,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+    CenterLeft,
+    CenterRight,
+    TopLeft,
+    TopCenter,
+    TopRight,
+    /// A point of the asset's bounds, from `(-0.5, -0.5)` at the bottom left
+    /// to `(0.5, 0.5)` at the top right.
+    Custom(Vec2),
+}
+
+impl VelloAnchor {
+    /// The normalized pivot, from `(-0.5, -0.5)` at the bottom left to
+    /// `(0.5, 0.5)` at the top right.
+    pub fn as_vec(&self) -> Vec2 {
+        match self {
+            VelloAnchor::Center => Vec2::ZERO,
+            VelloAnchor::BottomLeft => Vec2::new(-0.5, -0.5),
+            VelloAnchor::BottomCenter => Vec2::new(0.0, -0.5),
+            VelloAnchor::BottomRight => Vec2::new(0.5, -0.5),
+            VelloAnchor::CenterLeft => Vec2::new(-0.5, 0.0),
+            VelloAnchor::CenterRight => Vec2::new(0.5, 0.0),
+            VelloAnchor::TopLeft => Vec2::new(-0.5, 0.5),
+            VelloAnchor::TopCenter => Vec2::new(0.0, 0.5),
+            VelloAnchor::TopRight => Vec2::new(0.5, 0.5),
+            VelloAnchor::Custom(point) => *point,
+        }
+    }
+
+    /// Where the anchor is, measured from the asset's top-left corner with Y
+    /// pointing down, in the asset's authored units.
+    pub fn origin(&self, asset: &VelloAsset) -> Vec2 {
+        let content_size = Vec2::new(asset.width, asset.height) / asset.baked_scale();
+        let pivot = self.as_vec();
+        Vec2::new(pivot.x + 0.5, 0.5 - pivot.y) * content_size
+    }
+
+    /// Place the origin of an asset at the anchor, like
+    /// [`VelloAssetOrigin::apply`](crate::prelude::VelloAssetOrigin::apply).
+    pub fn apply(&self, asset: &mut VelloAsset) {
+        let origin = self.origin(asset);
+        asset.local_transform_center.translation.x = origin.x;
+        asset.local_transform_center.translation.y = -origin.y;
+    }
+
+    /// Move a world space transform so an asset drawn at its own origin is
+    /// anchored, e.g. to test its [`VelloAsset::local_rect`] against a
+    /// pointer. Apply it after [`VelloUnits`](crate::VelloUnits).
+    pub fn apply_to_transform(
+        &self,
+        asset: &VelloAsset,
+        transform: &GlobalTransform,
+    ) -> GlobalTransform {
+        let current = asset.local_transform_center.translation;
+        let moved = self.origin(asset) - Vec2::new(current.x, -current.y);
+        // Content Y points down, entity Y points up
+        let offset = moved * asset.baked_scale() * Vec2::new(1.0, -1.0);
+        *transform * Transform::from_translation(-offset.extend(0.0))
+    }
+}
@@ -1,18 +1,45 @@
-use super::Metadata;
+use super::{
+    load_image_from_bytes, load_lottie_from_bytes, load_lottie_from_str, load_svg_from_str,
+    LottieAudio, LottieImageLayer, LottieMarker, LottieTextLayer, Metadata, VelloAssetError,
+    VelloAssetReport,
+};
+use crate::Theme;
 use bevy::prelude::*;
 use bevy::reflect::TypePath;
+use bevy::utils::HashMap;
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
 use vello::Scene;
+use vello_svg::usvg;
 
 #[derive(Clone)]
 pub enum VectorFile {
     Svg {
         /// A static scene
         scene: Arc<Scene>,
+        /// The parsed SVG, to re-encode with other stroke widths and export
+        tree: Arc<usvg::Tree>,
     },
     Lottie {
         /// The original image encoding
         composition: Arc<velato::Composition>,
+        /// Raster image layers, which velato doesn't render
+        images: Arc<[LottieImageLayer]>,
+        /// Text layers, which velato doesn't render
+        texts: Arc<[LottieTextLayer]>,
+        /// Themes bundled with a dotLottie animation, by id
+        themes: Arc<HashMap<String, Theme>>,
+        /// Named frames and segments
+        markers: Arc<[LottieMarker]>,
+        /// Audio layers, which aren't played
+        audio: Arc<[LottieAudio]>,
+        /// The Lottie JSON, kept for saving when loaded with `keep_source`
+        source: Option<Arc<serde_json::Value>>,
+    },
+    Image {
+        /// A raster image, drawn at its pixel size
+        image: vello::peniko::Image,
     },
 }
 
@@ -22,14 +49,63 @@ pub struct VelloAsset {
     pub local_transform_center: Transform,
     pub width: f32,
     pub height: f32,
+    /// Features encountered on import which render differently in-engine.
+    pub report: VelloAssetReport,
 }
 
 impl VelloAsset {
+    /// Build an asset from an SVG document, e.g. one generated at runtime.
+    pub fn from_svg_str(svg: &str) -> Result<Self, VelloAssetError> {
+        load_svg_from_str(svg)
+    }
+
+    /// Build an asset from raster image bytes (PNG, JPEG or WebP), to mix
+    /// raster and vector content with the same transform and z-ordering.
+    pub fn from_image_bytes(bytes: &[u8]) -> Result<Self, VelloAssetError> {
+        load_image_from_bytes(bytes)
+    }
+
+    /// Build an asset from Lottie JSON bytes, e.g. downloaded at runtime.
+    pub fn from_lottie_slice(bytes: &[u8]) -> Result<Self, VelloAssetError> {
+        load_lottie_from_bytes(bytes)
+    }
+
+    /// Build an asset from a Lottie JSON string.
+    pub fn from_lottie_json(json: &str) -> Result<Self, VelloAssetError> {
+        load_lottie_from_str(json)
+    }
+
+    /// Scale the asset's size and content. The scale is baked into
+    /// [`VelloAsset::local_transform_center`], so entity transforms can stay
+    /// at identity.
+    pub fn bake_scale(&mut self, scale: f32) {
+        self.width *= scale;
+        self.height *= scale;
+        self.local_transform_center.scale /= scale;
+    }
+
+    /// The scale baked into this asset with [`VelloAsset::bake_scale`].
+    pub fn baked_scale(&self) -> Vec2 {
+        self.local_transform_center.scale.truncate().recip()
+    }
+
+    /// Returns the bounding box relative to the entity's origin, placed by
+    /// [`VelloAsset::local_transform_center`].
+    pub fn local_rect(&self) -> Rect {
+        // Content spans from (0, -height) to (width, 0) before scaling
+        let content_size = Vec2::new(self.width, self.height) / self.baked_scale();
+        let to_local = self.local_transform_center.compute_matrix().inverse();
+        let a = to_local.transform_point3(Vec3::new(0.0, -content_size.y, 0.0));
+        let b = to_local.transform_point3(Vec3::new(content_size.x, 0.0, 0.0));
+        Rect::from_corners(a.truncate(), b.truncate())
+    }
+
     /// Returns the bounding box in world space
     pub fn bb_in_world_space(&self, gtransform: &GlobalTransform) -> Rect {
         // Convert local coordinates to world coordinates
-        let local_min = Vec3::new(-self.width / 2.0, -self.height / 2.0, 0.0).extend(1.0);
-        let local_max = Vec3::new(self.width / 2.0, self.height / 2.0, 0.0).extend(1.0);
+        let local_rect = self.local_rect();
+        let local_min = local_rect.min.extend(0.0).extend(1.0);
+        let local_max = local_rect.max.extend(0.0).extend(1.0);
 
         let min_world = gtransform.compute_matrix() * local_min;
         let max_world = gtransform.compute_matrix() * local_max;
@@ -54,6 +130,156 @@ impl VelloAsset {
             .map(|(min, max)| Rect { min, max })
     }
 
+    /// The composition of a Lottie, if this asset is a Lottie.
+    fn composition(&self) -> Option<&velato::Composition> {
+        match &self.data {
+            VectorFile::Lottie { composition, .. } => Some(composition.as_ref()),
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => None,
+        }
+    }
+
+    /// The frame rate of a Lottie, in frames per second.
+    pub fn frame_rate(&self) -> Option<f64> {
+        self.composition().map(|composition| composition.frame_rate)
+    }
+
+    /// The frames of a Lottie, from its in-point to its out-point.
+    pub fn frames(&self) -> Option<Range<f64>> {
+        self.composition()
+            .map(|composition| composition.frames.clone())
+    }
+
+    /// How long a Lottie plays for at normal speed, or `None` if its frame
+    /// rate is zero or not finite.
+    pub fn duration(&self) -> Option<Duration> {
+        let composition = self.composition()?;
+        let frames = composition.frames.end - composition.frames.start;
+        Duration::try_from_secs_f64((frames / composition.frame_rate).max(0.0)).ok()
+    }
+
+    /// The markers of a Lottie, naming frames and segments.
+    pub fn markers(&self) -> &[LottieMarker] {
+        match &self.data {
+            VectorFile::Lottie { markers, .. } => markers,
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => &[],
+        }
+    }
+
+    /// A marker of a Lottie, by name.
+    pub fn marker(&self, name: &str) -> Option<&LottieMarker> {
+        self.markers().iter().find(|marker| marker.name == name)
+    }
+
+    /// The audio layers of a Lottie, to play alongside it.
+    pub fn audio(&self) -> &[LottieAudio] {
+        match &self.data {
+            VectorFile::Lottie { audio, .. } => audio,
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => &[],
+        }
+    }
+
+    /// The names of a Lottie's top-level layers, from top to bottom.
+    pub fn layer_names(&self) -> impl Iterator<Item = &str> {
+        self.composition()
+            .into_iter()
+            .flat_map(|composition| composition.layers.iter())
+            .map(|layer| layer.name.as_str())
+    }
+
+    /// The composition of a Lottie, to edit at runtime, e.g. from an in-game
+    /// editor. Edit assets through `Assets::get_mut`, which marks them
+    /// changed so the next frame renders the edit.
+    ///
+    /// A composition shared with identical assets is copied first, so other
+    /// assets are unaffected.
+    pub fn composition_mut(&mut self) -> Option<&mut velato::Composition> {
+        match &mut self.data {
+            VectorFile::Lottie { composition, .. } => Some(Arc::make_mut(composition)),
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => None,
+        }
+    }
+
+    /// A layer of a Lottie by name, to edit at runtime, e.g. to replace the
+    /// shapes in its content. Top-level layers are searched first, then the
+    /// layers of precompositions. See [`VelloAsset::composition_mut`].
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut velato::model::Layer> {
+        let composition = self.composition_mut()?;
+        let precomp_layers = composition.assets.values_mut().flatten();
+        composition
+            .layers
+            .iter_mut()
+            .chain(precomp_layers)
+            .find(|layer| layer.name == name)
+    }
+
+    /// The Lottie JSON the asset was loaded from, if it was loaded with
+    /// [`keep_source`](crate::assets::VelloAssetLoaderSettings::keep_source).
+    pub fn source(&self) -> Option<&serde_json::Value> {
+        match &self.data {
+            VectorFile::Lottie { source, .. } => source.as_deref(),
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => None,
+        }
+    }
+
+    /// The Lottie JSON the asset was loaded from, to edit before saving it
+    /// with [`LottieSaver`](crate::assets::LottieSaver). Edits aren't rendered
+    /// until the asset is rebuilt from the JSON.
+    pub fn source_mut(&mut self) -> Option<&mut serde_json::Value> {
+        match &mut self.data {
+            VectorFile::Lottie { source, .. } => source.as_mut().map(Arc::make_mut),
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => None,
+        }
+    }
+
+    /// Hide every layer of a Lottie with the given name, returning whether
+    /// any was found. Hidden layers stay in the composition, so layers
+    /// parented to them keep their transforms, and are marked hidden in the
+    /// [source](VelloAsset::source). See [`VelloAsset::composition_mut`].
+    pub fn hide_layer(&mut self, name: &str) -> bool {
+        if let Some(source) = self.source_mut() {
+            let hide = |layers: &mut serde_json::Value| {
+                let layers = layers.as_array_mut().into_iter().flatten();
+                for layer in layers.filter(|layer| layer["nm"].as_str() == Some(name)) {
+                    layer["hd"] = serde_json::Value::Bool(true);
+                }
+            };
+            hide(&mut source["layers"]);
+            for asset in source["assets"].as_array_mut().into_iter().flatten() {
+                hide(&mut asset["layers"]);
+            }
+        }
+        let Some(composition) = self.composition_mut() else {
+            return false;
+        };
+        let precomp_layers = composition.assets.values_mut().flatten();
+        let mut hidden = false;
+        for layer in composition.layers.iter_mut().chain(precomp_layers) {
+            if layer.name == name {
+                layer.frames = 0.0..0.0;
+                hidden = true;
+            }
+        }
+        hidden
+    }
+
+    /// The ids of the themes bundled with a dotLottie animation.
+    pub fn theme_ids(&self) -> impl Iterator<Item = &str> {
+        let themes = match &self.data {
+            VectorFile::Lottie { themes, .. } => Some(themes.keys()),
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => None,
+        };
+        themes.into_iter().flatten().map(String::as_str)
+    }
+
+    /// A theme bundled with a dotLottie animation, by id. Add it to the
+    /// entity as a [`Theme`] to apply it.
+    pub fn theme(&self, id: &str) -> Option<&Theme> {
+        match &self.data {
+            VectorFile::Lottie { themes, .. } => themes.get(id),
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => None,
+        }
+    }
+
     /// Gets the lottie metadata (if vector is a lottie), an object used for
     /// inspecting this vector's layers and shapes
     pub fn metadata(&self) -> Option<Metadata> {
@@ -1,79 +1,575 @@
-use crate::assets::parser::{load_lottie_from_bytes, load_svg_from_bytes};
-use crate::VelloAsset;
+use crate::assets::dotlottie::read_dotlottie;
+use crate::assets::parser::{
+    base_font_db, decompress_if_compressed, encode_svg, encode_svg_incrementally, font_db_with,
+    gunzip_if_compressed, load_image_from_bytes, load_lottie_with_resources, parse_lottie,
+    parse_svg, LottieResources, EMPTY_FONT_DB,
+};
+use crate::assets::precomps::lottie_precomps;
+use crate::assets::progress::LoadSteps;
+use crate::assets::svg_fragments::svg_fragments;
+use crate::assets::svg_resources::{
+    decode_data_uri, embed_images, font_faces, image_hrefs, is_relative,
+};
+use crate::assets::svg_styles::style_svg;
+use crate::assets::{
+    external_image_paths, font_families, lottie_fonts, svg_diagnostics, LottieAudio,
+    LottieCompatibility, VelloAssetLoaderSettings, VelloLoadFailures, VelloLoadProgress,
+    VelloLottieFontMap, VelloParseMode, VelloSvgFontMap,
+};
+use crate::{Theme, VectorFile, VelloAsset, VelloFont};
 use bevy::asset::io::Reader;
-use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::asset::{AssetLoader, AssetPath, AsyncReadExt, LoadContext};
 use bevy::prelude::*;
+use bevy::tasks::AsyncComputeTaskPool;
 use bevy::utils::thiserror::{self, Error};
-use bevy::utils::BoxedFuture;
-#[derive(Default)]
-pub struct VelloAssetLoader;
+use bevy::utils::{BoxedFuture, HashMap};
+use std::borrow::Cow;
+use std::io::Read;
+use std::sync::Arc;
+use vello::glyph::skrifa::FontRef;
+pub struct VelloAssetLoader {
+    progress: VelloLoadProgress,
+    fonts: VelloSvgFontMap,
+    lottie_fonts: VelloLottieFontMap,
+    failures: VelloLoadFailures,
+}
+
+impl FromWorld for VelloAssetLoader {
+    fn from_world(world: &mut World) -> Self {
+        let progress = world
+            .get_resource_or_insert_with(VelloLoadProgress::default)
+            .clone();
+        let fonts = world
+            .get_resource_or_insert_with(VelloSvgFontMap::default)
+            .clone();
+        let lottie_fonts = world
+            .get_resource_or_insert_with(VelloLottieFontMap::default)
+            .clone();
+        let failures = world
+            .get_resource_or_insert_with(VelloLoadFailures::default)
+            .clone();
+        Self {
+            progress,
+            fonts,
+            lottie_fonts,
+            failures,
+        }
+    }
+}
 
+/// An error loading or parsing a vector asset.
 #[non_exhaustive]
 #[derive(Debug, Error)]
-pub enum VectorLoaderError {
+pub enum VelloAssetError {
     #[error("Could not load vector: {0}")]
     Io(#[from] std::io::Error),
     #[error("Could not parse vector: {0}")]
     Parse(String),
-    #[error("Could not parse shader: {0}")]
+    #[error("Could not decode vector as UTF-8: {0}")]
     FromUtf8(#[from] std::string::FromUtf8Error),
-    #[error("Could not parse shader: {0}")]
+    #[error("Could not decode vector as UTF-8: {0}")]
     FromStrUtf8(#[from] std::str::Utf8Error),
-    #[error("Could not parse shader: {0}")]
+    #[error("Could not parse SVG: {0}")]
     Usvg(#[from] vello_svg::usvg::Error),
+    #[error("Unsupported features in strict mode: {0}")]
+    Strict(String),
 }
 
 impl AssetLoader for VelloAssetLoader {
     type Asset = VelloAsset;
 
-    type Settings = ();
+    type Settings = VelloAssetLoaderSettings;
 
-    type Error = VectorLoaderError;
+    type Error = VelloAssetError;
 
     fn load<'a>(
         &'a self,
         reader: &'a mut Reader,
-        _settings: &'a Self::Settings,
+        settings: &'a Self::Settings,
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
-            let mut bytes = Vec::new();
-            reader.read_to_end(&mut bytes).await?;
-            let path = load_context.path().to_owned();
-            let ext = path
-                .extension()
-                .and_then(std::ffi::OsStr::to_str)
-                .ok_or(VectorLoaderError::Parse("Invalid extension".to_string()))?
-                .to_owned();
-
-            debug!("parsing {}...", load_context.path().display());
-            match ext.as_str() {
-                "svg" => {
-                    let vello_vector = load_svg_from_bytes(&bytes)?;
-                    info!(
-                        path = format!("{}", load_context.path().display()),
-                        size = format!("{:?}", (vello_vector.width, vello_vector.height)),
-                        "finished parsing svg asset"
-                    );
-                    Ok(vello_vector)
+            // Failures are reported as events, so a malformed file doesn't
+            // go unnoticed
+            let asset_path = load_context.asset_path().clone_owned();
+            let result = self.load_vector(reader, settings, load_context).await;
+            if let Err(err) = &result {
+                self.failures.push(&asset_path, err);
+            }
+            result
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[
+            "svg",
+            "svgz",
+            "json",
+            "json.br",
+            "json.zst",
+            "lottie",
+            "vello.png",
+            "vello.jpg",
+            "vello.jpeg",
+            "vello.webp",
+        ]
+    }
+}
+
+impl VelloAssetLoader {
+    async fn load_vector(
+        &self,
+        reader: &mut Reader<'_>,
+        settings: &VelloAssetLoaderSettings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<VelloAsset, VelloAssetError> {
+        let asset_path = load_context.asset_path().clone_owned();
+        let mut steps = LoadSteps::new(&self.progress, &asset_path);
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        steps.complete();
+        let ext = load_context
+            .path()
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+        // Compressed Lotties (e.g. `.json.br`) are decompressed first, and
+        // their format is decided by the content
+        if let Cow::Owned(decompressed) = decompress_if_compressed(&bytes, ext.as_deref())? {
+            bytes = decompressed;
+        }
+        // Paths from custom asset sources (e.g. pak files or URLs) may not
+        // have a known extension, so the content decides the format
+        let ext = ext
+            .filter(|ext| self.extensions().contains(&ext.as_str()))
+            .or_else(|| sniff_format(&bytes).map(str::to_owned))
+            .ok_or(VelloAssetError::Parse("Unknown file format".to_string()))?;
+
+        let validate = settings.validate;
+        let compatibility = settings.lottie_compatibility;
+        debug!("parsing {asset_path}...");
+        match ext.as_str() {
+            "svg" | "svgz" => {
+                let bytes = gunzip_if_compressed(&bytes)?.into_owned();
+                // Fonts used by text become dependencies of the SVG, and
+                // are used to shape its text
+                let svg_str = std::str::from_utf8(&bytes)?;
+                let (families, faces, hrefs) = (
+                    font_families(svg_str),
+                    font_faces(svg_str),
+                    image_hrefs(svg_str),
+                );
+                steps.add(families.len() + faces.len() + hrefs.len());
+                // Fonts which can't be read fall back to the base fonts
+                let mut fonts = vec![];
+                for family in families {
+                    if let Some(path) = self.fonts.get(&family) {
+                        let _: Handle<VelloFont> = load_context.load(&path);
+                        match load_context.read_asset_bytes(path).await {
+                            Ok(font_data) => fonts.push((family, font_data)),
+                            Err(err) => warn!("could not read SVG font '{family}': {err}"),
+                        }
+                    }
+                    steps.complete();
+                }
+                // External images and `@font-face` fonts are relative to the
+                // SVG, and are read from the same asset source, so they
+                // become dependencies and hot reload with it
+                for (family, url) in faces {
+                    let font_data = if is_relative(&url) {
+                        match asset_path.resolve_embed(&url) {
+                            Ok(resolved) => load_context
+                                .read_asset_bytes(resolved)
+                                .await
+                                .map_err(|err| err.to_string()),
+                            Err(err) => Err(err.to_string()),
+                        }
+                    } else {
+                        decode_data_uri(&url).ok_or_else(|| "unsupported URL".to_string())
+                    };
+                    match font_data {
+                        Ok(font_data) => fonts.push((family, font_data)),
+                        Err(err) => warn!("could not read SVG font '{url}': {err}"),
+                    }
+                    steps.complete();
+                }
+                let mut images = HashMap::new();
+                for href in hrefs {
+                    let image = match asset_path.resolve_embed(&href) {
+                        Ok(resolved) => load_context
+                            .read_asset_bytes(resolved)
+                            .await
+                            .map_err(|err| err.to_string()),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    match image {
+                        Ok(image) => {
+                            images.insert(href, image);
+                        }
+                        Err(err) => warn!("could not read SVG image '{href}': {err}"),
+                    }
+                    steps.complete();
+                }
+                let bytes = embed_images(svg_str, &images).into_owned().into_bytes();
+                // Parsing and encoding run on the compute pool so bulk
+                // loads don't stall the asset thread
+                let within_parse = steps.within_step();
+                let task_settings = settings.clone();
+                let (mut vello_vector, fragments) = AsyncComputeTaskPool::get()
+                    .spawn(async move {
+                        let system_fonts = task_settings.system_fonts;
+                        let extended = (task_settings.render_text && !fonts.is_empty())
+                            .then(|| font_db_with(fonts, system_fonts));
+                        let fontdb = if task_settings.render_text {
+                            extended.as_deref().unwrap_or(base_font_db(system_fonts))
+                        } else {
+                            &EMPTY_FONT_DB
+                        };
+                        let options = task_settings.usvg_options();
+                        let svg = style_svg(
+                            std::str::from_utf8(&bytes)?,
+                            task_settings.svg_styles,
+                            &task_settings.style_sheet,
+                        );
+                        let diagnostics = if task_settings.validate {
+                            svg_diagnostics(&svg)
+                        } else {
+                            vec![]
+                        };
+                        // Top-level symbols and groups with ids are
+                        // labeled sub-assets, e.g. `icons.svg#play`
+                        let mut fragments = vec![];
+                        for (id, fragment) in svg_fragments(&svg) {
+                            match parse_svg(fragment.as_bytes(), &options, fontdb) {
+                                Ok(tree) => fragments.push((id, encode_svg(tree))),
+                                Err(err) => warn!("skipping SVG fragment '{id}': {err}"),
+                            }
+                        }
+                        // Large SVGs are encoded in chunks, so they don't
+                        // hold a task pool thread for hundreds of milliseconds
+                        let mut vello_vector =
+                            encode_svg_incrementally(&svg, &options, fontdb, within_parse).await?;
+                        vello_vector.report = diagnostics.into();
+                        Ok::<_, VelloAssetError>((vello_vector, fragments))
+                    })
+                    .await?;
+                for (id, mut fragment) in fragments {
+                    fragment.bake_scale(settings.scale);
+                    settings.origin.apply(&mut fragment);
+                    load_context.add_labeled_asset(id, fragment);
+                }
+                vello_vector.bake_scale(settings.scale);
+                settings.origin.apply(&mut vello_vector);
+                report_diagnostics(&vello_vector, settings, &asset_path)?;
+                steps.complete();
+                info!(
+                    path = format!("{asset_path}"),
+                    size = format!("{:?}", (vello_vector.width, vello_vector.height)),
+                    "finished parsing svg asset"
+                );
+                Ok(vello_vector)
+            }
+            "json" => {
+                // The JSON is parsed once, on the compute pool, and shared by
+                // every step which reads it. Gzipped JSON is found by sniffing
+                let root = AsyncComputeTaskPool::get()
+                    .spawn(async move { parse_lottie(&gunzip_if_compressed(&bytes)?) })
+                    .await?;
+                // Images which aren't embedded are relative to the file, and
+                // are read from the same asset source (e.g. embedded or HTTP)
+                let image_paths = external_image_paths(&root);
+                steps.add(image_paths.len());
+                let mut resources = LottieResources {
+                    fonts: self
+                        .read_lottie_fonts(&root, load_context, &mut steps)
+                        .await,
+                    system_fonts: settings.system_fonts,
+                    ..default()
+                };
+                for image_path in image_paths {
+                    let image = match asset_path.resolve_embed(&image_path) {
+                        Ok(resolved) => load_context
+                            .read_asset_bytes(resolved)
+                            .await
+                            .map_err(|err| err.to_string()),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    match image {
+                        Ok(image) => {
+                            resources.images.insert(image_path, image);
+                        }
+                        Err(err) => warn!("could not read Lottie image '{image_path}': {err}"),
+                    }
+                    steps.complete();
+                }
+                // Large compositions are parsed on the compute pool so
+                // they don't delay other loads
+                let source = settings.keep_source.then(|| root.clone());
+                let (vello_vector, precomps) = AsyncComputeTaskPool::get()
+                    .spawn(async move {
+                        load_lottie_and_precomps(root, &resources, validate, compatibility)
+                    })
+                    .await?;
+                // Precompositions are labeled sub-assets, e.g.
+                // `buttons.json#comp_0`
+                for (id, precomp) in precomps {
+                    let precomp = finish_lottie(precomp, settings, &asset_path)?;
+                    load_context.add_labeled_asset(id, precomp);
+                }
+                let mut vello_vector = finish_lottie(vello_vector, settings, &asset_path)?;
+                attach_source(&mut vello_vector, source);
+                steps.complete();
+                info!(
+                    path = format!("{asset_path}"),
+                    size = format!("{:?}", (vello_vector.width, vello_vector.height)),
+                    "finished parsing lottie json asset"
+                );
+                Ok(vello_vector)
+            }
+            "lottie" => {
+                // Every animation is a labeled sub-asset, and the active
+                // animation is the root asset
+                let dotlottie = read_dotlottie(&bytes)?;
+                // Each animation is parsed as its own step
+                steps.add(dotlottie.animations.len().saturating_sub(1));
+                let mut active = None;
+                let images = dotlottie.images;
+                for (id, json) in dotlottie.animations {
+                    let root = AsyncComputeTaskPool::get()
+                        .spawn(async move { parse_lottie(&json) })
+                        .await?;
+                    let resources = LottieResources {
+                        images: images.clone(),
+                        fonts: self
+                            .read_lottie_fonts(&root, load_context, &mut steps)
+                            .await,
+                        system_fonts: settings.system_fonts,
+                    };
+                    // Bundled themes recolor the layers using their slots
+                    let animation_themes: HashMap<String, Theme> = dotlottie
+                        .themes
+                        .iter()
+                        .filter_map(|theme| Some((theme.id.clone(), theme.to_theme(&id, &root)?)))
+                        .collect();
+                    let source = settings.keep_source.then(|| root.clone());
+                    let (vello_vector, precomps) = AsyncComputeTaskPool::get()
+                        .spawn(async move {
+                            load_lottie_and_precomps(root, &resources, validate, compatibility)
+                        })
+                        .await?;
+                    // Precompositions are labeled by animation, e.g.
+                    // `buttons.lottie#hover/comp_0`
+                    for (precomp_id, precomp) in precomps {
+                        let precomp = finish_lottie(precomp, settings, &asset_path)?;
+                        load_context.add_labeled_asset(format!("{id}/{precomp_id}"), precomp);
+                    }
+                    let mut vello_vector = finish_lottie(vello_vector, settings, &asset_path)?;
+                    attach_source(&mut vello_vector, source);
+                    if let VectorFile::Lottie { themes, .. } = &mut vello_vector.data {
+                        *themes = Arc::new(animation_themes);
+                    }
+                    if id == dotlottie.active {
+                        active.replace(vello_vector.clone());
+                    }
+                    load_context.add_labeled_asset(id, vello_vector);
+                    steps.complete();
+                }
+                let vello_vector = active.ok_or(VelloAssetError::Parse(format!(
+                    "Active animation '{}' not found in dotLottie",
+                    dotlottie.active
+                )))?;
+                info!(
+                    path = format!("{asset_path}"),
+                    size = format!("{:?}", (vello_vector.width, vello_vector.height)),
+                    "finished parsing dotLottie asset"
+                );
+                Ok(vello_vector)
+            }
+            "image" => {
+                // Raster images share the loader's scale and origin settings
+                let mut vello_vector = AsyncComputeTaskPool::get()
+                    .spawn(async move { load_image_from_bytes(&bytes) })
+                    .await?;
+                vello_vector.bake_scale(settings.scale);
+                settings.origin.apply(&mut vello_vector);
+                steps.complete();
+                info!(
+                    path = format!("{asset_path}"),
+                    size = format!("{:?}", (vello_vector.width, vello_vector.height)),
+                    "finished decoding image asset"
+                );
+                Ok(vello_vector)
+            }
+            _ => Err(VelloAssetError::Parse("Unknown file extension".to_string())),
+        }
+    }
+
+    /// Read the fonts mapped in [`VelloLottieFontMap`] for Lottie text, which
+    /// become dependencies of the Lottie. Each font is a load step. Fonts
+    /// which can't be read or parsed are skipped, and their text layers are
+    /// reported as missing a font.
+    async fn read_lottie_fonts(
+        &self,
+        json: &serde_json::Value,
+        load_context: &mut LoadContext<'_>,
+        steps: &mut LoadSteps,
+    ) -> HashMap<String, VelloFont> {
+        let mut fonts = HashMap::default();
+        let names = lottie_fonts(json);
+        steps.add(names.len());
+        for (name, _) in names {
+            if let Some(path) = self.lottie_fonts.get(&name) {
+                let _: Handle<VelloFont> = load_context.load(&path);
+                match load_context.read_asset_bytes(&path).await {
+                    // Malformed fonts would panic when rendering text
+                    Ok(font_data) => match FontRef::new(&font_data) {
+                        Ok(_) => {
+                            fonts.insert(name, VelloFont::new(font_data));
+                        }
+                        Err(err) => warn!("could not parse Lottie font '{path}': {err}"),
+                    },
+                    Err(err) => warn!("could not read Lottie font '{path}': {err}"),
                 }
-                "json" => {
-                    let vello_vector = load_lottie_from_bytes(&bytes)?;
-                    info!(
-                        path = format!("{}", load_context.path().display()),
-                        size = format!("{:?}", (vello_vector.width, vello_vector.height)),
-                        "finished parsing lottie json asset"
-                    );
-                    Ok(vello_vector)
+            }
+            steps.complete();
+        }
+        fonts
+    }
+}
+
+/// Guess the format of a file from its content, as an extension.
+fn sniff_format(bytes: &[u8]) -> Option<&'static str> {
+    const ZIP_MAGIC: [u8; 4] = [0x50, 0x4b, 0x03, 0x04];
+    const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+    const PNG_MAGIC: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    const JPEG_MAGIC: [u8; 3] = [0xff, 0xd8, 0xff];
+    if bytes.starts_with(&ZIP_MAGIC) {
+        return Some("lottie");
+    }
+    if bytes.starts_with(&GZIP_MAGIC) {
+        // Lottie JSON is gzipped as often as SVG, so the start of the
+        // decompressed content decides
+        let mut head = Vec::new();
+        let _ = flate2::read::GzDecoder::new(bytes)
+            .take(SNIFF_BYTES)
+            .read_to_end(&mut head);
+        return match sniff_text(&head)? {
+            "svg" => Some("svgz"),
+            format => Some(format),
+        };
+    }
+    // Raster images, e.g. `icon.vello.png`, are detected by content
+    let webp = bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(&b"WEBP"[..]);
+    if bytes.starts_with(&PNG_MAGIC) || bytes.starts_with(&JPEG_MAGIC) || webp {
+        return Some("image");
+    }
+    sniff_text(bytes)
+}
+
+/// How much of a gzipped file is decompressed to guess its format.
+const SNIFF_BYTES: u64 = 512;
+
+/// Guess the format of a text file from its first character.
+fn sniff_text(bytes: &[u8]) -> Option<&'static str> {
+    let text = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    match text.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'{') => Some("json"),
+        Some(b'<') => Some("svg"),
+        _ => None,
+    }
+}
+
+/// Parse a Lottie, and each of its precompositions as a standalone Lottie.
+/// Precompositions aren't validated, since the Lottie's report covers them.
+fn load_lottie_and_precomps(
+    root: serde_json::Value,
+    resources: &LottieResources,
+    validate: bool,
+    compatibility: LottieCompatibility,
+) -> Result<(VelloAsset, Vec<(String, VelloAsset)>), VelloAssetError> {
+    let precomps = lottie_precomps(&root);
+    let vello_vector = load_lottie_with_resources(root, resources, validate, compatibility)?;
+    let precomps = precomps
+        .into_iter()
+        .filter_map(|(id, json)| {
+            match load_lottie_with_resources(json, resources, false, compatibility) {
+                Ok(precomp) => Some((id, precomp)),
+                Err(err) => {
+                    warn!("skipping Lottie precomposition '{id}': {err}");
+                    None
                 }
-                _ => Err(VectorLoaderError::Parse(
-                    "Unknown file extension".to_string(),
-                )),
             }
         })
+        .collect();
+    Ok((vello_vector, precomps))
+}
+
+/// Apply loader settings to a parsed Lottie, and report its diagnostics.
+fn finish_lottie(
+    mut vello_vector: VelloAsset,
+    settings: &VelloAssetLoaderSettings,
+    path: &AssetPath,
+) -> Result<VelloAsset, VelloAssetError> {
+    vello_vector.bake_scale(settings.scale);
+    settings.origin.apply(&mut vello_vector);
+    // Audio is played by the game, so its paths are resolved like images
+    if let VectorFile::Lottie { audio, .. } = &mut vello_vector.data {
+        let resolved: Vec<LottieAudio> = audio
+            .iter()
+            .cloned()
+            .map(|mut clip| {
+                if let Ok(resolved) = path.resolve_embed(&clip.path) {
+                    clip.path = resolved.to_string();
+                }
+                clip
+            })
+            .collect();
+        *audio = resolved.into();
+    }
+    if !settings.keep_layer_names {
+        if let VectorFile::Lottie { composition, .. } = &mut vello_vector.data {
+            // Compositions shared with identical assets are copied first
+            let composition = Arc::make_mut(composition);
+            let precomp_layers = composition.assets.values_mut().flatten();
+            for layer in composition.layers.iter_mut().chain(precomp_layers) {
+                layer.name.clear();
+            }
+        }
     }
+    report_diagnostics(&vello_vector, settings, path)?;
+    Ok(vello_vector)
+}
 
-    fn extensions(&self) -> &[&str] {
-        &["svg", "json"]
+/// Keep the Lottie JSON with the asset, for saving with
+/// [`LottieSaver`](crate::assets::LottieSaver).
+fn attach_source(vello_vector: &mut VelloAsset, json: Option<serde_json::Value>) {
+    if let VectorFile::Lottie { source, .. } = &mut vello_vector.data {
+        *source = json.map(Arc::new);
+    }
+}
+
+/// Log an asset's report once, or fail the load in strict mode.
+fn report_diagnostics(
+    vello_vector: &VelloAsset,
+    settings: &VelloAssetLoaderSettings,
+    path: &AssetPath,
+) -> Result<(), VelloAssetError> {
+    let report = &vello_vector.report;
+    if report.is_empty() {
+        return Ok(());
+    }
+    if settings.parse_mode == VelloParseMode::Strict {
+        let diagnostics: Vec<String> = report
+            .diagnostics()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        return Err(VelloAssetError::Strict(diagnostics.join("; ")));
     }
+    warn!(
+        path = format!("{path}"),
+        "{} feature(s) render differently in-engine:\n{report}",
+        report.diagnostics().len()
+    );
+    Ok(())
 }
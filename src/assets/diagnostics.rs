@@ -0,0 +1,252 @@
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+/// A feature encountered while importing a vector file which renders
+/// differently in-engine than in its authoring tool.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportDiagnostic {
+    /// An expression outside the supported subset, which is not evaluated.
+    /// The property's static value is used instead.
+    UnsupportedExpression { layer: String, expression: String },
+    /// A layer type which is not rendered.
+    UnsupportedLayer { layer: String, kind: &'static str },
+    /// Layer effects, which are ignored.
+    Effects { layer: String },
+    /// Layer styles (e.g. drop shadows), which are ignored.
+    LayerStyles { layer: String },
+    /// Merge path shapes, which are ignored.
+    MergePaths { layer: String },
+    /// A 3D layer, which is rendered flat.
+    ThreeDimensional { layer: String },
+    /// A layer which extends past the composition's frames, and is clipped.
+    ClippedLayer { layer: String },
+    /// An image layer whose image could not be found or decoded.
+    MissingImage { layer: String },
+    /// A text layer whose font is neither mapped in
+    /// [`VelloLottieFontMap`](crate::assets::VelloLottieFontMap) nor
+    /// installed.
+    MissingFont { layer: String, font: String },
+    /// A layer using a luma matte, which is rendered as an alpha matte.
+    LumaMatte { layer: String },
+    /// An SVG element with a filter (e.g. a blur), which is ignored.
+    SvgFilter { element: String },
+}
+
+impl fmt::Display for ImportDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportDiagnostic::UnsupportedExpression { layer, expression } => {
+                write!(
+                    f,
+                    "layer '{layer}' has an unsupported expression, which is not evaluated: {expression}"
+                )
+            }
+            ImportDiagnostic::UnsupportedLayer { layer, kind } => {
+                write!(
+                    f,
+                    "layer '{layer}' is a {kind} layer, which is not rendered"
+                )
+            }
+            ImportDiagnostic::Effects { layer } => {
+                write!(f, "layer '{layer}' has effects, which are ignored")
+            }
+            ImportDiagnostic::LayerStyles { layer } => {
+                write!(f, "layer '{layer}' has layer styles, which are ignored")
+            }
+            ImportDiagnostic::MergePaths { layer } => {
+                write!(f, "layer '{layer}' has merge paths, which are ignored")
+            }
+            ImportDiagnostic::ThreeDimensional { layer } => {
+                write!(f, "layer '{layer}' is 3D, and is rendered flat")
+            }
+            ImportDiagnostic::ClippedLayer { layer } => {
+                write!(
+                    f,
+                    "layer '{layer}' extends past the composition, and is clipped"
+                )
+            }
+            ImportDiagnostic::MissingImage { layer } => {
+                write!(
+                    f,
+                    "layer '{layer}' has an image which could not be loaded, and is not rendered"
+                )
+            }
+            ImportDiagnostic::MissingFont { layer, font } => {
+                write!(
+                    f,
+                    "layer '{layer}' uses font '{font}', which was not found, and is not rendered"
+                )
+            }
+            ImportDiagnostic::LumaMatte { layer } => {
+                write!(
+                    f,
+                    "layer '{layer}' uses a luma matte, which is rendered as an alpha matte"
+                )
+            }
+            ImportDiagnostic::SvgFilter { element } => {
+                write!(f, "element '{element}' has a filter, which is ignored")
+            }
+        }
+    }
+}
+
+/// The features of an asset which render differently in-engine, collected
+/// when it loads. Reports are logged once on load, and are empty for assets
+/// loaded with [`validate`](crate::assets::VelloAssetLoaderSettings::validate)
+/// turned off, except for resources which failed to load.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VelloAssetReport {
+    diagnostics: Arc<[ImportDiagnostic]>,
+}
+
+impl VelloAssetReport {
+    /// The features found, in the order they were encountered.
+    pub fn diagnostics(&self) -> &[ImportDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Whether the asset renders as authored, as far as the validation pass
+    /// can tell.
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl From<Vec<ImportDiagnostic>> for VelloAssetReport {
+    fn from(diagnostics: Vec<ImportDiagnostic>) -> Self {
+        Self {
+            diagnostics: diagnostics.into(),
+        }
+    }
+}
+
+impl fmt::Display for VelloAssetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "- {diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Inspect Lottie JSON for features which velato does not support.
+pub(crate) fn lottie_diagnostics(root: &Value) -> Vec<ImportDiagnostic> {
+    let mut diagnostics = vec![];
+
+    let (start, end) = (root["ip"].as_f64(), root["op"].as_f64());
+    let precomp_layers = root["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| asset["layers"].as_array())
+        .flatten()
+        .map(|layer| (layer, true));
+    let layers = root["layers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|layer| (layer, false))
+        .chain(precomp_layers);
+    for (layer, in_precomp) in layers {
+        let name = layer["nm"].as_str().unwrap_or_default().to_owned();
+        let kind = match layer["ty"].as_u64() {
+            // Top-level image and text layers are drawn alongside the
+            // composition
+            Some(2) if in_precomp => Some("image"),
+            Some(5) if in_precomp => Some("text"),
+            Some(6) => Some("audio"),
+            Some(13) => Some("camera"),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            diagnostics.push(ImportDiagnostic::UnsupportedLayer {
+                layer: name.clone(),
+                kind,
+            });
+        }
+        if has_items(&layer["ef"]) {
+            diagnostics.push(ImportDiagnostic::Effects {
+                layer: name.clone(),
+            });
+        }
+        if has_items(&layer["sy"]) {
+            diagnostics.push(ImportDiagnostic::LayerStyles {
+                layer: name.clone(),
+            });
+        }
+        if has_merge_paths(&layer["shapes"]) {
+            diagnostics.push(ImportDiagnostic::MergePaths {
+                layer: name.clone(),
+            });
+        }
+        if matches!(layer["tt"].as_u64(), Some(3 | 4)) {
+            diagnostics.push(ImportDiagnostic::LumaMatte {
+                layer: name.clone(),
+            });
+        }
+        if layer["ddd"].as_u64() == Some(1) {
+            diagnostics.push(ImportDiagnostic::ThreeDimensional {
+                layer: name.clone(),
+            });
+        }
+        let clipped_start = layer["ip"]
+            .as_f64()
+            .zip(start)
+            .is_some_and(|(ip, s)| ip < s);
+        let clipped_end = layer["op"].as_f64().zip(end).is_some_and(|(op, e)| op > e);
+        if clipped_start || clipped_end {
+            diagnostics.push(ImportDiagnostic::ClippedLayer { layer: name });
+        }
+    }
+
+    diagnostics
+}
+
+/// Inspect an SVG document for features which aren't rendered.
+pub(crate) fn svg_diagnostics(svg: &str) -> Vec<ImportDiagnostic> {
+    let Ok(document) = roxmltree::Document::parse(svg) else {
+        return vec![];
+    };
+    document
+        .descendants()
+        .filter(|node| node.is_element())
+        .filter(|node| {
+            let attribute = node
+                .attribute("filter")
+                .is_some_and(|filter| filter.trim() != "none");
+            let style = node.attribute("style").is_some_and(|style| {
+                style.split(';').any(|declaration| {
+                    declaration
+                        .split_once(':')
+                        .is_some_and(|(property, value)| {
+                            property.trim() == "filter" && value.trim() != "none"
+                        })
+                })
+            });
+            attribute || style
+        })
+        .map(|node| ImportDiagnostic::SvgFilter {
+            element: node
+                .attribute("id")
+                .unwrap_or(node.tag_name().name())
+                .to_owned(),
+        })
+        .collect()
+}
+
+fn has_items(value: &Value) -> bool {
+    value.as_array().is_some_and(|items| !items.is_empty())
+}
+
+fn has_merge_paths(shapes: &Value) -> bool {
+    shapes
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|shape| shape["ty"].as_str() == Some("mm") || has_merge_paths(&shape["it"]))
+}
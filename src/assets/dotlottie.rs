@@ -0,0 +1,226 @@
+use super::asset_loader::VelloAssetError;
+use crate::Theme;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::{Cursor, Read};
+
+#[derive(Deserialize)]
+struct Manifest {
+    animations: Vec<ManifestAnimation>,
+    #[serde(default, rename = "activeAnimationId")]
+    active_animation_id: Option<String>,
+    #[serde(default)]
+    themes: Vec<ManifestTheme>,
+}
+
+#[derive(Deserialize)]
+struct ManifestAnimation {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ManifestTheme {
+    id: String,
+    /// The animations the theme applies to, or all of them.
+    #[serde(default)]
+    animations: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct ThemeFile {
+    #[serde(default)]
+    rules: Vec<ThemeRule>,
+}
+
+#[derive(Deserialize)]
+struct ThemeRule {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    animations: Option<Vec<String>>,
+}
+
+/// A theme bundled in a dotLottie archive, overriding the colors of slots.
+pub(crate) struct DotLottieTheme {
+    pub id: String,
+    animations: Option<Vec<String>>,
+    /// Slot colors, and the animations they apply to.
+    colors: Vec<(String, Color, Option<Vec<String>>)>,
+}
+
+impl DotLottieTheme {
+    /// Build a [`Theme`] for an animation, recoloring the layers which use
+    /// the theme's slots. Returns `None` if the theme doesn't apply to the
+    /// animation.
+    pub fn to_theme(&self, animation: &str, root: &Value) -> Option<Theme> {
+        let applies = |animations: &Option<Vec<String>>| {
+            animations.as_ref().map_or(true, |animations| {
+                animations.iter().any(|id| id == animation)
+            })
+        };
+        if !applies(&self.animations) {
+            return None;
+        }
+        let slot_layers = slot_layers(root);
+        let mut theme = Theme::new();
+        for (slot, color, animations) in &self.colors {
+            if !applies(animations) {
+                continue;
+            }
+            for layer in slot_layers.get(slot).into_iter().flatten() {
+                theme.edit(layer, *color);
+            }
+        }
+        Some(theme)
+    }
+}
+
+/// The animations of a dotLottie archive.
+pub(crate) struct DotLottie {
+    /// The animation shown by default.
+    pub active: String,
+    /// Animation ids and their Lottie JSON, in manifest order.
+    pub animations: Vec<(String, Vec<u8>)>,
+    /// Images bundled with the animations, keyed by their path in the
+    /// archive.
+    pub images: HashMap<String, Vec<u8>>,
+    /// Themes bundled with the animations.
+    pub themes: Vec<DotLottieTheme>,
+}
+
+/// Read the manifest and animations of a dotLottie (`.lottie`) archive.
+pub(crate) fn read_dotlottie(bytes: &[u8]) -> Result<DotLottie, VelloAssetError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|err| VelloAssetError::Parse(format!("Invalid dotLottie archive: {err}")))?;
+
+    let manifest: Manifest = serde_json::from_slice(&read_file(&mut archive, "manifest.json")?)
+        .map_err(|err| VelloAssetError::Parse(format!("Invalid dotLottie manifest: {err}")))?;
+
+    let mut animations = Vec::with_capacity(manifest.animations.len());
+    for animation in manifest.animations {
+        // Version 1 archives use `animations/`, version 2 uses `a/`
+        let json = read_file(&mut archive, &format!("animations/{}.json", animation.id))
+            .or_else(|_| read_file(&mut archive, &format!("a/{}.json", animation.id)))?;
+        animations.push((animation.id, json));
+    }
+    let active = manifest
+        .active_animation_id
+        .or_else(|| animations.first().map(|(id, _)| id.clone()))
+        .ok_or(VelloAssetError::Parse(
+            "dotLottie archive has no animations".to_string(),
+        ))?;
+
+    // Version 1 archives use `images/`, version 2 uses `i/`
+    let image_names: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.starts_with("images/") || name.starts_with("i/"))
+        .map(ToOwned::to_owned)
+        .collect();
+    let mut images = HashMap::default();
+    for name in image_names {
+        let image = read_file(&mut archive, &name)?;
+        images.insert(name, image);
+    }
+
+    let mut themes = Vec::with_capacity(manifest.themes.len());
+    for theme in manifest.themes {
+        // Version 1 archives use `themes/`, version 2 uses `t/`
+        let file = read_file(&mut archive, &format!("t/{}.json", theme.id))
+            .or_else(|_| read_file(&mut archive, &format!("themes/{}.json", theme.id)))?;
+        let file: ThemeFile = serde_json::from_slice(&file).map_err(|err| {
+            VelloAssetError::Parse(format!("Invalid dotLottie theme '{}': {err}", theme.id))
+        })?;
+        // Only color slots map onto `Theme`
+        let colors = file
+            .rules
+            .into_iter()
+            .filter(|rule| rule.kind.eq_ignore_ascii_case("color"))
+            .filter_map(|rule| Some((rule.id, rule_color(&rule.value)?, rule.animations)))
+            .collect();
+        themes.push(DotLottieTheme {
+            id: theme.id,
+            animations: theme.animations,
+            colors,
+        });
+    }
+
+    Ok(DotLottie {
+        active,
+        animations,
+        images,
+        themes,
+    })
+}
+
+fn read_file(
+    archive: &mut zip::ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<Vec<u8>, VelloAssetError> {
+    let mut file = archive
+        .by_name(name)
+        .map_err(|err| VelloAssetError::Parse(format!("Missing '{name}' in dotLottie: {err}")))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A static color rule value, with channels from 0 to 1.
+fn rule_color(value: &Value) -> Option<Color> {
+    let channels: Vec<f32> = value
+        .as_array()?
+        .iter()
+        .map(|c| c.as_f64().map(|c| c as f32))
+        .collect::<Option<_>>()?;
+    match channels[..] {
+        [r, g, b] => Some(Color::rgb(r, g, b)),
+        [r, g, b, a] => Some(Color::rgba(r, g, b, a)),
+        _ => None,
+    }
+}
+
+/// The layers using each slot id (`sid`) of Lottie JSON.
+fn slot_layers(root: &Value) -> HashMap<String, Vec<String>> {
+    fn collect_slots(value: &Value, layer: &str, slots: &mut HashMap<String, Vec<String>>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(slot) = map.get("sid").and_then(Value::as_str) {
+                    slots
+                        .entry(slot.to_owned())
+                        .or_default()
+                        .push(layer.to_owned());
+                }
+                for value in map.values() {
+                    collect_slots(value, layer, slots);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    collect_slots(item, layer, slots);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut slots = HashMap::default();
+    let precomp_layers = root["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| asset["layers"].as_array());
+    let layers = root["layers"]
+        .as_array()
+        .into_iter()
+        .chain(precomp_layers)
+        .flatten();
+    for layer in layers {
+        let name = layer["nm"].as_str().unwrap_or_default();
+        collect_slots(layer, name, &mut slots);
+    }
+    slots
+}
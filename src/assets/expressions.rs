@@ -0,0 +1,392 @@
+use super::{ImportDiagnostic, DEFAULT_FRAME_RATE};
+use serde_json::{json, Map, Value};
+
+/// The most loop periods baked for a single property.
+const MAX_PERIODS: i64 = 1000;
+
+/// The composition timing expressions are evaluated against.
+#[derive(Clone, Copy)]
+struct Timing {
+    start: f64,
+    end: f64,
+    frame_rate: f64,
+}
+
+/// Bake a supported subset of expressions into keyframes, since velato
+/// doesn't evaluate expressions:
+///
+/// - `loopOut()` and `loopIn()`, with `"cycle"` or `"pingpong"`
+/// - linear time, e.g. `time * 90` or `value + time * 90`
+///
+/// Other expressions are left in place, so the property's static value is
+/// used, and are reported.
+pub(crate) fn bake_expressions(root: &mut Value) -> Vec<ImportDiagnostic> {
+    let timing = Timing {
+        start: root["ip"].as_f64().unwrap_or(0.0),
+        end: root["op"].as_f64().unwrap_or(0.0),
+        frame_rate: root["fr"].as_f64().unwrap_or(DEFAULT_FRAME_RATE),
+    };
+    let mut diagnostics = vec![];
+    if let Some(layers) = root.get_mut("layers").and_then(Value::as_array_mut) {
+        for layer in layers {
+            bake_layer(layer, timing, &mut diagnostics);
+        }
+    }
+    let precomps = root.get_mut("assets").and_then(Value::as_array_mut);
+    for precomp in precomps.into_iter().flatten() {
+        if let Some(layers) = precomp.get_mut("layers").and_then(Value::as_array_mut) {
+            for layer in layers {
+                bake_layer(layer, timing, &mut diagnostics);
+            }
+        }
+    }
+    diagnostics
+}
+
+fn bake_layer(layer: &mut Value, timing: Timing, diagnostics: &mut Vec<ImportDiagnostic>) {
+    let name = layer["nm"].as_str().unwrap_or_default().to_owned();
+    bake_value(layer, &name, timing, diagnostics);
+}
+
+fn bake_value(
+    value: &mut Value,
+    layer: &str,
+    timing: Timing,
+    diagnostics: &mut Vec<ImportDiagnostic>,
+) {
+    match value {
+        Value::Object(property) => {
+            if let (Some(Value::String(expression)), true) =
+                (property.get("x"), property.contains_key("k"))
+            {
+                let expression = expression.clone();
+                if bake_property(property, &expression, timing) {
+                    property.remove("x");
+                } else {
+                    diagnostics.push(ImportDiagnostic::UnsupportedExpression {
+                        layer: layer.to_owned(),
+                        expression: expression.trim().to_owned(),
+                    });
+                }
+            }
+            for value in property.values_mut() {
+                bake_value(value, layer, timing, diagnostics);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                bake_value(item, layer, timing, diagnostics);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace an animatable property's keyframes with the result of its
+/// expression. Returns `false` if the expression isn't supported.
+fn bake_property(property: &mut Map<String, Value>, expression: &str, timing: Timing) -> bool {
+    let expression: String = expression.chars().filter(|c| !c.is_whitespace()).collect();
+    let expression = expression.trim_end_matches(';');
+
+    if let Some(rate) = linear_time_rate(expression) {
+        return bake_linear_time(property, rate, timing);
+    }
+    let (args, loop_out) = if let Some(args) = expression.strip_prefix("loopOut(") {
+        (args, true)
+    } else if let Some(args) = expression.strip_prefix("loopIn(") {
+        (args, false)
+    } else {
+        return false;
+    };
+    let pingpong = match args.trim_end_matches(')').trim_matches(['"', '\'']) {
+        "" | "cycle" => false,
+        "pingpong" => true,
+        _ => return false,
+    };
+    bake_loop(property, loop_out, pingpong, timing)
+}
+
+/// The rate, per second, of expressions like `time * 90` or
+/// `value + 90 * time`.
+fn linear_time_rate(expression: &str) -> Option<f64> {
+    let expression = expression
+        .strip_prefix("value+")
+        .or_else(|| expression.strip_suffix("+value"))
+        .unwrap_or(expression);
+    if expression == "time" {
+        return Some(1.0);
+    }
+    expression
+        .strip_prefix("time*")
+        .or_else(|| expression.strip_suffix("*time"))
+        .and_then(|rate| rate.parse().ok())
+}
+
+fn bake_linear_time(property: &mut Map<String, Value>, rate: f64, timing: Timing) -> bool {
+    // Only static properties, e.g. a constant rotation
+    if property.get("a").and_then(Value::as_u64) == Some(1) {
+        return false;
+    }
+    let value = match &property["k"] {
+        Value::Number(value) => value.as_f64(),
+        Value::Array(values) if values.len() == 1 => values[0].as_f64(),
+        _ => None,
+    };
+    let Some(value) = value else {
+        return false;
+    };
+    let at = |frame: f64| value + rate * frame / timing.frame_rate;
+    property.insert("a".to_owned(), json!(1));
+    property.insert(
+        "k".to_owned(),
+        json!([
+            {
+                "t": timing.start,
+                "s": [at(timing.start)],
+                "o": { "x": [0.0], "y": [0.0] },
+                "i": { "x": [1.0], "y": [1.0] },
+            },
+            { "t": timing.end, "s": [at(timing.end)] },
+        ]),
+    );
+    true
+}
+
+fn bake_loop(
+    property: &mut Map<String, Value>,
+    loop_out: bool,
+    pingpong: bool,
+    timing: Timing,
+) -> bool {
+    let Some(keyframes) = property["k"].as_array() else {
+        return false;
+    };
+    // Every keyframe needs its own value, rather than the legacy `e` values
+    if keyframes.len() < 2 || keyframes.iter().any(|keyframe| keyframe["s"].is_null()) {
+        return false;
+    }
+    let times: Option<Vec<f64>> = keyframes.iter().map(|k| k["t"].as_f64()).collect();
+    let Some(times) = times else {
+        return false;
+    };
+    let (first, last) = (times[0], times[times.len() - 1]);
+    let period = last - first;
+    if period <= 0.0 {
+        return false;
+    }
+
+    let periods = if loop_out {
+        0..=((timing.end - first) / period)
+            .ceil()
+            .min(MAX_PERIODS as f64) as i64
+    } else {
+        -((first - timing.start) / period)
+            .ceil()
+            .min(MAX_PERIODS as f64) as i64..=0
+    };
+    let mut baked: Vec<Value> = vec![];
+    for c in periods {
+        let reversed = pingpong && c.rem_euclid(2) == 1;
+        // Reversed keyframes already start one period in
+        let offset = if reversed { c - 1 } else { c } as f64 * period;
+        let keys: Vec<Value> = if reversed {
+            reverse_keyframes(keyframes, last)
+        } else {
+            keyframes.clone()
+        };
+        for mut key in keys {
+            let t = key["t"].as_f64().unwrap_or_default() + offset;
+            key["t"] = json!(t);
+            append_keyframe(&mut baked, key);
+        }
+    }
+    property.insert("k".to_owned(), Value::Array(baked));
+    true
+}
+
+/// Keyframes played backwards, from `last` to `2 * last - first`.
+fn reverse_keyframes(keyframes: &[Value], last: f64) -> Vec<Value> {
+    let n = keyframes.len();
+    (0..n)
+        .map(|m| {
+            let key = &keyframes[n - 1 - m];
+            let mut reversed = json!({
+                "t": 2.0 * last - key["t"].as_f64().unwrap_or_default(),
+                "s": key["s"].clone(),
+            });
+            // Easing is stored on the key starting a segment, so the segment
+            // out of this key is the original segment into it, backwards
+            if m + 1 < n {
+                let segment = &keyframes[n - 2 - m];
+                reversed["o"] = mirror_handle(&segment["i"]);
+                reversed["i"] = mirror_handle(&segment["o"]);
+                if !segment["h"].is_null() {
+                    reversed["h"] = segment["h"].clone();
+                }
+            }
+            reversed
+        })
+        .collect()
+}
+
+/// Append a keyframe, joining it with the previous keyframe if they are at
+/// the same time.
+fn append_keyframe(keyframes: &mut Vec<Value>, key: Value) {
+    let Some(previous) = keyframes.last_mut() else {
+        keyframes.push(key);
+        return;
+    };
+    if previous["t"] != key["t"] {
+        keyframes.push(key);
+    } else if previous["s"] == key["s"] {
+        // The loop is seamless, so continue with the next segment's easing
+        if let (Some(previous), Value::Object(key)) = (previous.as_object_mut(), key) {
+            for field in ["o", "i", "h"] {
+                match key.get(field) {
+                    Some(value) => previous.insert(field.to_owned(), value.clone()),
+                    None => previous.remove(field),
+                };
+            }
+        }
+    } else {
+        // The loop jumps back, so start the next segment just after
+        let mut key = key;
+        key["t"] = json!(key["t"].as_f64().unwrap_or_default() + 0.01);
+        keyframes.push(key);
+    }
+}
+
+/// Mirror a bezier easing handle, for playing a segment backwards.
+fn mirror_handle(handle: &Value) -> Value {
+    fn mirror(value: &Value) -> Value {
+        match value {
+            Value::Number(n) => json!(1.0 - n.as_f64().unwrap_or_default()),
+            Value::Array(items) => Value::Array(items.iter().map(mirror).collect()),
+            _ => value.clone(),
+        }
+    }
+    if handle.is_null() {
+        return Value::Null;
+    }
+    json!({ "x": mirror(&handle["x"]), "y": mirror(&handle["y"]) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMING: Timing = Timing {
+        start: 0.0,
+        end: 20.0,
+        frame_rate: 30.0,
+    };
+
+    fn property(keyframes: Value) -> Map<String, Value> {
+        let Value::Object(property) = json!({ "a": 1, "k": keyframes }) else {
+            unreachable!();
+        };
+        property
+    }
+
+    fn times(property: &Map<String, Value>) -> Vec<f64> {
+        let keyframes = property["k"].as_array().unwrap();
+        keyframes
+            .iter()
+            .map(|key| key["t"].as_f64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn loop_out_cycle_repeats_until_the_out_point() {
+        let mut property = property(json!([{ "t": 0, "s": [0] }, { "t": 10, "s": [100] }]));
+        assert!(bake_loop(&mut property, true, false, TIMING));
+        // Each period jumps back to the first value just after the last
+        assert_eq!(times(&property), [0.0, 10.0, 10.01, 20.0, 20.01, 30.0]);
+        assert_eq!(property["k"][2]["s"], json!([0]));
+    }
+
+    #[test]
+    fn loop_out_pingpong_alternates_directions() {
+        let mut property = property(json!([{ "t": 0, "s": [0] }, { "t": 10, "s": [100] }]));
+        assert!(bake_loop(&mut property, true, true, TIMING));
+        assert_eq!(times(&property), [0.0, 10.0, 20.0, 30.0]);
+        let values: Vec<&Value> = property["k"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|key| &key["s"])
+            .collect();
+        assert_eq!(
+            values,
+            [&json!([0]), &json!([100]), &json!([0]), &json!([100])]
+        );
+    }
+
+    #[test]
+    fn loop_in_repeats_from_the_in_point() {
+        let mut property = property(json!([{ "t": 10, "s": [0] }, { "t": 20, "s": [100] }]));
+        assert!(bake_loop(&mut property, false, false, TIMING));
+        assert_eq!(times(&property), [0.0, 10.0, 10.01, 20.0]);
+    }
+
+    #[test]
+    fn loops_need_two_keyframes_with_values() {
+        let mut single = property(json!([{ "t": 0, "s": [0] }]));
+        assert!(!bake_loop(&mut single, true, false, TIMING));
+        let mut legacy = property(json!([{ "t": 0, "s": [0], "e": [1] }, { "t": 10 }]));
+        assert!(!bake_loop(&mut legacy, true, false, TIMING));
+    }
+
+    #[test]
+    fn reversed_keyframes_mirror_their_easing() {
+        let keyframes = [
+            json!({
+                "t": 0,
+                "s": [0],
+                "o": { "x": [0.25], "y": [0.0] },
+                "i": { "x": [0.75], "y": [1.0] },
+            }),
+            json!({ "t": 10, "s": [1] }),
+        ];
+        let reversed = reverse_keyframes(&keyframes, 10.0);
+        assert_eq!(
+            reversed,
+            [
+                json!({
+                    "t": 10.0,
+                    "s": [1],
+                    "o": { "x": [0.25], "y": [0.0] },
+                    "i": { "x": [0.75], "y": [1.0] },
+                }),
+                json!({ "t": 20.0, "s": [0] }),
+            ]
+        );
+    }
+
+    #[test]
+    fn bakes_linear_time_and_reports_unsupported_expressions() {
+        let mut root = json!({
+            "ip": 0,
+            "op": 60,
+            "fr": 30,
+            "layers": [{
+                "nm": "spinner",
+                "ks": {
+                    "r": { "a": 0, "k": 45, "x": "time * 90" },
+                    "o": { "a": 0, "k": 100, "x": "wiggle(1, 2)" },
+                },
+            }],
+        });
+        let diagnostics = bake_expressions(&mut root);
+        let transform = &root["layers"][0]["ks"];
+        assert!(transform["r"]["x"].is_null());
+        assert_eq!(transform["r"]["k"][1]["s"], json!([225.0]));
+        assert_eq!(
+            diagnostics,
+            [ImportDiagnostic::UnsupportedExpression {
+                layer: "spinner".to_owned(),
+                expression: "wiggle(1, 2)".to_owned(),
+            }]
+        );
+    }
+}
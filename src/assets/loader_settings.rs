@@ -0,0 +1,172 @@
+use crate::VelloAsset;
+use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
+use vello_svg::usvg;
+
+/// Settings for loading vector assets, set per load with
+/// `AssetServer::load_with_settings` or per file with `.meta` files.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct VelloAssetLoaderSettings {
+    /// How to handle features which render differently in-engine.
+    pub parse_mode: VelloParseMode,
+    /// Whether unsupported features (e.g. effects, mattes, expressions and
+    /// filters) are inspected on load, and collected into
+    /// [`VelloAsset::report`].
+    pub validate: bool,
+    /// A scale baked into the asset, e.g. `4.0` to always display 24x24
+    /// icons at 96x96 without scaling the entity's transform.
+    pub scale: f32,
+    /// Where the asset's local origin is placed.
+    pub origin: VelloAssetOrigin,
+    /// SVG only. The DPI used to convert physical units (e.g. `mm`, `in`).
+    pub dpi: f32,
+    /// SVG only. The font family used when text doesn't specify one.
+    pub font_family: String,
+    /// SVG only. The font size used when text doesn't specify one.
+    pub font_size: f32,
+    /// SVG only. Languages used to resolve `systemLanguage` conditionals.
+    pub languages: Vec<String>,
+    /// SVG only. The default `shape-rendering` hint.
+    pub shape_rendering: SvgShapeRendering,
+    /// SVG only. Whether `<text>` is converted to paths and rendered, or
+    /// dropped.
+    pub render_text: bool,
+    /// Whether text whose font isn't mapped (with
+    /// [`VelloSvgFontMap`](crate::assets::VelloSvgFontMap) or
+    /// [`VelloLottieFontMap`](crate::assets::VelloLottieFontMap)) falls back
+    /// to the fonts installed on the system. Off by default, since system
+    /// fonts are slow to load and differ between machines.
+    pub system_fonts: bool,
+    /// SVG only. Whether `<style>` blocks and the classes they select (as
+    /// exported by Illustrator and Figma) are applied.
+    pub svg_styles: bool,
+    /// SVG only. Extra CSS applied after the SVG's own `<style>` blocks, e.g.
+    /// `.accent { fill: #ff4081 }` to restyle a class.
+    pub style_sheet: String,
+    /// Lottie only. Whether layer names are kept. Names are needed by
+    /// [`Theme`](crate::Theme)s and [`Metadata`](crate::assets::Metadata).
+    pub keep_layer_names: bool,
+    /// Lottie only. Whether the Lottie JSON is kept with the asset, so it can
+    /// be edited and saved with [`LottieSaver`](crate::assets::LottieSaver).
+    pub keep_source: bool,
+    /// Lottie only. How Lotties from old or nonstandard exporters (e.g.
+    /// without a frame rate, or with 0-255 colors) are handled.
+    pub lottie_compatibility: LottieCompatibility,
+}
+
+impl Default for VelloAssetLoaderSettings {
+    fn default() -> Self {
+        Self {
+            parse_mode: VelloParseMode::default(),
+            validate: true,
+            scale: 1.0,
+            origin: VelloAssetOrigin::default(),
+            dpi: 96.0,
+            font_family: "Times New Roman".to_owned(),
+            font_size: 12.0,
+            languages: vec!["en".to_owned()],
+            shape_rendering: SvgShapeRendering::default(),
+            render_text: true,
+            system_fonts: false,
+            svg_styles: true,
+            style_sheet: String::new(),
+            keep_layer_names: true,
+            keep_source: false,
+            lottie_compatibility: LottieCompatibility::default(),
+        }
+    }
+}
+
+impl VelloAssetLoaderSettings {
+    /// The usvg options for these settings.
+    pub fn usvg_options(&self) -> usvg::Options {
+        usvg::Options {
+            dpi: self.dpi,
+            font_family: self.font_family.clone(),
+            font_size: self.font_size,
+            languages: self.languages.clone(),
+            shape_rendering: self.shape_rendering.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// How to handle features which render differently in-engine, reported as
+/// [`ImportDiagnostic`](crate::assets::ImportDiagnostic)s.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Default, Clone, Copy, Debug)]
+pub enum VelloParseMode {
+    /// Log diagnostics and load the asset anyway.
+    #[default]
+    Lenient,
+    /// Fail the load if there are any diagnostics.
+    Strict,
+}
+
+/// How Lotties from old or nonstandard exporters are handled, rather than
+/// failing deep inside velato.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum LottieCompatibility {
+    /// Patch nonstandard fields before parsing. Lotties without a frame rate
+    /// play at `frame_rate`.
+    Shim { frame_rate: f64 },
+    /// Fail the load with an error naming the nonstandard field.
+    Reject,
+}
+
+/// The frame rate of Lotties without one, unless shimmed with another.
+pub(crate) const DEFAULT_FRAME_RATE: f64 = 30.0;
+
+impl Default for LottieCompatibility {
+    fn default() -> Self {
+        Self::Shim {
+            frame_rate: DEFAULT_FRAME_RATE,
+        }
+    }
+}
+
+/// Where an asset's local origin is placed, in the asset's authored units.
+#[derive(Serialize, Deserialize, PartialEq, Default, Clone, Copy, Debug)]
+pub enum VelloAssetOrigin {
+    /// The top-left corner, as authored.
+    TopLeft,
+    /// The geometric center.
+    #[default]
+    Center,
+    /// A custom point, measured from the top-left corner with Y pointing
+    /// down.
+    Custom(f32, f32),
+}
+
+impl VelloAssetOrigin {
+    /// Place the origin of an asset.
+    pub fn apply(&self, asset: &mut VelloAsset) {
+        let content_size = Vec2::new(asset.width, asset.height) / asset.baked_scale();
+        let origin = match self {
+            VelloAssetOrigin::TopLeft => Vec2::ZERO,
+            VelloAssetOrigin::Center => content_size / 2.0,
+            VelloAssetOrigin::Custom(x, y) => Vec2::new(*x, *y),
+        };
+        asset.local_transform_center.translation.x = origin.x;
+        asset.local_transform_center.translation.y = -origin.y;
+    }
+}
+
+/// The SVG `shape-rendering` hint.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Default, Clone, Copy, Debug)]
+pub enum SvgShapeRendering {
+    OptimizeSpeed,
+    CrispEdges,
+    #[default]
+    GeometricPrecision,
+}
+
+impl From<SvgShapeRendering> for usvg::ShapeRendering {
+    fn from(value: SvgShapeRendering) -> Self {
+        match value {
+            SvgShapeRendering::OptimizeSpeed => usvg::ShapeRendering::OptimizeSpeed,
+            SvgShapeRendering::CrispEdges => usvg::ShapeRendering::CrispEdges,
+            SvgShapeRendering::GeometricPrecision => usvg::ShapeRendering::GeometricPrecision,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{LottiePlayer, VelloAsset, VelloFont, VelloLayers};
+use bevy::asset::{AssetPath, RecursiveDependencyLoadState, UntypedAssetId};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// The loading status of vector content, useful to gate loading screens.
+///
+/// Every [`VelloAsset`] and [`VelloFont`] referenced by an entity (including
+/// [`VelloLayers`] and [`LottiePlayer`] states) is tracked along with its
+/// dependencies. Handles held elsewhere can be tracked with
+/// [`VelloLoadingStatus::track`].
+#[derive(Resource, Default, Debug)]
+pub struct VelloLoadingStatus {
+    tracked: Vec<UntypedHandle>,
+    pending: usize,
+    failed: usize,
+    ready: bool,
+}
+
+impl VelloLoadingStatus {
+    /// Track a handle which isn't referenced by an entity.
+    pub fn track(&mut self, handle: impl Into<UntypedHandle>) {
+        self.tracked.push(handle.into());
+    }
+
+    /// Whether every tracked asset and its dependencies finished loading.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// The amount of tracked assets still loading.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// The amount of tracked assets which failed to load.
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+}
+
+/// An event emitted when every tracked vector asset finished loading. See
+/// [`VelloLoadingStatus`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct VelloAssetsReady;
+
+/// An event emitted when a vector asset finishes loading, along with its
+/// dependencies. Assets added directly to `Assets` don't emit it.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct VelloAssetReady {
+    /// The asset which finished loading.
+    pub id: AssetId<VelloAsset>,
+}
+
+/// An event emitted when a vector asset fails to load, e.g. a malformed
+/// user-provided Lottie file.
+#[derive(Event, Clone, Debug)]
+pub struct VelloAssetLoadFailed {
+    /// The path of the asset which failed to load.
+    pub path: AssetPath<'static>,
+    /// Why the asset failed to load.
+    pub error: String,
+}
+
+/// Load failures reported by the asset loader, waiting to be sent as
+/// [`VelloAssetLoadFailed`] events.
+#[derive(Resource, Clone, Default, Debug)]
+pub(crate) struct VelloLoadFailures(Arc<Mutex<Vec<VelloAssetLoadFailed>>>);
+
+impl VelloLoadFailures {
+    pub(crate) fn push(&self, path: &AssetPath, error: impl ToString) {
+        if let Ok(mut failures) = self.0.lock() {
+            failures.push(VelloAssetLoadFailed {
+                path: path.clone_owned(),
+                error: error.to_string(),
+            });
+        }
+    }
+}
+
+pub(crate) fn report_load_failures(
+    failures: Res<VelloLoadFailures>,
+    mut events: EventWriter<VelloAssetLoadFailed>,
+) {
+    let Ok(mut failures) = failures.0.lock() else {
+        return;
+    };
+    for failure in failures.drain(..) {
+        error!(path = %failure.path, "failed to load vector asset: {}", failure.error);
+        events.send(failure);
+    }
+}
+
+pub(crate) fn update_loading_status(
+    mut status: ResMut<VelloLoadingStatus>,
+    mut events: EventWriter<VelloAssetsReady>,
+    asset_server: Res<AssetServer>,
+    query_assets: Query<&Handle<VelloAsset>>,
+    query_layers: Query<&VelloLayers>,
+    query_players: Query<&LottiePlayer>,
+    query_fonts: Query<&Handle<VelloFont>>,
+) {
+    let ids: HashSet<UntypedAssetId> = query_assets
+        .iter()
+        .map(|handle| handle.id().untyped())
+        .chain(
+            query_layers
+                .iter()
+                .flat_map(|layers| layers.0.iter().map(|layer| layer.asset.id().untyped())),
+        )
+        .chain(query_players.iter().flat_map(|player| {
+            player
+                .states()
+                .filter_map(|state| state.asset.as_ref().map(|asset| asset.id().untyped()))
+        }))
+        .chain(query_fonts.iter().map(|handle| handle.id().untyped()))
+        .chain(status.tracked.iter().map(|handle| handle.id()))
+        .collect();
+
+    let (mut pending, mut failed) = (0, 0);
+    for id in ids {
+        // Assets added directly to `Assets` have no load state
+        match asset_server.get_recursive_dependency_load_state(id) {
+            Some(
+                RecursiveDependencyLoadState::NotLoaded | RecursiveDependencyLoadState::Loading,
+            ) => pending += 1,
+            Some(RecursiveDependencyLoadState::Failed) => failed += 1,
+            Some(RecursiveDependencyLoadState::Loaded) | None => {}
+        }
+    }
+
+    let was_ready = status.ready;
+    status.pending = pending;
+    status.failed = failed;
+    status.ready = pending == 0;
+    if status.ready && !was_ready {
+        events.send(VelloAssetsReady);
+    }
+}
+
+pub(crate) fn report_ready_assets(
+    mut asset_events: EventReader<AssetEvent<VelloAsset>>,
+    mut events: EventWriter<VelloAssetReady>,
+) {
+    for event in asset_events.read() {
+        if let AssetEvent::LoadedWithDependencies { id } = event {
+            events.send(VelloAssetReady { id: *id });
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use super::lottie_images::asset_file_path;
+use serde_json::Value;
+
+/// An audio layer of a Lottie composition, e.g. exported by Bodymovin into an
+/// `audio/` folder next to the Lottie. Audio isn't played, so it is exposed
+/// for games to play with their audio plugin of choice.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LottieAudio {
+    /// The name of the audio layer.
+    pub layer: String,
+    /// The asset path of the audio file. Lotties loaded with the
+    /// `AssetServer` resolve it against the Lottie's path, otherwise it is
+    /// relative to the Lottie.
+    pub path: String,
+    /// The frame the audio starts playing at.
+    pub start: f64,
+}
+
+/// Whether a Lottie asset is an audio file, either referenced by an audio
+/// layer or named like one.
+pub(crate) fn is_audio_asset(root: &Value, asset: &Value) -> bool {
+    const AUDIO_EXTENSIONS: [&str; 6] = ["mp3", "wav", "ogg", "m4a", "aac", "flac"];
+    let named_audio = asset["p"]
+        .as_str()
+        .and_then(|file| file.rsplit_once('.'))
+        .is_some_and(|(_, ext)| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    let id = asset["id"].as_str();
+    named_audio || audio_layers(root).any(|layer| layer["refId"].as_str() == id)
+}
+
+/// Collect the audio layers of Lottie JSON which reference external files.
+pub(crate) fn lottie_audio(root: &Value) -> Vec<LottieAudio> {
+    let assets = root["assets"].as_array().into_iter().flatten();
+    audio_layers(root)
+        .filter_map(|layer| {
+            let id = layer["refId"].as_str()?;
+            let asset = assets
+                .clone()
+                .find(|asset| asset["id"].as_str() == Some(id))?;
+            Some(LottieAudio {
+                layer: layer["nm"].as_str().unwrap_or_default().to_owned(),
+                path: asset_file_path(asset)?,
+                start: layer["ip"].as_f64().unwrap_or(0.0),
+            })
+        })
+        .collect()
+}
+
+/// The top-level audio layers of Lottie JSON.
+fn audio_layers(root: &Value) -> impl Iterator<Item = &Value> {
+    root["layers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|layer| layer["ty"].as_u64() == Some(6))
+}
@@ -0,0 +1,207 @@
+use super::{LottieCompatibility, VelloAssetError};
+use serde_json::Value;
+
+/// Check a Lottie for fields from old or nonstandard exporters, which velato
+/// can't parse or would render wrongly. Depending on `compatibility`, they are
+/// patched (returning what was patched) or fail the load.
+pub(crate) fn apply_compatibility(
+    root: &mut Value,
+    compatibility: LottieCompatibility,
+) -> Result<Vec<String>, VelloAssetError> {
+    let incompatible =
+        |issue: String| VelloAssetError::Parse(format!("Incompatible Lottie: {issue}"));
+    let shim = matches!(compatibility, LottieCompatibility::Shim { .. });
+
+    // Fields which can't be guessed
+    for (field, name) in [("w", "width"), ("h", "height")] {
+        if !root[field].is_number() {
+            return Err(incompatible(format!("missing {name} (`{field}`)")));
+        }
+    }
+    if !root["layers"].is_array() {
+        return Err(incompatible("missing layers (`layers`)".to_string()));
+    }
+
+    let mut issues = vec![];
+    if !root["fr"].as_f64().is_some_and(|fr| fr > 0.0) {
+        if let LottieCompatibility::Shim { frame_rate } = compatibility {
+            root["fr"] = frame_rate.into();
+        }
+        issues.push("missing frame rate (`fr`)".to_string());
+    }
+    if !root["ip"].is_number() {
+        root["ip"] = 0.into();
+        issues.push("missing in-point (`ip`)".to_string());
+    }
+    if !root["op"].is_number() {
+        // The composition lasts as long as its layers
+        let out_point = root["layers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|layer| layer["op"].as_f64())
+            .reduce(f64::max)
+            .ok_or_else(|| incompatible("missing out-point (`op`)".to_string()))?;
+        root["op"] = out_point.into();
+        issues.push("missing out-point (`op`)".to_string());
+    }
+
+    let mut legacy_keyframes = false;
+    let mut legacy_colors = false;
+    visit_objects(root, &mut |object| {
+        legacy_keyframes |= fix_keyframes(object);
+        legacy_colors |= fix_colors(object);
+    });
+    if legacy_keyframes {
+        issues.push("legacy keyframe end values (`e`)".to_string());
+    }
+    if legacy_colors {
+        issues.push("legacy 0-255 colors".to_string());
+    }
+
+    match issues.first() {
+        Some(issue) if !shim => Err(incompatible(issue.clone())),
+        _ => Ok(issues),
+    }
+}
+
+/// Visit every object in a JSON tree.
+fn visit_objects(value: &mut Value, f: &mut impl FnMut(&mut serde_json::Map<String, Value>)) {
+    match value {
+        Value::Object(object) => {
+            f(object);
+            for child in object.values_mut() {
+                visit_objects(child, f);
+            }
+        }
+        Value::Array(array) => {
+            for child in array {
+                visit_objects(child, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Legacy keyframes store the end value of each segment (`e`), and the last
+/// keyframe has no value. Each keyframe without a start value (`s`) gets the
+/// end value of the keyframe before it.
+fn fix_keyframes(property: &mut serde_json::Map<String, Value>) -> bool {
+    let Some(Value::Array(keyframes)) = property.get_mut("k") else {
+        return false;
+    };
+    let mut fixed = false;
+    for i in 1..keyframes.len() {
+        let (before, after) = keyframes.split_at_mut(i);
+        let (previous, keyframe) = (&before[i - 1], &mut after[0]);
+        let Some(keyframe) = keyframe.as_object_mut() else {
+            continue;
+        };
+        if keyframe.contains_key("t") && !keyframe.contains_key("s") {
+            if let Some(end) = previous.get("e") {
+                keyframe.insert("s".to_string(), end.clone());
+                fixed = true;
+            }
+        }
+    }
+    fixed
+}
+
+/// Old Bodymovin versions export fill and stroke colors in 0-255 rather
+/// than 0-1. Colors with any channel above 1 are rescaled.
+fn fix_colors(shape: &mut serde_json::Map<String, Value>) -> bool {
+    if !matches!(shape.get("ty").and_then(Value::as_str), Some("fl" | "st")) {
+        return false;
+    }
+    let Some(value) = shape.get_mut("c").and_then(|color| color.get_mut("k")) else {
+        return false;
+    };
+    // Static colors are a number array, animated colors are keyframes
+    let mut channels: Vec<&mut Value> = match value {
+        Value::Array(k) if k.iter().all(Value::is_number) => k.iter_mut().collect(),
+        Value::Array(keyframes) => keyframes
+            .iter_mut()
+            .filter_map(Value::as_object_mut)
+            .flat_map(|keyframe| {
+                keyframe
+                    .iter_mut()
+                    .filter(|(key, _)| matches!(key.as_str(), "s" | "e"))
+                    .flat_map(|(_, value)| value.as_array_mut().into_iter().flatten())
+            })
+            .collect(),
+        _ => return false,
+    };
+    let legacy = channels
+        .iter()
+        .any(|channel| channel.as_f64().is_some_and(|c| c > 1.0));
+    if legacy {
+        for channel in channels.iter_mut() {
+            if let Some(c) = channel.as_f64() {
+                **channel = (c / 255.0).into();
+            }
+        }
+    }
+    legacy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SHIM: LottieCompatibility = LottieCompatibility::Shim { frame_rate: 24.0 };
+
+    fn complete() -> Value {
+        json!({ "w": 100, "h": 100, "fr": 30, "ip": 0, "op": 60, "layers": [] })
+    }
+
+    #[test]
+    fn accepts_standard_files() {
+        let mut root = complete();
+        let issues = apply_compatibility(&mut root, LottieCompatibility::Reject).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(root, complete());
+    }
+
+    #[test]
+    fn rejects_missing_size_even_when_shimmed() {
+        let mut root = json!({ "h": 100, "layers": [] });
+        assert!(apply_compatibility(&mut root, SHIM).is_err());
+    }
+
+    #[test]
+    fn shims_missing_timing() {
+        let incomplete = json!({ "w": 100, "h": 100, "layers": [{ "op": 42 }] });
+        let mut root = incomplete.clone();
+        assert!(apply_compatibility(&mut root, LottieCompatibility::Reject).is_err());
+
+        let mut root = incomplete;
+        let issues = apply_compatibility(&mut root, SHIM).unwrap();
+        assert_eq!(issues.len(), 3);
+        assert_eq!(root["fr"], json!(24.0));
+        assert_eq!(root["ip"], json!(0));
+        assert_eq!(root["op"], json!(42.0));
+    }
+
+    #[test]
+    fn rescales_legacy_colors() {
+        let mut root = complete();
+        root["layers"] =
+            json!([{ "shapes": [{ "ty": "fl", "c": { "a": 0, "k": [255, 0, 0, 1] } }] }]);
+        let issues = apply_compatibility(&mut root, SHIM).unwrap();
+        assert_eq!(issues, ["legacy 0-255 colors"]);
+        let color = &root["layers"][0]["shapes"][0]["c"]["k"];
+        assert_eq!(color, &json!([1.0, 0.0, 0.0, 1.0 / 255.0]));
+    }
+
+    #[test]
+    fn fills_legacy_keyframe_values() {
+        let mut root = complete();
+        root["layers"] = json!([{
+            "ks": { "o": { "a": 1, "k": [{ "t": 0, "s": [0], "e": [100] }, { "t": 10 }] } },
+        }]);
+        let issues = apply_compatibility(&mut root, SHIM).unwrap();
+        assert_eq!(issues, ["legacy keyframe end values (`e`)"]);
+        assert_eq!(root["layers"][0]["ks"]["o"]["k"][1]["s"], json!([100]));
+    }
+}
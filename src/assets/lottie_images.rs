@@ -0,0 +1,122 @@
+use super::lottie_audio::is_audio_asset;
+use super::ImportDiagnostic;
+use base64::Engine;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde_json::Value;
+use std::sync::Arc;
+use vello::peniko::{Blob, Format, Image};
+
+/// A raster image layer of a Lottie composition. Velato skips image layers,
+/// so they are drawn alongside the composition.
+#[derive(Clone)]
+pub struct LottieImageLayer {
+    /// The index of the layer in the composition.
+    pub layer: usize,
+    /// The decoded image.
+    pub image: Image,
+    /// The size the image is drawn at, from the image asset.
+    pub size: Vec2,
+}
+
+/// The paths of images referenced by Lottie JSON which aren't embedded,
+/// relative to the Lottie file, e.g. `images/img_0.png` as exported by
+/// Bodymovin.
+pub(crate) fn external_image_paths(root: &Value) -> Vec<String> {
+    root["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|asset| !is_audio_asset(root, asset))
+        .filter_map(asset_file_path)
+        .collect()
+}
+
+/// Decode the image layers of a Lottie composition. Embedded images are
+/// decoded from their data URI, other images are looked up in `external` by
+/// their path.
+pub(crate) fn lottie_image_layers(
+    root: &Value,
+    composition: &velato::Composition,
+    external: &HashMap<String, Vec<u8>>,
+) -> (Vec<LottieImageLayer>, Vec<ImportDiagnostic>) {
+    let assets: HashMap<&str, &Value> = root["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| Some((asset["id"].as_str()?, asset)))
+        .collect();
+
+    let (mut images, mut diagnostics) = (vec![], vec![]);
+    let layers = root["layers"].as_array().into_iter().flatten().enumerate();
+    for (index, layer) in layers.filter(|(_, layer)| layer["ty"].as_u64() == Some(2)) {
+        let name = layer["nm"].as_str().unwrap_or_default();
+        // Velato keeps layers in file order, but fall back to the name in
+        // case a layer was dropped
+        let velato_index = match composition.layers.get(index) {
+            Some(velato_layer) if velato_layer.name == name => Some(index),
+            _ => composition
+                .layers
+                .iter()
+                .position(|velato_layer| velato_layer.name == name),
+        };
+        let asset = layer["refId"]
+            .as_str()
+            .and_then(|id| assets.get(id).copied());
+        let image = asset.and_then(|asset| {
+            let data = match asset["p"].as_str()?.strip_prefix("data:") {
+                Some(data_uri) => {
+                    let (_, encoded) = data_uri.split_once(',')?;
+                    base64::engine::general_purpose::STANDARD
+                        .decode(encoded)
+                        .ok()?
+                }
+                None => external.get(&asset_file_path(asset)?)?.clone(),
+            };
+            decode_image(&data)
+        });
+        match (velato_index, image, asset) {
+            (Some(layer), Some(image), Some(asset)) => {
+                let size = Vec2::new(
+                    asset["w"].as_f64().unwrap_or(image.width as f64) as f32,
+                    asset["h"].as_f64().unwrap_or(image.height as f64) as f32,
+                );
+                images.push(LottieImageLayer { layer, image, size });
+            }
+            _ => diagnostics.push(ImportDiagnostic::MissingImage {
+                layer: name.to_owned(),
+            }),
+        }
+    }
+    (images, diagnostics)
+}
+
+/// The path of a non-embedded file asset, joining its directory (`u`) and
+/// file name (`p`). Windows separators are normalized, since exports keep the
+/// authoring machine's.
+pub(crate) fn asset_file_path(asset: &Value) -> Option<String> {
+    let file = asset["p"].as_str()?;
+    if file.is_empty() || file.starts_with("data:") || asset["e"].as_u64() == Some(1) {
+        return None;
+    }
+    let dir = asset["u"].as_str().unwrap_or_default().replace('\\', "/");
+    let dir = dir.trim_start_matches('/').trim_end_matches('/');
+    let file = file.replace('\\', "/");
+    if dir.is_empty() {
+        Some(file)
+    } else {
+        Some(format!("{dir}/{file}"))
+    }
+}
+
+/// Decode a raster image into RGBA pixels.
+pub(crate) fn decode_image(data: &[u8]) -> Option<Image> {
+    let image = image::load_from_memory(data).ok()?.to_rgba8();
+    let (width, height) = image.dimensions();
+    Some(Image::new(
+        Blob::new(Arc::new(image.into_raw())),
+        Format::Rgba8,
+        width,
+        height,
+    ))
+}
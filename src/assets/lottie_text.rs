@@ -0,0 +1,199 @@
+use super::parser::SYSTEM_FONT_DB;
+use super::ImportDiagnostic;
+use crate::VelloFont;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use vello::peniko::{self, Blob, Color};
+use vello_svg::usvg::fontdb::{Family, Query};
+
+/// Maps font names used by Lottie text layers to [`VelloFont`] asset paths.
+///
+/// Text layers using a mapped font are shaped with that font when the Lottie
+/// loads, and mapped fonts become dependencies of the Lottie. Fonts which
+/// aren't mapped are resolved against system fonts by family, if the loader
+/// settings opt into `system_fonts`.
+#[derive(Resource, Clone, Default, Debug)]
+pub struct VelloLottieFontMap(Arc<RwLock<HashMap<String, String>>>);
+
+impl VelloLottieFontMap {
+    /// Map a Lottie font name (`fName`) to a [`VelloFont`] asset path.
+    pub fn insert(&self, font: impl Into<String>, path: impl Into<String>) {
+        if let Ok(mut map) = self.0.write() {
+            map.insert(font.into(), path.into());
+        }
+    }
+
+    /// The asset path mapped to a Lottie font name.
+    pub fn get(&self, font: &str) -> Option<String> {
+        self.0.read().ok()?.get(font).cloned()
+    }
+}
+
+/// A text layer of a Lottie composition. Velato skips text layers, so they
+/// are drawn alongside the composition.
+#[derive(Clone)]
+pub struct LottieTextLayer {
+    /// The index of the layer in the composition.
+    pub layer: usize,
+    /// The font text is shaped with.
+    pub font: VelloFont,
+    /// Text documents, and the frames they start at.
+    pub documents: Vec<(f64, LottieTextDocument)>,
+}
+
+impl LottieTextLayer {
+    /// The text document shown at a frame.
+    pub fn document(&self, frame: f64) -> Option<&LottieTextDocument> {
+        self.documents
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= frame)
+            .or(self.documents.first())
+            .map(|(_, document)| document)
+    }
+}
+
+/// The text, and its style, of a Lottie text layer.
+#[derive(Clone, Debug)]
+pub struct LottieTextDocument {
+    pub text: String,
+    pub size: f32,
+    pub color: Color,
+    /// The distance between baselines.
+    pub line_height: f32,
+    /// Where lines are anchored, from `0.0` (left) to `1.0` (right).
+    pub justify: f32,
+}
+
+/// The font names used by Lottie text, and their families.
+pub(crate) fn lottie_fonts(root: &Value) -> Vec<(String, String)> {
+    root["fonts"]["list"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|font| {
+            let name = font["fName"].as_str()?;
+            let family = font["fFamily"].as_str().unwrap_or(name);
+            Some((name.to_owned(), family.to_owned()))
+        })
+        .collect()
+}
+
+/// Collect the text layers of a Lottie composition. Fonts are looked up in
+/// `fonts` by their Lottie font name, then in system fonts by family if
+/// `system_fonts` is set.
+pub(crate) fn lottie_text_layers(
+    root: &Value,
+    composition: &velato::Composition,
+    fonts: &HashMap<String, VelloFont>,
+    system_fonts: bool,
+) -> (Vec<LottieTextLayer>, Vec<ImportDiagnostic>) {
+    let families: HashMap<String, String> = lottie_fonts(root).into_iter().collect();
+
+    let (mut texts, mut diagnostics) = (vec![], vec![]);
+    let layers = root["layers"].as_array().into_iter().flatten().enumerate();
+    for (index, layer) in layers.filter(|(_, layer)| layer["ty"].as_u64() == Some(5)) {
+        let name = layer["nm"].as_str().unwrap_or_default();
+        // Velato keeps layers in file order, but fall back to the name in
+        // case a layer was dropped
+        let Some(velato_index) = (match composition.layers.get(index) {
+            Some(velato_layer) if velato_layer.name == name => Some(index),
+            _ => composition
+                .layers
+                .iter()
+                .position(|velato_layer| velato_layer.name == name),
+        }) else {
+            diagnostics.push(ImportDiagnostic::UnsupportedLayer {
+                layer: name.to_owned(),
+                kind: "text",
+            });
+            continue;
+        };
+        let keyframes = layer["t"]["d"]["k"].as_array().into_iter().flatten();
+        let documents: Vec<(f64, LottieTextDocument, String)> = keyframes
+            .filter_map(|keyframe| {
+                let (document, font) = text_document(&keyframe["s"])?;
+                Some((keyframe["t"].as_f64().unwrap_or(0.0), document, font))
+            })
+            .collect();
+        let Some((_, _, font_name)) = documents.first() else {
+            diagnostics.push(ImportDiagnostic::UnsupportedLayer {
+                layer: name.to_owned(),
+                kind: "text",
+            });
+            continue;
+        };
+        let family = families.get(font_name).unwrap_or(font_name);
+        let Some(font) = fonts
+            .get(font_name)
+            .cloned()
+            .or_else(|| system_fonts.then(|| system_font(family)).flatten())
+        else {
+            diagnostics.push(ImportDiagnostic::MissingFont {
+                layer: name.to_owned(),
+                font: font_name.clone(),
+            });
+            continue;
+        };
+        texts.push(LottieTextLayer {
+            layer: velato_index,
+            font,
+            documents: documents
+                .into_iter()
+                .map(|(start, document, _)| (start, document))
+                .collect(),
+        });
+    }
+    (texts, diagnostics)
+}
+
+/// Parse a text document, returning it with its font name.
+fn text_document(document: &Value) -> Option<(LottieTextDocument, String)> {
+    let size = document["s"].as_f64()? as f32;
+    let color = match document["fc"].as_array() {
+        Some(components) => {
+            let channels: Vec<f64> = components.iter().filter_map(Value::as_f64).collect();
+            // Older exports use 0-255 channels
+            let max = if channels.iter().any(|c| *c > 1.0) {
+                255.0
+            } else {
+                1.0
+            };
+            let channel = |i: usize, default: f64| channels.get(i).copied().unwrap_or(default);
+            Color::rgba(
+                channel(0, 0.0) / max,
+                channel(1, 0.0) / max,
+                channel(2, 0.0) / max,
+                channel(3, max) / max,
+            )
+        }
+        None => Color::BLACK,
+    };
+    let justify = match document["j"].as_u64() {
+        Some(1) => 1.0,
+        Some(2) => 0.5,
+        _ => 0.0,
+    };
+    let text = LottieTextDocument {
+        text: document["t"].as_str()?.to_owned(),
+        size,
+        color,
+        line_height: document["lh"].as_f64().map_or(size * 1.2, |lh| lh as f32),
+        justify,
+    };
+    Some((text, document["f"].as_str().unwrap_or_default().to_owned()))
+}
+
+/// Find a font family in system fonts.
+fn system_font(family: &str) -> Option<VelloFont> {
+    let query = Query {
+        families: &[Family::Name(family)],
+        ..Default::default()
+    };
+    let id = SYSTEM_FONT_DB.query(&query)?;
+    SYSTEM_FONT_DB.with_face_data(id, |data, index| VelloFont {
+        font: Arc::new(peniko::Font::new(Blob::new(Arc::new(data.to_vec())), index)),
+    })
+}
@@ -1,7 +1,44 @@
 use bevy::utils::hashbrown::HashSet;
+use serde_json::Value;
+use std::ops::Range;
 use std::sync::Arc;
 use velato::Composition;
 
+/// A named frame or segment of a Lottie composition, e.g. `"jump"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LottieMarker {
+    /// The marker's name (`cm`).
+    pub name: String,
+    /// The frame the marker starts at.
+    pub start: f64,
+    /// The length of the marker in frames, or `0.0` for a single frame.
+    pub duration: f64,
+}
+
+impl LottieMarker {
+    /// The frames the marker spans. Play them with
+    /// [`Segment::Marker`](crate::Segment::Marker).
+    pub fn frames(&self) -> Range<f64> {
+        self.start..self.start + self.duration
+    }
+}
+
+/// Read the markers of Lottie JSON.
+pub(crate) fn lottie_markers(root: &Value) -> Vec<LottieMarker> {
+    root["markers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|marker| {
+            Some(LottieMarker {
+                name: marker["cm"].as_str()?.to_owned(),
+                start: marker["tm"].as_f64()?,
+                duration: marker["dr"].as_f64().unwrap_or(0.0),
+            })
+        })
+        .collect()
+}
+
 /// Metadata used for introspection and color swapping.
 pub struct Metadata {
     pub(crate) composition: Arc<Composition>,
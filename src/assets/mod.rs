@@ -5,11 +5,64 @@ pub use asset::{VectorFile, VelloAsset};
 
 mod parser;
 pub use parser::{
-    load_lottie_from_bytes, load_lottie_from_str, load_svg_from_bytes, load_svg_from_str,
+    load_image_from_bytes, load_lottie_from_bytes, load_lottie_from_str, load_svg_from_bytes,
+    load_svg_from_str,
 };
 
+mod dotlottie;
+mod expressions;
+mod lottie_audio;
+pub use lottie_audio::LottieAudio;
+mod lottie_compat;
+mod lottie_images;
+pub(crate) use lottie_images::external_image_paths;
+pub use lottie_images::LottieImageLayer;
+mod lottie_text;
+pub(crate) use lottie_text::lottie_fonts;
+pub use lottie_text::{LottieTextDocument, LottieTextLayer, VelloLottieFontMap};
+mod precomps;
+mod shared;
+mod svg_encoder;
+pub(crate) use svg_encoder::encode_tree;
+mod svg_fragments;
+mod svg_resources;
+mod svg_styles;
+
 mod asset_loader;
-pub(crate) use asset_loader::{VectorLoaderError, VelloAssetLoader};
+pub use asset_loader::VelloAssetError;
+pub(crate) use asset_loader::VelloAssetLoader;
+
+mod loader_settings;
+pub(crate) use loader_settings::DEFAULT_FRAME_RATE;
+pub use loader_settings::{
+    LottieCompatibility, SvgShapeRendering, VelloAssetLoaderSettings, VelloAssetOrigin,
+    VelloParseMode,
+};
+
+mod diagnostics;
+pub(crate) use diagnostics::{lottie_diagnostics, svg_diagnostics};
+pub use diagnostics::{ImportDiagnostic, VelloAssetReport};
+
+mod processor;
+pub use processor::LottieMinifier;
+
+mod saver;
+pub use saver::LottieSaver;
 
 mod metadata;
-pub use metadata::Metadata;
+pub(crate) use metadata::lottie_markers;
+pub use metadata::{LottieMarker, Metadata};
+
+mod svg_fonts;
+pub(crate) use svg_fonts::font_families;
+pub use svg_fonts::VelloSvgFontMap;
+
+mod progress;
+pub(crate) use progress::prune_load_progress;
+pub use progress::VelloLoadProgress;
+
+mod loading;
+pub(crate) use loading::{
+    report_load_failures, report_ready_assets, update_loading_status, VelloLoadFailures,
+};
+pub use loading::{VelloAssetLoadFailed, VelloAssetReady, VelloAssetsReady, VelloLoadingStatus};
@@ -1,30 +1,184 @@
-use super::asset_loader::VectorLoaderError;
+use super::asset_loader::VelloAssetError;
 use crate::assets::asset::VectorFile;
-use crate::VelloAsset;
+use crate::assets::expressions::bake_expressions;
+use crate::assets::lottie_audio::lottie_audio;
+use crate::assets::lottie_compat::apply_compatibility;
+use crate::assets::lottie_images::{decode_image, lottie_image_layers};
+use crate::assets::lottie_text::lottie_text_layers;
+use crate::assets::shared::{ContentKey, SharedCache};
+use crate::assets::svg_encoder::{encode_node, encode_tree, svg_nodes, SVG_CHUNK_NODES};
+use crate::assets::{
+    lottie_diagnostics, lottie_markers, svg_diagnostics, LottieCompatibility, VelloAssetReport,
+};
+use crate::{VelloAsset, VelloFont};
 use bevy::prelude::*;
+use bevy::tasks::futures_lite::future;
+use bevy::utils::HashMap;
 use once_cell::sync::Lazy;
+use std::borrow::Cow;
+use std::io::Read;
 use std::sync::Arc;
 use vello::Scene;
 use vello_svg::usvg::{self, fontdb::Database};
 
-pub static FONT_DB: Lazy<Database> = Lazy::new(usvg::fontdb::Database::default);
+/// The fonts installed on the system, only loaded once a load opts into them.
+pub(crate) static SYSTEM_FONT_DB: Lazy<Database> = Lazy::new(|| {
+    let mut db = Database::new();
+    db.load_system_fonts();
+    db
+});
 
-/// Deserialize an SVG file from bytes.
-pub fn load_svg_from_bytes(bytes: &[u8]) -> Result<VelloAsset, VectorLoaderError> {
+/// A font database without fonts, used to drop SVG text.
+pub(crate) static EMPTY_FONT_DB: Lazy<Database> = Lazy::new(Database::new);
+
+/// The fonts used for text whose font isn't mapped: system fonts if
+/// `system_fonts` is set, or none.
+pub(crate) fn base_font_db(system_fonts: bool) -> &'static Database {
+    if system_fonts {
+        &SYSTEM_FONT_DB
+    } else {
+        &EMPTY_FONT_DB
+    }
+}
+
+/// Deserialize an SVG file from bytes. No fonts are mapped, so text is
+/// dropped.
+pub fn load_svg_from_bytes(bytes: &[u8]) -> Result<VelloAsset, VelloAssetError> {
+    let bytes = gunzip_if_compressed(bytes)?;
+    let usvg = parse_svg(&bytes, &usvg::Options::default(), &EMPTY_FONT_DB)?;
+    let mut vello_vector = encode_svg(usvg);
+    vello_vector.report = svg_diagnostics(std::str::from_utf8(&bytes)?).into();
+    Ok(vello_vector)
+}
+
+/// Decompress gzipped bytes (e.g. `.svgz`), or return uncompressed bytes as
+/// they are.
+pub(crate) fn gunzip_if_compressed(bytes: &[u8]) -> Result<Cow<[u8]>, VelloAssetError> {
+    const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+    if !bytes.starts_with(&GZIP_MAGIC) {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    Ok(Cow::Owned(decompressed))
+}
+
+/// Decompress Brotli (`.br`) or Zstandard (`.zst`) bytes, as shipped to web
+/// builds, or return uncompressed bytes as they are. Brotli has no magic
+/// number, so it is only recognized by the file extension.
+pub(crate) fn decompress_if_compressed<'a>(
+    bytes: &'a [u8],
+    extension: Option<&str>,
+) -> Result<Cow<'a, [u8]>, VelloAssetError> {
+    const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+    let mut decompressed = Vec::new();
+    if bytes.starts_with(&ZSTD_MAGIC) {
+        ruzstd::StreamingDecoder::new(bytes)
+            .map_err(|err| VelloAssetError::Parse(err.to_string()))?
+            .read_to_end(&mut decompressed)?;
+    } else if extension == Some("br") {
+        brotli_decompressor::Decompressor::new(bytes, 4096).read_to_end(&mut decompressed)?;
+    } else {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    Ok(Cow::Owned(decompressed))
+}
+
+/// Parse SVG bytes into a usvg tree, without encoding it. Text is shaped into
+/// paths with the fonts in `fontdb`.
+pub(crate) fn parse_svg(
+    bytes: &[u8],
+    options: &usvg::Options,
+    fontdb: &Database,
+) -> Result<usvg::Tree, VelloAssetError> {
     let svg_str = std::str::from_utf8(bytes)?;
+    Ok(usvg::Tree::from_str(svg_str, options, fontdb)?)
+}
+
+/// Base fonts extended with the fonts read for SVGs, by those fonts.
+static FONT_DBS: SharedCache<Database> = SharedCache::new();
 
-    let usvg = usvg::Tree::from_str(svg_str, &usvg::Options::default(), &FONT_DB)?;
+/// The [`base_font_db`], with each font's data added under its family name,
+/// so SVG text using that family is shaped with it. SVGs using the same
+/// fonts share one database. Only the base database's face list is copied
+/// into it, since face data is in files or behind an `Arc`.
+pub(crate) fn font_db_with(fonts: Vec<(String, Vec<u8>)>, system_fonts: bool) -> Arc<Database> {
+    let system_fonts_part = [u8::from(system_fonts)];
+    let parts = std::iter::once(&system_fonts_part[..]).chain(
+        fonts
+            .iter()
+            .flat_map(|(family, font_data)| [family.as_bytes(), font_data]),
+    );
+    FONT_DBS
+        .get_or_try_insert(ContentKey::from_parts(parts), || {
+            let mut fontdb = base_font_db(system_fonts).clone();
+            for (family, font_data) in fonts {
+                add_font_family(&mut fontdb, &family, font_data);
+            }
+            Ok::<_, std::convert::Infallible>(fontdb)
+        })
+        .unwrap_or_else(|never| match never {})
+}
+
+/// Add font data to a font database under the given family name. Every face
+/// shares the data through one `Arc`, rather than copying it.
+fn add_font_family(fontdb: &mut Database, family: &str, font_data: Vec<u8>) {
+    let mut faces = Database::new();
+    faces.load_font_source(usvg::fontdb::Source::Binary(Arc::new(font_data)));
+    for face in faces.faces() {
+        let mut info = face.clone();
+        info.families = vec![(
+            family.to_owned(),
+            usvg::fontdb::Language::English_UnitedStates,
+        )];
+        fontdb.push_face_info(info);
+    }
+}
 
+/// Encode a parsed usvg tree into a vello asset.
+pub(crate) fn encode_svg(usvg: usvg::Tree) -> VelloAsset {
     // Process the loaded SVG into Vello-compatible data
     let mut scene = Scene::new();
-    vello_svg::render_tree(&mut scene, &usvg);
+    encode_tree(&mut scene, &usvg, 1.0);
+    svg_asset(scene, Arc::new(usvg))
+}
 
-    let width = usvg.size().width();
-    let height = usvg.size().height();
+/// Parse and encode an SVG. The parsed SVG is encoded a chunk of nodes at a
+/// time, yielding in between so other tasks run, and reporting the fraction
+/// done to `progress`.
+pub(crate) async fn encode_svg_incrementally(
+    svg: &str,
+    options: &usvg::Options,
+    fontdb: &Database,
+    progress: impl Fn(f32),
+) -> Result<VelloAsset, VelloAssetError> {
+    let tree = parse_svg(svg.as_bytes(), options, fontdb)?;
+    let nodes = svg_nodes(&tree);
+    let chunks = nodes.chunks(SVG_CHUNK_NODES);
+    let chunk_count = chunks.len();
+    let mut scene = Scene::new();
+    for (index, chunk) in chunks.enumerate() {
+        for (transform, node) in chunk {
+            encode_node(&mut scene, node, *transform, 1.0);
+        }
+        progress((index + 1) as f32 / chunk_count as f32);
+        future::yield_now().await;
+    }
+    if chunk_count == 0 {
+        progress(1.0);
+    }
+    Ok(svg_asset(scene, Arc::new(tree)))
+}
 
-    let vello_vector = VelloAsset {
+/// A vello asset drawing an SVG, encoded from its parsed tree.
+fn svg_asset(scene: Scene, tree: Arc<usvg::Tree>) -> VelloAsset {
+    let width = tree.size().width();
+    let height = tree.size().height();
+
+    VelloAsset {
         data: VectorFile::Svg {
             scene: Arc::new(scene),
+            tree,
         },
         local_transform_center: {
             let mut transform = Transform::default();
@@ -34,30 +188,121 @@ pub fn load_svg_from_bytes(bytes: &[u8]) -> Result<VelloAsset, VectorLoaderError
         },
         width,
         height,
-    };
+        report: VelloAssetReport::default(),
+    }
+}
 
-    Ok(vello_vector)
+/// Decode a raster image (PNG, JPEG or WebP) from bytes. The image is drawn
+/// at its pixel size, like an SVG of the same size.
+pub fn load_image_from_bytes(bytes: &[u8]) -> Result<VelloAsset, VelloAssetError> {
+    let image = decode_image(bytes)
+        .ok_or_else(|| VelloAssetError::Parse("Unable to decode image".to_string()))?;
+    let (width, height) = (image.width as f32, image.height as f32);
+    Ok(VelloAsset {
+        data: VectorFile::Image { image },
+        local_transform_center: {
+            let mut transform = Transform::default();
+            transform.translation.x = width / 2.0;
+            transform.translation.y = -height / 2.0;
+            transform
+        },
+        width,
+        height,
+        report: VelloAssetReport::default(),
+    })
 }
 
 /// Deserialize an SVG file from a string slice.
-pub fn load_svg_from_str(svg_str: &str) -> Result<VelloAsset, VectorLoaderError> {
+pub fn load_svg_from_str(svg_str: &str) -> Result<VelloAsset, VelloAssetError> {
     let bytes = svg_str.as_bytes();
 
     load_svg_from_bytes(bytes)
 }
 
-/// Deserialize a Lottie file from bytes.
-pub fn load_lottie_from_bytes(bytes: &[u8]) -> Result<VelloAsset, VectorLoaderError> {
-    // Load Lottie JSON bytes with the Velato (bodymovin) parser
-    let composition = velato::Composition::from_slice(bytes)
-        .map_err(|err| VectorLoaderError::Parse(format!("Unable to parse lottie JSON: {err:?}")))?;
+/// Parsed compositions, by a hash of their baked JSON.
+static COMPOSITIONS: SharedCache<velato::Composition> = SharedCache::new();
+
+/// Resources used by Lottie JSON which aren't embedded in it.
+#[derive(Default)]
+pub(crate) struct LottieResources {
+    /// Image bytes, keyed by their path relative to the Lottie file.
+    pub images: HashMap<String, Vec<u8>>,
+    /// Fonts for text layers, keyed by their Lottie font name.
+    pub fonts: HashMap<String, VelloFont>,
+    /// Whether fonts which aren't in `fonts` are found in system fonts.
+    pub system_fonts: bool,
+}
+
+/// Deserialize a Lottie file from bytes. Only embedded images are loaded,
+/// and no fonts are mapped, so text layers are dropped.
+pub fn load_lottie_from_bytes(bytes: &[u8]) -> Result<VelloAsset, VelloAssetError> {
+    load_lottie_with_resources(
+        parse_lottie(bytes)?,
+        &LottieResources::default(),
+        true,
+        LottieCompatibility::default(),
+    )
+}
+
+/// Parse Lottie JSON once, to share between everything reading it.
+pub(crate) fn parse_lottie(bytes: &[u8]) -> Result<serde_json::Value, VelloAssetError> {
+    serde_json::from_slice(bytes)
+        .map_err(|err| VelloAssetError::Parse(format!("Unable to parse lottie JSON: {err}")))
+}
+
+/// Build a Lottie from its parsed JSON, with the resources it references.
+/// Unsupported features are only inspected when `validate` is set.
+pub(crate) fn load_lottie_with_resources(
+    mut root: serde_json::Value,
+    resources: &LottieResources,
+    validate: bool,
+    compatibility: LottieCompatibility,
+) -> Result<VelloAsset, VelloAssetError> {
+    // Nonstandard fields are patched, and supported expressions are baked
+    // into keyframes, before velato sees them
+    for shim in apply_compatibility(&mut root, compatibility)? {
+        debug!("patched nonstandard Lottie: {shim}");
+    }
+    let expression_diagnostics = bake_expressions(&mut root);
+    let markers = lottie_markers(&root);
+    let audio = lottie_audio(&root);
+    let baked = serde_json::to_vec(&root)
+        .map_err(|err| VelloAssetError::Parse(format!("Unable to parse lottie JSON: {err}")))?;
+    let bytes = baked.as_slice();
+
+    // Load Lottie JSON bytes with the Velato (bodymovin) parser. Identical
+    // compositions share one parsed representation
+    let composition = COMPOSITIONS.get_or_try_insert(ContentKey::new(bytes), || {
+        velato::Composition::from_slice(bytes)
+            .map_err(|err| VelloAssetError::Parse(format!("Unable to parse lottie JSON: {err:?}")))
+    })?;
 
     let width = composition.width as f32;
     let height = composition.height as f32;
+    let (images, image_diagnostics) = lottie_image_layers(&root, &composition, &resources.images);
+    let (texts, text_diagnostics) = lottie_text_layers(
+        &root,
+        &composition,
+        &resources.fonts,
+        resources.system_fonts,
+    );
+    // Missing resources are always reported
+    let mut diagnostics = image_diagnostics;
+    diagnostics.extend(text_diagnostics);
+    if validate {
+        diagnostics.extend(lottie_diagnostics(&root));
+        diagnostics.extend(expression_diagnostics);
+    }
 
     let vello_vector = VelloAsset {
         data: VectorFile::Lottie {
-            composition: Arc::new(composition),
+            composition,
+            images: images.into(),
+            texts: texts.into(),
+            themes: Arc::default(),
+            markers: markers.into(),
+            audio: audio.into(),
+            source: None,
         },
         local_transform_center: {
             let mut transform = Transform::default();
@@ -67,13 +312,14 @@ pub fn load_lottie_from_bytes(bytes: &[u8]) -> Result<VelloAsset, VectorLoaderEr
         },
         width,
         height,
+        report: diagnostics.into(),
     };
 
     Ok(vello_vector)
 }
 
 /// Deserialize a Lottie file from a string slice.
-pub fn load_lottie_from_str(json_str: &str) -> Result<VelloAsset, VectorLoaderError> {
+pub fn load_lottie_from_str(json_str: &str) -> Result<VelloAsset, VelloAssetError> {
     let bytes = json_str.as_bytes();
 
     load_lottie_from_bytes(bytes)
@@ -0,0 +1,48 @@
+use serde_json::{json, Value};
+
+/// Split the precompositions of Lottie JSON into standalone Lottie JSON,
+/// keyed by their asset id.
+///
+/// Each precomposition keeps the document's frames and assets, so nested
+/// precomps, images and fonts still resolve. Its size is the precomp's own
+/// size, or the size of the first layer referencing it.
+pub(crate) fn lottie_precomps(root: &Value) -> Vec<(String, Value)> {
+    let assets = root["assets"].as_array().into_iter().flatten();
+    let all_layers: Vec<&Value> = root["layers"]
+        .as_array()
+        .into_iter()
+        .chain(
+            assets
+                .clone()
+                .filter_map(|asset| asset["layers"].as_array()),
+        )
+        .flatten()
+        .collect();
+
+    let mut precomps = vec![];
+    for asset in assets {
+        let (Some(id), Some(layers)) = (asset["id"].as_str(), asset.get("layers")) else {
+            continue;
+        };
+        let reference = all_layers
+            .iter()
+            .find(|layer| layer["refId"].as_str() == Some(id));
+        let size = |key: &str| {
+            [asset, reference.copied().unwrap_or(&Value::Null), root]
+                .into_iter()
+                .map(|value| &value[key])
+                .find(|size| size.is_number())
+                .cloned()
+                .unwrap_or(json!(0))
+        };
+        let mut precomp = root.clone();
+        precomp["w"] = size("w");
+        precomp["h"] = size("h");
+        precomp["nm"] = asset.get("nm").cloned().unwrap_or_else(|| json!(id));
+        precomp["layers"] = layers.clone();
+        // Markers name frames of the main composition
+        precomp["markers"] = json!([]);
+        precomps.push((id.to_owned(), precomp));
+    }
+    precomps
+}
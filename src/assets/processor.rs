@@ -0,0 +1,88 @@
+use super::parser::{load_lottie_with_resources, parse_lottie, LottieResources};
+use super::{
+    ImportDiagnostic, VelloAssetError, VelloAssetLoader, VelloAssetLoaderSettings, VelloParseMode,
+};
+use bevy::asset::io::Writer;
+use bevy::asset::meta::{AssetAction, AssetMeta};
+use bevy::asset::processor::{Process, ProcessContext, ProcessError};
+use bevy::asset::AsyncWriteExt;
+use bevy::utils::BoxedFuture;
+
+/// An asset processor which validates and minifies Lottie JSON at build time,
+/// so malformed files fail when processing rather than in-game, and shipped
+/// files are smaller. The output is still Lottie JSON, loaded with the same
+/// settings it was validated with.
+///
+/// This isn't a pre-parsed format: processed files are parsed at runtime like
+/// any other Lottie, only with less whitespace to read. velato compositions
+/// can't be serialized, so there's no binary form to load instead.
+///
+/// Opt in per file with a `.meta` file, or for every `.json` asset with
+/// `App::set_default_asset_processor::<LottieMinifier>("json")`.
+pub struct LottieMinifier;
+
+impl Process for LottieMinifier {
+    type Settings = VelloAssetLoaderSettings;
+
+    type OutputLoader = VelloAssetLoader;
+
+    fn process<'a>(
+        &'a self,
+        context: &'a mut ProcessContext,
+        meta: AssetMeta<(), Self>,
+        writer: &'a mut Writer,
+    ) -> BoxedFuture<'a, Result<VelloAssetLoaderSettings, ProcessError>> {
+        Box::pin(async move {
+            let AssetAction::Process { settings, .. } = meta.asset else {
+                return Err(ProcessError::WrongMetaType);
+            };
+            let json = parse_lottie(context.asset_bytes())
+                .map_err(|err| ProcessError::AssetSaveError(err.into()))?;
+            // Fail early on files the runtime loader would reject with the
+            // same settings
+            validate(json.clone(), &settings)
+                .map_err(|err| ProcessError::AssetSaveError(err.into()))?;
+            let minified = serde_json::to_vec(&json)
+                .map_err(|err| ProcessError::AssetSaveError(err.into()))?;
+            writer
+                .write_all(&minified)
+                .await
+                .map_err(|err| ProcessError::AssetSaveError(err.into()))?;
+            Ok(settings)
+        })
+    }
+}
+
+/// Parse a Lottie as the loader would. Images and fonts are resolved when
+/// loading, so missing ones don't fail strict mode here.
+fn validate(
+    json: serde_json::Value,
+    settings: &VelloAssetLoaderSettings,
+) -> Result<(), VelloAssetError> {
+    let vello_vector = load_lottie_with_resources(
+        json,
+        &LottieResources::default(),
+        settings.validate,
+        settings.lottie_compatibility,
+    )?;
+    if settings.parse_mode != VelloParseMode::Strict {
+        return Ok(());
+    }
+    let unsupported: Vec<String> = vello_vector
+        .report
+        .diagnostics()
+        .iter()
+        .filter(|diagnostic| {
+            !matches!(
+                diagnostic,
+                ImportDiagnostic::MissingImage { .. } | ImportDiagnostic::MissingFont { .. }
+            )
+        })
+        .map(ToString::to_string)
+        .collect();
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(VelloAssetError::Strict(unsupported.join("; ")))
+    }
+}
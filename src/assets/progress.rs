@@ -0,0 +1,94 @@
+use crate::VelloAsset;
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Progress of vector assets being loaded from files, from `0.0` to `1.0`.
+///
+/// Progress is reported by step: reading the file, reading each font and
+/// image it references, then parsing it on the
+/// [`AsyncComputeTaskPool`](bevy::tasks::AsyncComputeTaskPool), which large
+/// SVGs report chunk by chunk.
+#[derive(Resource, Clone, Default, Debug)]
+pub struct VelloLoadProgress(Arc<RwLock<HashMap<AssetPath<'static>, f32>>>);
+
+impl VelloLoadProgress {
+    /// The load progress of an asset, if it is being loaded from a file.
+    pub fn get(&self, handle: &Handle<VelloAsset>) -> Option<f32> {
+        let path = handle.path()?;
+        self.0.read().ok()?.get(path).copied()
+    }
+
+    pub(crate) fn set(&self, path: &AssetPath, progress: f32) {
+        if let Ok(mut map) = self.0.write() {
+            map.insert(path.clone_owned(), progress);
+        }
+    }
+}
+
+/// The steps of loading one file: reading it, reading each file it
+/// references, then parsing it. Steps are added as references are found.
+pub(crate) struct LoadSteps {
+    progress: VelloLoadProgress,
+    path: AssetPath<'static>,
+    done: usize,
+    total: usize,
+}
+
+impl LoadSteps {
+    /// Start reading a file, with parsing it left to do.
+    pub(crate) fn new(progress: &VelloLoadProgress, path: &AssetPath) -> Self {
+        let steps = Self {
+            progress: progress.clone(),
+            path: path.clone_owned(),
+            done: 0,
+            total: 2,
+        };
+        steps.report();
+        steps
+    }
+
+    /// Add steps to do before parsing, e.g. reading referenced files.
+    pub(crate) fn add(&mut self, steps: usize) {
+        self.total += steps;
+        self.report();
+    }
+
+    /// Finish the current step.
+    pub(crate) fn complete(&mut self) {
+        self.done = (self.done + 1).min(self.total);
+        self.report();
+    }
+
+    /// Report progress through the current step, from `0.0` to `1.0`, e.g.
+    /// from a task parsing the file.
+    pub(crate) fn within_step(&self) -> impl Fn(f32) + Send + Sync + 'static {
+        let (progress, path) = (self.progress.clone(), self.path.clone());
+        let (done, total) = (self.done as f32, self.total as f32);
+        move |fraction| progress.set(&path, (done + fraction.clamp(0.0, 1.0)) / total)
+    }
+
+    fn report(&self) {
+        let progress = self.done as f32 / self.total as f32;
+        self.progress.set(&self.path, progress);
+    }
+}
+
+/// Forget the progress of assets which were unloaded, so cycling through
+/// many files doesn't grow the progress map.
+pub(crate) fn prune_load_progress(
+    mut events: EventReader<AssetEvent<VelloAsset>>,
+    progress: Res<VelloLoadProgress>,
+    asset_server: Res<AssetServer>,
+) {
+    let removed = events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Removed { .. }));
+    if !removed {
+        return;
+    }
+    if let Ok(mut map) = progress.0.write() {
+        map.retain(|path, _| asset_server.get_path_id(path).is_some());
+    }
+}
@@ -0,0 +1,44 @@
+use super::{VelloAsset, VelloAssetError, VelloAssetLoader, VelloAssetLoaderSettings};
+use bevy::asset::io::Writer;
+use bevy::asset::saver::{AssetSaver, SavedAsset};
+use bevy::asset::AsyncWriteExt;
+use bevy::utils::BoxedFuture;
+
+/// An asset saver which writes Lottie assets back to Lottie JSON, for
+/// round-trip editing in tooling, e.g. with
+/// `LoadAndSave<VelloAssetLoader, LottieSaver>` or directly from an editor.
+///
+/// The saved JSON is the Lottie's source, so only Lotties loaded with
+/// [`keep_source`](VelloAssetLoaderSettings::keep_source) can be saved.
+/// Edits made with [`VelloAsset::source_mut`] and
+/// [`VelloAsset::hide_layer`] are saved, while edits made directly to the
+/// parsed composition are not.
+#[derive(Default)]
+pub struct LottieSaver;
+
+impl AssetSaver for LottieSaver {
+    type Asset = VelloAsset;
+
+    type Settings = ();
+
+    type OutputLoader = VelloAssetLoader;
+
+    type Error = VelloAssetError;
+
+    fn save<'a>(
+        &'a self,
+        writer: &'a mut Writer,
+        asset: SavedAsset<'a, Self::Asset>,
+        _settings: &'a Self::Settings,
+    ) -> BoxedFuture<'a, Result<VelloAssetLoaderSettings, Self::Error>> {
+        Box::pin(async move {
+            let source = asset.source().ok_or(VelloAssetError::Parse(
+                "Only Lotties loaded with `keep_source` can be saved".to_string(),
+            ))?;
+            let json = serde_json::to_vec(source)
+                .map_err(|err| VelloAssetError::Parse(format!("Unable to write JSON: {err}")))?;
+            writer.write_all(&json).await?;
+            Ok(VelloAssetLoaderSettings::default())
+        })
+    }
+}
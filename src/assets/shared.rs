@@ -0,0 +1,98 @@
+use bevy::utils::HashMap;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex, Weak};
+
+/// The content parsed data is derived from, as a BLAKE3 hash and a length.
+/// The hash is strong enough that colliding content is never expected, so
+/// the content itself isn't kept to compare.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct ContentKey([u8; 32], u64);
+
+impl ContentKey {
+    pub(crate) fn new(content: &[u8]) -> Self {
+        Self(*blake3::hash(content).as_bytes(), content.len() as u64)
+    }
+
+    /// The key of content made of several parts, e.g. font families and
+    /// their data, without concatenating them. Parts are length-prefixed, so
+    /// moving bytes between parts changes the key.
+    pub(crate) fn from_parts<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        let mut len = 0;
+        for part in parts {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part);
+            len += part.len() as u64;
+        }
+        Self(*hasher.finalize().as_bytes(), len)
+    }
+}
+
+/// Parsed data shared between identical assets, e.g. one animation loaded
+/// under several paths or labels. Entries are weak, so data is freed along
+/// with the last asset using it.
+pub(crate) struct SharedCache<T>(Lazy<Mutex<HashMap<ContentKey, Weak<T>>>>);
+
+impl<T> SharedCache<T> {
+    pub(crate) const fn new() -> Self {
+        Self(Lazy::new(Default::default))
+    }
+
+    /// The shared data for some content, or the result of `parse`, which is
+    /// shared from then on. Parsing happens outside the lock, so identical
+    /// assets loading at the same time may each parse once.
+    pub(crate) fn get_or_try_insert<E>(
+        &self,
+        key: ContentKey,
+        parse: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Arc<T>, E> {
+        let shared = self
+            .0
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(&key)?.upgrade());
+        if let Some(shared) = shared {
+            return Ok(shared);
+        }
+        let parsed = Arc::new(parse()?);
+        if let Ok(mut cache) = self.0.lock() {
+            cache.retain(|_, parsed| parsed.strong_count() > 0);
+            cache.insert(key, Arc::downgrade(&parsed));
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_identical_content() {
+        let cache = SharedCache::new();
+        let key = ContentKey::new(b"a");
+        let first = cache.get_or_try_insert(key, || Ok::<_, ()>(1)).unwrap();
+        let second = cache.get_or_try_insert(key, || Ok::<_, ()>(2)).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        let other = cache
+            .get_or_try_insert(ContentKey::new(b"b"), || Ok::<_, ()>(3))
+            .unwrap();
+        assert_eq!(*other, 3);
+    }
+
+    #[test]
+    fn drops_unused_entries() {
+        let cache = SharedCache::new();
+        let key = ContentKey::new(b"a");
+        drop(cache.get_or_try_insert(key, || Ok::<_, ()>(1)).unwrap());
+        let reparsed = cache.get_or_try_insert(key, || Ok::<_, ()>(2)).unwrap();
+        assert_eq!(*reparsed, 2);
+    }
+
+    #[test]
+    fn parts_are_keyed_with_their_boundaries() {
+        let key = |parts: [&[u8]; 2]| ContentKey::from_parts(parts);
+        assert_eq!(key([b"ab", b"c"]), key([b"ab", b"c"]));
+        assert_ne!(key([b"ab", b"c"]), key([b"a", b"bc"]));
+    }
+}
@@ -0,0 +1,369 @@
+use crate::assets::lottie_images::decode_image;
+use bevy::log::warn;
+use vello::kurbo::{Affine, BezPath, Cap, Join, Point, Rect, Stroke};
+use vello::peniko::{BlendMode, Brush, Color, ColorStop, Compose, Extend, Fill, Gradient, Mix};
+use vello::Scene;
+use vello_svg::usvg::{self, tiny_skia_path::PathSegment};
+
+/// Large SVGs are encoded this many nodes at a time, yielding in between.
+pub(crate) const SVG_CHUNK_NODES: usize = 512;
+
+/// The nodes drawing a parsed SVG, in order, with the transform of their
+/// parent. Groups which don't need a layer are flattened into their
+/// children, so an SVG wrapped in one group still splits into many nodes.
+pub(crate) fn svg_nodes(tree: &usvg::Tree) -> Vec<(Affine, &usvg::Node)> {
+    let mut nodes = vec![];
+    let view_box = to_affine(tree.view_box().to_transform(tree.size()));
+    flatten_group(tree.root(), view_box, &mut nodes);
+    nodes
+}
+
+fn flatten_group<'a>(
+    group: &'a usvg::Group,
+    transform: Affine,
+    nodes: &mut Vec<(Affine, &'a usvg::Node)>,
+) {
+    let transform = transform * to_affine(group.transform());
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(child) if !child.should_isolate() => {
+                flatten_group(child, transform, nodes);
+            }
+            _ => nodes.push((transform, node)),
+        }
+    }
+}
+
+/// Encode a whole parsed SVG into a scene, with its stroke widths scaled by
+/// `stroke_scale`.
+pub(crate) fn encode_tree(scene: &mut Scene, tree: &usvg::Tree, stroke_scale: f64) {
+    for (transform, node) in svg_nodes(tree) {
+        encode_node(scene, node, transform, stroke_scale);
+    }
+}
+
+/// Encode a node, drawn with the transform of its parent, with its stroke
+/// widths scaled by `stroke_scale`.
+pub(crate) fn encode_node(
+    scene: &mut Scene,
+    node: &usvg::Node,
+    transform: Affine,
+    stroke_scale: f64,
+) {
+    match node {
+        usvg::Node::Group(group) => encode_group(scene, group, transform, stroke_scale),
+        usvg::Node::Path(path) => encode_path(scene, path, transform, stroke_scale),
+        usvg::Node::Image(image) => encode_image(scene, image, transform, stroke_scale),
+        // Text is already shaped into paths
+        usvg::Node::Text(text) => {
+            encode_group(scene, text.flattened(), transform, stroke_scale);
+        }
+    }
+}
+
+fn encode_group(scene: &mut Scene, group: &usvg::Group, transform: Affine, stroke_scale: f64) {
+    let transform = transform * to_affine(group.transform());
+    let isolated = group.should_isolate();
+    // Masks and filters aren't supported, and are drawn as plain layers
+    let clip = group.clip_path().map(clip_outline);
+    if let Some(clip) = &clip {
+        scene.push_layer(Mix::Clip, 1.0, transform, clip);
+    }
+    if isolated {
+        let blend_mode = BlendMode::new(to_mix(group.blend_mode()), Compose::SrcOver);
+        let bounds = to_rect(group.layer_bounding_box());
+        scene.push_layer(blend_mode, group.opacity().get(), transform, &bounds);
+    }
+    for child in group.children() {
+        encode_node(scene, child, transform, stroke_scale);
+    }
+    if isolated {
+        scene.pop_layer();
+    }
+    if clip.is_some() {
+        scene.pop_layer();
+    }
+}
+
+/// The outline of a clip path, in the coordinates of the group it clips.
+/// Clip paths of clip paths are ignored.
+fn clip_outline(clip_path: &usvg::ClipPath) -> BezPath {
+    let mut outline = BezPath::new();
+    append_outlines(
+        clip_path.root(),
+        to_affine(clip_path.transform()),
+        &mut outline,
+    );
+    outline
+}
+
+fn append_outlines(group: &usvg::Group, transform: Affine, outline: &mut BezPath) {
+    let transform = transform * to_affine(group.transform());
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(child) => append_outlines(child, transform, outline),
+            usvg::Node::Path(path) => {
+                let mut path = to_bez_path(path.data());
+                path.apply_affine(transform);
+                outline.extend(path);
+            }
+            usvg::Node::Text(text) => append_outlines(text.flattened(), transform, outline),
+            usvg::Node::Image(_) => {}
+        }
+    }
+}
+
+fn encode_path(scene: &mut Scene, path: &usvg::Path, transform: Affine, stroke_scale: f64) {
+    if path.visibility() != usvg::Visibility::Visible {
+        return;
+    }
+    let outline = to_bez_path(path.data());
+    let fill = |scene: &mut Scene| {
+        if let Some(fill) = path.fill() {
+            encode_fill(scene, fill, transform, &outline);
+        }
+    };
+    let stroke = |scene: &mut Scene| {
+        if let Some(stroke) = path.stroke() {
+            encode_stroke(scene, stroke, transform, stroke_scale, &outline);
+        }
+    };
+    match path.paint_order() {
+        usvg::PaintOrder::FillAndStroke => {
+            fill(scene);
+            stroke(scene);
+        }
+        usvg::PaintOrder::StrokeAndFill => {
+            stroke(scene);
+            fill(scene);
+        }
+    }
+}
+
+fn encode_fill(scene: &mut Scene, fill: &usvg::Fill, transform: Affine, outline: &BezPath) {
+    let Some((brush, brush_transform)) = to_brush(fill.paint(), fill.opacity()) else {
+        return;
+    };
+    let rule = match fill.rule() {
+        usvg::FillRule::NonZero => Fill::NonZero,
+        usvg::FillRule::EvenOdd => Fill::EvenOdd,
+    };
+    scene.fill(rule, transform, &brush, brush_transform, outline);
+}
+
+fn encode_stroke(
+    scene: &mut Scene,
+    stroke: &usvg::Stroke,
+    transform: Affine,
+    stroke_scale: f64,
+    outline: &BezPath,
+) {
+    let Some((brush, brush_transform)) = to_brush(stroke.paint(), stroke.opacity()) else {
+        return;
+    };
+    let cap = match stroke.linecap() {
+        usvg::LineCap::Butt => Cap::Butt,
+        usvg::LineCap::Round => Cap::Round,
+        usvg::LineCap::Square => Cap::Square,
+    };
+    let join = match stroke.linejoin() {
+        usvg::LineJoin::Miter | usvg::LineJoin::MiterClip => Join::Miter,
+        usvg::LineJoin::Round => Join::Round,
+        usvg::LineJoin::Bevel => Join::Bevel,
+    };
+    let mut style = Stroke::new(stroke.width().get() as f64 * stroke_scale)
+        .with_caps(cap)
+        .with_join(join)
+        .with_miter_limit(stroke.miterlimit().get() as f64);
+    if let Some(dashes) = stroke.dasharray() {
+        style = style.with_dashes(
+            stroke.dashoffset() as f64,
+            dashes.iter().map(|dash| *dash as f64),
+        );
+    }
+    scene.stroke(&style, transform, &brush, brush_transform, outline);
+}
+
+fn encode_image(scene: &mut Scene, image: &usvg::Image, transform: Affine, stroke_scale: f64) {
+    if image.visibility() != usvg::Visibility::Visible {
+        return;
+    }
+    let view_box = image.view_box();
+    // Sliced images overflow their view box
+    if view_box.aspect.slice {
+        scene.push_layer(Mix::Clip, 1.0, transform, &to_rect(view_box.rect));
+    }
+    match image.kind() {
+        usvg::ImageKind::JPEG(data) | usvg::ImageKind::PNG(data) | usvg::ImageKind::GIF(data) => {
+            match decode_image(data) {
+                Some(decoded) => {
+                    let size = usvg::Size::from_wh(decoded.width as f32, decoded.height as f32);
+                    if let Some(size) = size {
+                        scene.draw_image(&decoded, transform * fit_view_box(view_box, size));
+                    }
+                }
+                None => warn!("unable to decode an image embedded in an SVG"),
+            }
+        }
+        usvg::ImageKind::SVG(tree) => {
+            let fit = transform * fit_view_box(view_box, tree.size());
+            for (node_transform, node) in svg_nodes(tree) {
+                encode_node(scene, node, fit * node_transform, stroke_scale);
+            }
+        }
+    }
+    if view_box.aspect.slice {
+        scene.pop_layer();
+    }
+}
+
+/// Place content of the given size in an image's view box, keeping its
+/// aspect ratio as the view box asks.
+fn fit_view_box(view_box: usvg::ViewBox, size: usvg::Size) -> Affine {
+    let content = usvg::ViewBox {
+        rect: size.to_non_zero_rect(0.0, 0.0),
+        aspect: view_box.aspect,
+    };
+    let origin = Point::new(view_box.rect.x() as f64, view_box.rect.y() as f64);
+    Affine::translate(origin.to_vec2()) * to_affine(content.to_transform(view_box.rect.size()))
+}
+
+/// The brush and brush transform of a paint. Patterns aren't supported.
+fn to_brush(paint: &usvg::Paint, opacity: usvg::Opacity) -> Option<(Brush, Option<Affine>)> {
+    let (gradient, base) = match paint {
+        usvg::Paint::Color(color) => return Some((to_color(*color, opacity).into(), None)),
+        usvg::Paint::LinearGradient(linear) => (
+            Gradient::new_linear(
+                (linear.x1() as f64, linear.y1() as f64),
+                (linear.x2() as f64, linear.y2() as f64),
+            ),
+            &***linear,
+        ),
+        usvg::Paint::RadialGradient(radial) => (
+            Gradient::new_two_point_radial(
+                (radial.fx() as f64, radial.fy() as f64),
+                0.0,
+                (radial.cx() as f64, radial.cy() as f64),
+                radial.r().get(),
+            ),
+            &***radial,
+        ),
+        usvg::Paint::Pattern(_) => return None,
+    };
+    let extend = match base.spread_method() {
+        usvg::SpreadMethod::Pad => Extend::Pad,
+        usvg::SpreadMethod::Reflect => Extend::Reflect,
+        usvg::SpreadMethod::Repeat => Extend::Repeat,
+    };
+    let stops: Vec<ColorStop> = base
+        .stops()
+        .iter()
+        .map(|stop| ColorStop {
+            offset: stop.offset().get(),
+            color: to_color(stop.color(), stop.opacity() * opacity),
+        })
+        .collect();
+    let gradient = gradient.with_extend(extend).with_stops(stops.as_slice());
+    Some((gradient.into(), Some(to_affine(base.transform()))))
+}
+
+fn to_color(color: usvg::Color, opacity: usvg::Opacity) -> Color {
+    Color::rgba8(color.red, color.green, color.blue, opacity.to_u8())
+}
+
+fn to_mix(blend_mode: usvg::BlendMode) -> Mix {
+    match blend_mode {
+        usvg::BlendMode::Normal => Mix::Normal,
+        usvg::BlendMode::Multiply => Mix::Multiply,
+        usvg::BlendMode::Screen => Mix::Screen,
+        usvg::BlendMode::Overlay => Mix::Overlay,
+        usvg::BlendMode::Darken => Mix::Darken,
+        usvg::BlendMode::Lighten => Mix::Lighten,
+        usvg::BlendMode::ColorDodge => Mix::ColorDodge,
+        usvg::BlendMode::ColorBurn => Mix::ColorBurn,
+        usvg::BlendMode::HardLight => Mix::HardLight,
+        usvg::BlendMode::SoftLight => Mix::SoftLight,
+        usvg::BlendMode::Difference => Mix::Difference,
+        usvg::BlendMode::Exclusion => Mix::Exclusion,
+        usvg::BlendMode::Hue => Mix::Hue,
+        usvg::BlendMode::Saturation => Mix::Saturation,
+        usvg::BlendMode::Color => Mix::Color,
+        usvg::BlendMode::Luminosity => Mix::Luminosity,
+    }
+}
+
+fn to_affine(transform: usvg::Transform) -> Affine {
+    let usvg::Transform {
+        sx,
+        ky,
+        kx,
+        sy,
+        tx,
+        ty,
+    } = transform;
+    Affine::new([sx, ky, kx, sy, tx, ty].map(f64::from))
+}
+
+fn to_rect(rect: usvg::NonZeroRect) -> Rect {
+    Rect::new(
+        rect.left() as f64,
+        rect.top() as f64,
+        rect.right() as f64,
+        rect.bottom() as f64,
+    )
+}
+
+fn to_bez_path(path: &usvg::tiny_skia_path::Path) -> BezPath {
+    let point = |point: usvg::tiny_skia_path::Point| Point::new(point.x as f64, point.y as f64);
+    let mut bez_path = BezPath::new();
+    for segment in path.segments() {
+        match segment {
+            PathSegment::MoveTo(to) => bez_path.move_to(point(to)),
+            PathSegment::LineTo(to) => bez_path.line_to(point(to)),
+            PathSegment::QuadTo(control, to) => bez_path.quad_to(point(control), point(to)),
+            PathSegment::CubicTo(first, second, to) => {
+                bez_path.curve_to(point(first), point(second), point(to))
+            }
+            PathSegment::Close => bez_path.close_path(),
+        }
+    }
+    bez_path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vello_svg::usvg::fontdb::Database;
+
+    fn parse(svg: &str) -> usvg::Tree {
+        usvg::Tree::from_str(svg, &usvg::Options::default(), &Database::new()).unwrap()
+    }
+
+    #[test]
+    fn transformed_groups_spanning_chunks_keep_their_transform() {
+        let rects = r#"<rect width="1" height="1"/>"#.repeat(SVG_CHUNK_NODES + 1);
+        let tree = parse(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20" viewBox="0 0 10 10">
+                <g transform="translate(5 0)">{rects}</g>
+            </svg>"#
+        ));
+        let nodes = svg_nodes(&tree);
+        assert_eq!(nodes.chunks(SVG_CHUNK_NODES).count(), 2);
+        let expected = Affine::scale(2.0) * Affine::translate((5.0, 0.0));
+        assert!(nodes.iter().all(|(transform, _)| *transform == expected));
+    }
+
+    #[test]
+    fn groups_drawn_in_a_layer_stay_whole() {
+        let tree = parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <g opacity="0.5"><rect width="1" height="1"/><rect width="2" height="2"/></g>
+                <rect width="3" height="3"/>
+            </svg>"#,
+        );
+        let nodes = svg_nodes(&tree);
+        assert_eq!(nodes.len(), 2);
+        assert!(matches!(nodes[0].1, usvg::Node::Group(_)));
+        assert!(matches!(nodes[1].1, usvg::Node::Path(_)));
+    }
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Maps font families referenced by SVG `<text>` to [`VelloFont`] asset
+/// paths.
+///
+/// Text using a mapped family is shaped with that font when the SVG loads.
+/// Mapped fonts become dependencies of the SVGs which use them, so they are
+/// included in load tracking and hot reloading. Families which aren't mapped
+/// are resolved against system fonts, if the loader settings opt into
+/// `system_fonts`.
+///
+/// [`VelloFont`]: crate::VelloFont
+#[derive(Resource, Clone, Default, Debug)]
+pub struct VelloSvgFontMap(Arc<RwLock<HashMap<String, String>>>);
+
+impl VelloSvgFontMap {
+    /// Map a font family to a [`VelloFont`](crate::VelloFont) asset path.
+    pub fn insert(&self, family: impl Into<String>, path: impl Into<String>) {
+        if let Ok(mut map) = self.0.write() {
+            map.insert(family.into(), path.into());
+        }
+    }
+
+    /// The asset path mapped to a font family.
+    pub fn get(&self, family: &str) -> Option<String> {
+        self.0.read().ok()?.get(family).cloned()
+    }
+}
+
+/// Collect the font families referenced by `font-family` attributes and
+/// style declarations.
+pub(crate) fn font_families(svg: &str) -> Vec<String> {
+    let mut families = Vec::new();
+    for (index, _) in svg.match_indices("font-family") {
+        let rest = svg[index + "font-family".len()..].trim_start();
+        let value = if let Some(rest) = rest.strip_prefix('=') {
+            // Attribute, e.g. `font-family="Inter, sans-serif"`
+            let rest = rest.trim_start();
+            let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                continue;
+            };
+            rest[1..].split(quote).next().unwrap_or_default()
+        } else if let Some(rest) = rest.strip_prefix(':') {
+            // Style declaration, e.g. `font-family: Inter;`
+            rest.split([';', '"', '}']).next().unwrap_or_default()
+        } else {
+            continue;
+        };
+        for family in value.split(',') {
+            let family = family.trim().trim_matches(['"', '\'']).trim();
+            if !family.is_empty() && !families.iter().any(|f| f == family) {
+                families.push(family.to_owned());
+            }
+        }
+    }
+    families
+}
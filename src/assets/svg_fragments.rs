@@ -0,0 +1,58 @@
+use roxmltree::{Document, Node};
+
+/// Build standalone SVG documents for the top-level `<symbol id="...">` and
+/// `<g id="...">` elements of an SVG, keyed by id. Each document keeps the
+/// top-level `<defs>` and `<style>` elements, so shared gradients and styles
+/// still resolve.
+pub(crate) fn svg_fragments(svg: &str) -> Vec<(String, String)> {
+    let Ok(document) = Document::parse(svg) else {
+        return vec![];
+    };
+    let root = document.root_element();
+    let Some(first_child) = root.first_child() else {
+        return vec![];
+    };
+    // The root start tag, with its size and namespaces, as authored
+    let root_start_tag = &svg[root.range().start..first_child.range().start];
+    let shared: String = root
+        .children()
+        .filter(|node| node.has_tag_name("defs") || node.has_tag_name("style"))
+        .map(|node| &svg[node.range()])
+        .collect();
+
+    root.children()
+        .filter(|node| node.has_tag_name("symbol") || node.has_tag_name("g"))
+        .filter_map(|node| {
+            let id = node.attribute("id")?;
+            let source = &svg[node.range()];
+            let document = if node.has_tag_name("symbol") {
+                symbol_document(node, id, source, &shared)?
+            } else {
+                format!("{root_start_tag}{shared}{source}</svg>")
+            };
+            Some((id.to_owned(), document))
+        })
+        .collect()
+}
+
+/// A document sized to a symbol's `viewBox`, which instantiates the symbol.
+fn symbol_document(node: Node, id: &str, source: &str, shared: &str) -> Option<String> {
+    let view_box = node.attribute("viewBox")?;
+    let [_, _, width, height] = parse_view_box(view_box)?;
+    Some(format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{width}" height="{height}" viewBox="0 0 {width} {height}">{shared}{source}<use href="#{id}" width="{width}" height="{height}"/></svg>"##
+    ))
+}
+
+fn parse_view_box(view_box: &str) -> Option<[f32; 4]> {
+    let mut values = view_box
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse::<f32>().ok());
+    Some([
+        values.next()??,
+        values.next()??,
+        values.next()??,
+        values.next()??,
+    ])
+}
@@ -0,0 +1,143 @@
+use base64::Engine;
+use bevy::utils::HashMap;
+use roxmltree::Document;
+use std::borrow::Cow;
+
+/// The hrefs of `<image>` elements which reference files, rather than data
+/// URIs or remote URLs.
+pub(crate) fn image_hrefs(svg: &str) -> Vec<String> {
+    let Ok(document) = Document::parse(svg) else {
+        return vec![];
+    };
+    document
+        .descendants()
+        .filter(|node| node.has_tag_name("image"))
+        .filter_map(image_href)
+        .filter(|href| is_relative(href))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Fonts declared by `@font-face` rules in `<style>` blocks, as families and
+/// the first `url(...)` of their `src`.
+pub(crate) fn font_faces(svg: &str) -> Vec<(String, String)> {
+    let Ok(document) = Document::parse(svg) else {
+        return vec![];
+    };
+    let css: String = document
+        .descendants()
+        .filter(|node| node.has_tag_name("style"))
+        .flat_map(|node| node.children().filter_map(|child| child.text()))
+        .collect();
+
+    let mut faces = vec![];
+    for (index, _) in css.match_indices("@font-face") {
+        let rest = &css[index..];
+        let Some((_, block)) = rest.split_once('{') else {
+            continue;
+        };
+        let block = block.split('}').next().unwrap_or_default();
+        let mut family = None;
+        let mut url = None;
+        for declaration in block.split(';') {
+            let Some((property, value)) = declaration.split_once(':') else {
+                continue;
+            };
+            match property.trim() {
+                "font-family" => family = Some(unquote(value.trim()).to_owned()),
+                "src" => {
+                    url = value
+                        .split_once("url(")
+                        .and_then(|(_, url)| url.split_once(')'))
+                        .map(|(url, _)| unquote(url.trim()).to_owned());
+                }
+                _ => {}
+            }
+        }
+        if let (Some(family), Some(url)) = (family, url) {
+            faces.push((family, url));
+        }
+    }
+    faces
+}
+
+/// Decode a `data:` URI, e.g. an embedded `@font-face` font.
+pub(crate) fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+    let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+    if header.ends_with(";base64") {
+        base64::engine::general_purpose::STANDARD.decode(data).ok()
+    } else {
+        Some(data.as_bytes().to_vec())
+    }
+}
+
+/// Replace the hrefs of `<image>` elements with data URIs of their bytes,
+/// keyed by href, so usvg doesn't read them from the file system.
+pub(crate) fn embed_images<'a>(svg: &'a str, images: &HashMap<String, Vec<u8>>) -> Cow<'a, str> {
+    if images.is_empty() {
+        return Cow::Borrowed(svg);
+    }
+    let Ok(document) = Document::parse(svg) else {
+        return Cow::Borrowed(svg);
+    };
+    let mut edits = vec![];
+    for node in document
+        .descendants()
+        .filter(|node| node.has_tag_name("image"))
+    {
+        let Some(href) = image_href(node) else {
+            continue;
+        };
+        let Some(bytes) = images.get(href) else {
+            continue;
+        };
+        // Find the href value in the element's start tag
+        let start = node.range().start;
+        let start_tag = &svg[start..];
+        let start_tag = &start_tag[..start_tag.find('>').unwrap_or(start_tag.len())];
+        let Some(at) = start_tag
+            .find(&format!("\"{href}\""))
+            .or_else(|| start_tag.find(&format!("'{href}'")))
+        else {
+            continue;
+        };
+        let value = start + at + 1;
+        let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+        edits.push((
+            value..value + href.len(),
+            format!("data:{};base64,{data}", mime_type(href)),
+        ));
+    }
+
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut embedded = svg.to_owned();
+    for (range, replacement) in edits {
+        embedded.replace_range(range, &replacement);
+    }
+    Cow::Owned(embedded)
+}
+
+fn image_href<'a>(node: roxmltree::Node<'a, '_>) -> Option<&'a str> {
+    node.attribute("href")
+        .or_else(|| node.attribute(("http://www.w3.org/1999/xlink", "href")))
+}
+
+/// Whether an href is a path relative to the SVG.
+pub(crate) fn is_relative(href: &str) -> bool {
+    !(href.is_empty() || href.starts_with('#') || href.starts_with("data:") || href.contains("://"))
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim_matches(['"', '\''])
+}
+
+fn mime_type(href: &str) -> &'static str {
+    let ext = href.rsplit('.').next().unwrap_or_default();
+    match ext.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => "image/png",
+    }
+}
@@ -0,0 +1,52 @@
+use roxmltree::Document;
+use std::borrow::Cow;
+
+/// Apply style settings to an SVG document before it is parsed.
+///
+/// `<style>` blocks are removed when `keep_styles` is false, and
+/// `style_sheet` is appended as a last `<style>` block, so its rules win over
+/// the document's own rules of the same specificity.
+pub(crate) fn style_svg<'a>(svg: &'a str, keep_styles: bool, style_sheet: &str) -> Cow<'a, str> {
+    if keep_styles && style_sheet.trim().is_empty() {
+        return Cow::Borrowed(svg);
+    }
+    let Ok(document) = Document::parse(svg) else {
+        // Let the parser report the error
+        return Cow::Borrowed(svg);
+    };
+    let root = document.root_element();
+    let mut edits: Vec<(std::ops::Range<usize>, String)> = vec![];
+    if !keep_styles {
+        edits.extend(
+            root.descendants()
+                .filter(|node| node.has_tag_name("style"))
+                .map(|node| (node.range(), String::new())),
+        );
+    }
+    if !style_sheet.trim().is_empty() {
+        // Insert before the root end tag, or expand a self-closing root
+        let root_source = &svg[root.range()];
+        let style = format!("<style><![CDATA[{style_sheet}]]></style>");
+        let edit = match root_source.rfind("</") {
+            Some(end_tag) if root.has_children() => {
+                let at = root.range().start + end_tag;
+                (at..at, style)
+            }
+            _ => {
+                let end = root.range().end;
+                let self_closing = root_source.trim_end_matches('>').trim_end_matches('/');
+                let start = root.range().start + self_closing.len();
+                (start..end, format!(">{style}</{}>", root.tag_name().name()))
+            }
+        };
+        edits.push(edit);
+    }
+
+    // Apply edits back to front, so earlier ranges stay valid
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut styled = svg.to_owned();
+    for (range, replacement) in edits {
+        styled.replace_range(range, &replacement);
+    }
+    Cow::Owned(styled)
+}
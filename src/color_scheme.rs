@@ -0,0 +1,94 @@
+//! Automatic switching between light and dark themes.
+
+use crate::Theme;
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowTheme, WindowThemeChanged};
+
+/// The color scheme of the app. Entities with [`ColorSchemeThemes`] switch
+/// their [`Theme`] when this changes.
+#[derive(Resource, PartialEq, Eq, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Resource)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Insert this resource to keep [`ColorScheme`] in sync with the OS (or
+/// browser) preference reported by the window. The primary window's current
+/// theme applies as soon as it is inserted.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct FollowWindowTheme;
+
+/// An event emitted when the [`ColorScheme`] changes.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ColorSchemeChanged {
+    pub scheme: ColorScheme,
+}
+
+/// Add this component to a `VelloAssetBundle` entity to pick its [`Theme`]
+/// from the current [`ColorScheme`].
+#[derive(PartialEq, Component, Default, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct ColorSchemeThemes {
+    pub light: Theme,
+    pub dark: Theme,
+}
+
+impl ColorSchemeThemes {
+    pub fn new(light: Theme, dark: Theme) -> Self {
+        Self { light, dark }
+    }
+
+    /// The theme for a color scheme.
+    pub fn get(&self, scheme: ColorScheme) -> &Theme {
+        match scheme {
+            ColorScheme::Light => &self.light,
+            ColorScheme::Dark => &self.dark,
+        }
+    }
+}
+
+pub(crate) fn follow_window_theme(
+    follow: Option<Res<FollowWindowTheme>>,
+    mut scheme: ResMut<ColorScheme>,
+    mut theme_events: EventReader<WindowThemeChanged>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+) {
+    let changed = theme_events.read().last().map(|event| event.theme);
+    let Some(follow) = follow else {
+        return;
+    };
+    // Windows only report changes, so the current theme is read when
+    // following starts
+    let theme = if follow.is_added() {
+        changed.or_else(|| windows.get_single().ok()?.window_theme)
+    } else {
+        changed
+    };
+    let Some(theme) = theme else {
+        return;
+    };
+    let target = match theme {
+        WindowTheme::Light => ColorScheme::Light,
+        WindowTheme::Dark => ColorScheme::Dark,
+    };
+    scheme.set_if_neq(target);
+}
+
+pub(crate) fn apply_color_scheme(
+    mut commands: Commands,
+    scheme: Res<ColorScheme>,
+    mut events: EventWriter<ColorSchemeChanged>,
+    query: Query<(Entity, Ref<ColorSchemeThemes>)>,
+) {
+    let scheme_changed = scheme.is_changed() && !scheme.is_added();
+    if scheme_changed {
+        events.send(ColorSchemeChanged { scheme: *scheme });
+    }
+    for (entity, themes) in query.iter() {
+        if scheme.is_changed() || themes.is_changed() {
+            commands.entity(entity).insert(themes.get(*scheme).clone());
+        }
+    }
+}
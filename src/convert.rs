@@ -0,0 +1,116 @@
+//! Extension traits to convert between Bevy and vello math and color types.
+
+use bevy::math::Affine2;
+use bevy::prelude::*;
+use vello::{kurbo, peniko};
+
+/// Convert a Bevy type to its [`kurbo`] equivalent.
+pub trait ToKurbo {
+    type Kurbo;
+
+    fn to_kurbo(&self) -> Self::Kurbo;
+}
+
+/// Convert a Bevy type to its [`peniko`] equivalent.
+pub trait ToPeniko {
+    type Peniko;
+
+    fn to_peniko(&self) -> Self::Peniko;
+}
+
+/// Convert a [`kurbo`] or [`peniko`] type to its Bevy equivalent.
+pub trait ToBevy {
+    type Bevy;
+
+    fn to_bevy(&self) -> Self::Bevy;
+}
+
+impl ToKurbo for Vec2 {
+    type Kurbo = kurbo::Point;
+
+    fn to_kurbo(&self) -> kurbo::Point {
+        kurbo::Point::new(self.x as f64, self.y as f64)
+    }
+}
+
+impl ToKurbo for Rect {
+    type Kurbo = kurbo::Rect;
+
+    fn to_kurbo(&self) -> kurbo::Rect {
+        kurbo::Rect::new(
+            self.min.x as f64,
+            self.min.y as f64,
+            self.max.x as f64,
+            self.max.y as f64,
+        )
+    }
+}
+
+impl ToKurbo for Affine2 {
+    type Kurbo = kurbo::Affine;
+
+    fn to_kurbo(&self) -> kurbo::Affine {
+        let [a, b, c, d] = self.matrix2.to_cols_array();
+        let [e, f] = self.translation.to_array();
+        kurbo::Affine::new([a, b, c, d, e, f].map(|v| v as f64))
+    }
+}
+
+impl ToPeniko for Color {
+    type Peniko = peniko::Color;
+
+    fn to_peniko(&self) -> peniko::Color {
+        peniko::Color::rgba(
+            self.r().into(),
+            self.g().into(),
+            self.b().into(),
+            self.a().into(),
+        )
+    }
+}
+
+impl ToBevy for kurbo::Point {
+    type Bevy = Vec2;
+
+    fn to_bevy(&self) -> Vec2 {
+        Vec2::new(self.x as f32, self.y as f32)
+    }
+}
+
+impl ToBevy for kurbo::Vec2 {
+    type Bevy = Vec2;
+
+    fn to_bevy(&self) -> Vec2 {
+        Vec2::new(self.x as f32, self.y as f32)
+    }
+}
+
+impl ToBevy for kurbo::Rect {
+    type Bevy = Rect;
+
+    fn to_bevy(&self) -> Rect {
+        Rect::new(
+            self.x0 as f32,
+            self.y0 as f32,
+            self.x1 as f32,
+            self.y1 as f32,
+        )
+    }
+}
+
+impl ToBevy for kurbo::Affine {
+    type Bevy = Affine2;
+
+    fn to_bevy(&self) -> Affine2 {
+        let [a, b, c, d, e, f] = self.as_coeffs().map(|v| v as f32);
+        Affine2::from_cols_array(&[a, b, c, d, e, f])
+    }
+}
+
+impl ToBevy for peniko::Color {
+    type Bevy = Color;
+
+    fn to_bevy(&self) -> Color {
+        Color::rgba_u8(self.r, self.g, self.b, self.a)
+    }
+}
@@ -1,6 +1,9 @@
 //! Logic for rendering debug visualizations
 
-use crate::{CoordinateSpace, VelloAsset, VelloFont, VelloText, ZFunction};
+use crate::{
+    CoordinateSpace, VelloAsset, VelloDefaultUnits, VelloDefaultYAxis, VelloFont, VelloSize,
+    VelloText, VelloUnits, VelloYAxis, ZFunction,
+};
 use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
 
@@ -31,10 +34,14 @@ fn render_velloasset_debug(
             &CoordinateSpace,
             &ZFunction,
             &DebugVisualizations,
+            (Option<&VelloUnits>, Option<&VelloSize>),
+            Option<&VelloYAxis>,
         ),
         Without<Node>,
     >,
     vectors: Res<Assets<VelloAsset>>,
+    default_units: Res<VelloDefaultUnits>,
+    default_y_axis: Res<VelloDefaultYAxis>,
     query_cam: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<Camera2d>>,
     mut gizmos: Gizmos,
 ) {
@@ -43,11 +50,16 @@ fn render_velloasset_debug(
     };
 
     // Show vectors
-    for (vector, gtransform, space, z_fn, _) in query_vectors
+    for (vector, gtransform, space, z_fn, _, (units, size), y_axis) in query_vectors
         .iter()
-        .filter(|(_, _, _, _, d)| **d == DebugVisualizations::Visible)
+        .filter(|(_, _, _, _, d, _, _)| **d == DebugVisualizations::Visible)
     {
         if let Some(vector) = vectors.get(vector) {
+            let gtransform = &y_axis.unwrap_or(&default_y_axis.0).apply(gtransform);
+            let gtransform = &match size {
+                Some(size) => size.apply(vector, gtransform),
+                None => units.unwrap_or(&default_units.0).apply(vector, gtransform),
+            };
             match space {
                 CoordinateSpace::WorldSpace => {
                     let rect = vector.bb_in_world_space(gtransform);
@@ -70,6 +82,8 @@ fn render_velloasset_debug(
                     };
                     draw_asset_debug(&mut gizmos, projection, z_fn, origin, rect.size());
                 }
+                // Gizmos are drawn for the 2D camera only
+                CoordinateSpace::Billboard3d => {}
             }
         }
     }
@@ -123,6 +137,7 @@ fn render_vellotext_debug(
                         rect.size() * Vec2::new(1.0, -1.0), // Flip Y
                     );
                 }
+                CoordinateSpace::Billboard3d => {}
             }
         }
     }
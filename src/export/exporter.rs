@@ -0,0 +1,116 @@
+use super::FrameSink;
+use crate::render::{ImageRenderError, VelloImageRenderer};
+use crate::{PlaybackOptions, Theme, VectorFile, VelloAsset};
+use bevy::prelude::*;
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::utils::thiserror::{self, Error};
+use std::ops::Range;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("Could not write frame: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not render frame: {0}")]
+    Render(#[from] ImageRenderError),
+    #[error("Could not encode frame: {0}")]
+    Encode(String),
+    #[error("Could not find {0}")]
+    NotFound(String),
+}
+
+/// Renders vector assets frame-by-frame outside of the normal ECS flow, and
+/// writes the frames to a [`FrameSink`].
+pub struct VelloExporter {
+    renderer: VelloImageRenderer,
+}
+
+impl VelloExporter {
+    /// Create an exporter on the given render device.
+    pub fn new(device: &RenderDevice) -> Result<Self, ExportError> {
+        Ok(Self {
+            renderer: VelloImageRenderer::new(device)?,
+        })
+    }
+
+    /// Render a single frame of an asset, stretched to `size` pixels.
+    /// Returns tightly packed, non-premultiplied RGBA8 pixels.
+    pub fn render_frame(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        asset: &VelloAsset,
+        frame: f64,
+        size: UVec2,
+        theme: Option<&Theme>,
+    ) -> Result<Vec<u8>, ExportError> {
+        Ok(self
+            .renderer
+            .render_asset_to_pixels(device, queue, asset, frame, size, theme)?)
+    }
+
+    /// Render the given frames of an asset and write them to a sink, e.g. a
+    /// [`PngSequence`](super::PngSequence).
+    #[allow(clippy::too_many_arguments)]
+    pub fn export(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        asset: &VelloAsset,
+        frames: impl IntoIterator<Item = f64>,
+        size: UVec2,
+        theme: Option<&Theme>,
+        sink: &mut impl FrameSink,
+    ) -> Result<(), ExportError> {
+        for (index, frame) in frames.into_iter().enumerate() {
+            let pixels = self.render_frame(device, queue, asset, frame, size, theme)?;
+            sink.write_frame(index, size, &pixels)?;
+        }
+        sink.finish()
+    }
+
+    /// Export the asset of an entity with its [`Theme`], e.g. to share an
+    /// animation state. When `frames` is `None`, the segments of the entity's
+    /// [`PlaybackOptions`] are exported at the composition frame rate.
+    pub fn export_entity(
+        &mut self,
+        world: &World,
+        entity: Entity,
+        frames: Option<Range<f64>>,
+        size: UVec2,
+        sink: &mut impl FrameSink,
+    ) -> Result<(), ExportError> {
+        let device = world
+            .get_resource::<RenderDevice>()
+            .ok_or(ExportError::NotFound("render device".to_string()))?;
+        let queue = world
+            .get_resource::<RenderQueue>()
+            .ok_or(ExportError::NotFound("render queue".to_string()))?;
+        let handle = world
+            .get::<Handle<VelloAsset>>(entity)
+            .ok_or(ExportError::NotFound(format!(
+                "asset for entity {entity:?}"
+            )))?;
+        let asset = world
+            .resource::<Assets<VelloAsset>>()
+            .get(handle)
+            .ok_or(ExportError::NotFound(format!("loaded asset {handle:?}")))?;
+        let theme = world.get::<Theme>(entity);
+
+        let frames = match (frames, &asset.data) {
+            (Some(frames), _) => frames,
+            (None, VectorFile::Svg { .. } | VectorFile::Image { .. }) => 0.0..1.0,
+            (None, VectorFile::Lottie { .. }) => world
+                .get::<PlaybackOptions>(entity)
+                .map(|options| options.segments.clone())
+                .unwrap_or_default()
+                .frames(asset),
+        };
+        let Range { start, end } = frames;
+        let frames = (0..)
+            .map(move |i| start + i as f64)
+            .take_while(move |frame| *frame < end);
+
+        self.export(device, queue, asset, frames, size, theme, sink)
+    }
+}
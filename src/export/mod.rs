@@ -0,0 +1,12 @@
+//! Offline export of vector assets to image sequences and SVG.
+//!
+//! This module requires the `export` feature.
+
+mod exporter;
+pub use exporter::{ExportError, VelloExporter};
+
+mod sink;
+pub use sink::{ApngFile, FrameSink, GifFile, PngSequence};
+
+mod svg;
+pub use svg::{asset_to_svg, scene_to_svg};
@@ -0,0 +1,185 @@
+use super::ExportError;
+use bevy::prelude::*;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+/// A destination for exported frames. Implement this to pipe frames into
+/// your own encoder.
+pub trait FrameSink {
+    /// Write a frame of tightly packed, non-premultiplied RGBA8 pixels.
+    fn write_frame(&mut self, index: usize, size: UVec2, rgba: &[u8]) -> Result<(), ExportError>;
+
+    /// Called once every frame has been written.
+    fn finish(&mut self) -> Result<(), ExportError> {
+        Ok(())
+    }
+}
+
+/// Writes every frame to a numbered PNG file in a directory, e.g.
+/// `frame_00042.png`.
+pub struct PngSequence {
+    pub directory: PathBuf,
+    pub prefix: String,
+}
+
+impl PngSequence {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            prefix: "frame_".to_string(),
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+}
+
+impl FrameSink for PngSequence {
+    fn write_frame(&mut self, index: usize, size: UVec2, rgba: &[u8]) -> Result<(), ExportError> {
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self
+            .directory
+            .join(format!("{}{index:05}.png", self.prefix));
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), size.x, size.y);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(rgba))
+            .map_err(|err| ExportError::Encode(err.to_string()))
+    }
+}
+
+/// Encodes every frame into an animated GIF file.
+pub struct GifFile {
+    pub path: PathBuf,
+    /// The playback rate of the encoded animation.
+    pub fps: f64,
+    encoder: Option<gif::Encoder<BufWriter<File>>>,
+}
+
+impl GifFile {
+    pub fn new(path: impl Into<PathBuf>, fps: f64) -> Self {
+        Self {
+            path: path.into(),
+            fps,
+            encoder: None,
+        }
+    }
+}
+
+impl FrameSink for GifFile {
+    fn write_frame(&mut self, _index: usize, size: UVec2, rgba: &[u8]) -> Result<(), ExportError> {
+        // GIF delays are in hundredths of a second
+        let delay = frame_delay(self.fps, 100.0)?;
+        let (width, height) = u16_size(size)?;
+        if self.encoder.is_none() {
+            let file = BufWriter::new(File::create(&self.path)?);
+            let mut encoder = gif::Encoder::new(file, width, height, &[])
+                .map_err(|err| ExportError::Encode(err.to_string()))?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(|err| ExportError::Encode(err.to_string()))?;
+            self.encoder = Some(encoder);
+        }
+        let encoder = self.encoder.as_mut().expect("encoder is initialized");
+        let mut pixels = rgba.to_vec();
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+        frame.delay = delay;
+        frame.dispose = gif::DisposalMethod::Background;
+        encoder
+            .write_frame(&frame)
+            .map_err(|err| ExportError::Encode(err.to_string()))
+    }
+
+    fn finish(&mut self) -> Result<(), ExportError> {
+        // Dropping the encoder writes the trailer
+        self.encoder.take();
+        Ok(())
+    }
+}
+
+/// Encodes every frame into an animated PNG file. Frames are buffered in
+/// memory until the export finishes, since APNG requires the frame count up
+/// front.
+pub struct ApngFile {
+    pub path: PathBuf,
+    /// The playback rate of the encoded animation.
+    pub fps: f64,
+    size: UVec2,
+    frames: Vec<Vec<u8>>,
+}
+
+impl ApngFile {
+    pub fn new(path: impl Into<PathBuf>, fps: f64) -> Self {
+        Self {
+            path: path.into(),
+            fps,
+            size: UVec2::ZERO,
+            frames: vec![],
+        }
+    }
+}
+
+impl FrameSink for ApngFile {
+    fn write_frame(&mut self, _index: usize, size: UVec2, rgba: &[u8]) -> Result<(), ExportError> {
+        // Frames are only encoded when the export finishes, so bad settings
+        // fail on the first frame instead
+        frame_delay(self.fps, 1000.0)?;
+        self.size = size;
+        self.frames.push(rgba.to_vec());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExportError> {
+        let frames = std::mem::take(&mut self.frames);
+        if frames.is_empty() {
+            return Ok(());
+        }
+        let file = BufWriter::new(File::create(&self.path)?);
+        let delay_ms = frame_delay(self.fps, 1000.0)?;
+        encode_apng(file, self.size, delay_ms, &frames)
+            .map_err(|err| ExportError::Encode(err.to_string()))
+    }
+}
+
+/// The delay between frames played at `fps`, in `units_per_second`, for
+/// formats which store it in 16 bits.
+fn frame_delay(fps: f64, units_per_second: f64) -> Result<u16, ExportError> {
+    let delay = (units_per_second / fps).round();
+    u16::try_from(delay as i64)
+        .ok()
+        .filter(|delay| *delay > 0)
+        .ok_or_else(|| ExportError::Encode(format!("{fps} fps can't be encoded")))
+}
+
+/// The size of a frame, for formats which store it in 16 bits.
+fn u16_size(size: UVec2) -> Result<(u16, u16), ExportError> {
+    let too_large = |_| ExportError::Encode(format!("{}x{} frames are too large", size.x, size.y));
+    Ok((
+        u16::try_from(size.x).map_err(too_large)?,
+        u16::try_from(size.y).map_err(too_large)?,
+    ))
+}
+
+/// A helper method to encode frames as an endlessly looping APNG.
+fn encode_apng(
+    file: BufWriter<File>,
+    size: UVec2,
+    delay_ms: u16,
+    frames: &[Vec<u8>],
+) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(file, size.x, size.y);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0)?;
+    encoder.set_frame_delay(delay_ms, 1000)?;
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(frame)?;
+    }
+    writer.finish()
+}
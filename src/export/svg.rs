@@ -0,0 +1,568 @@
+use crate::render::render_asset;
+use crate::{Theme, VectorFile, VelloAsset};
+use base64::Engine;
+use bevy::prelude::*;
+use std::fmt::Write;
+use std::io::Cursor;
+use vello::kurbo::Affine;
+use vello::peniko::{Color, Extend, Image};
+use vello::Scene;
+use vello_encoding::{Encoding, Patch, Style, Transform as EncodedTransform};
+use vello_svg::usvg;
+
+// Path tag bits
+const SEGMENT_TYPE: u8 = 0x3;
+const SUBPATH_END: u8 = 0x4;
+const F32: u8 = 0x8;
+const PATH: u8 = 0x10;
+const TRANSFORM: u8 = 0x20;
+const STYLE: u8 = 0x40;
+
+// Draw tags
+const COLOR: u32 = 0x44;
+const LINEAR_GRADIENT: u32 = 0x114;
+const RADIAL_GRADIENT: u32 = 0x29c;
+const IMAGE: u32 = 0x248;
+const BEGIN_CLIP: u32 = 0x9;
+const END_CLIP: u32 = 0x21;
+
+// Style flags
+const STROKE: u32 = 0x8000_0000;
+const EVEN_ODD: u32 = 0x4000_0000;
+const JOIN_MASK: u32 = 0x3000_0000;
+const JOIN_MITER: u32 = 0x1000_0000;
+const JOIN_ROUND: u32 = 0x2000_0000;
+const CAP_MASK: u32 = 0x0300_0000;
+const CAP_SQUARE: u32 = 0x0100_0000;
+const CAP_ROUND: u32 = 0x0200_0000;
+
+/// Serialize a scene to an SVG document of `size` pixels, e.g. to export a
+/// still or to compare renderer output against golden files.
+///
+/// Fills, strokes, gradients, images, clips and blend modes are written.
+/// Text isn't: glyph runs are skipped with a warning, since their outlines are
+/// only resolved by the renderer.
+pub fn scene_to_svg(scene: &Scene, size: Vec2) -> String {
+    if !scene.encoding().resources.glyph_runs.is_empty() {
+        warn_once!("text in vello scenes isn't exported to SVG, and is skipped");
+    }
+    let mut writer = SvgWriter::default();
+    writer.write_encoding(scene.encoding());
+    let SvgWriter { defs, body, .. } = writer;
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}"><defs>{defs}</defs>{body}</svg>"#,
+        w = size.x,
+        h = size.y,
+    )
+}
+
+/// Serialize a frame of an asset to an SVG document at the asset's size.
+///
+/// SVG assets are written from their parsed SVG, with text as paths, and
+/// ignore the frame and theme. Lottie frames are written from their scene
+/// with [`scene_to_svg`], so text layers are skipped. Images ignore the frame.
+pub fn asset_to_svg(asset: &VelloAsset, frame: f64, theme: Option<&Theme>) -> String {
+    let size = Vec2::new(asset.width, asset.height);
+    if let VectorFile::Svg { tree, .. } = &asset.data {
+        return tree_to_svg(tree, size);
+    }
+    let mut scene = Scene::new();
+    let baked = asset.baked_scale();
+    let transform = Affine::scale_non_uniform(baked.x as f64, baked.y as f64);
+    render_asset(
+        &mut velato::Renderer::new(),
+        asset,
+        frame,
+        transform,
+        theme,
+        &mut scene,
+    );
+    scene_to_svg(&scene, size)
+}
+
+/// Write a parsed SVG back out, stretched to `size` if its scale was baked.
+fn tree_to_svg(tree: &usvg::Tree, size: Vec2) -> String {
+    let svg = tree.to_string(&usvg::WriteOptions::default());
+    let (width, height) = (tree.size().width(), tree.size().height());
+    if size == Vec2::new(width, height) {
+        return svg;
+    }
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {width} {height}" preserveAspectRatio="none">{svg}</svg>"#,
+        w = size.x,
+        h = size.y,
+    )
+}
+
+/// A decoded path, with the transforms of its points and of its brush.
+struct EncodedPath {
+    data: String,
+    transform: Affine,
+    brush_transform: Affine,
+    style: Style,
+}
+
+#[derive(Default)]
+struct SvgWriter {
+    defs: String,
+    body: String,
+    next_id: usize,
+}
+
+impl SvgWriter {
+    fn write_encoding(&mut self, encoding: &Encoding) {
+        let mut ramps = encoding
+            .resources
+            .patches
+            .iter()
+            .filter_map(|patch| match patch {
+                Patch::Ramp { stops, extend, .. } => {
+                    Some((&encoding.resources.color_stops[stops.clone()], *extend))
+                }
+                _ => None,
+            });
+        let mut images = encoding
+            .resources
+            .patches
+            .iter()
+            .filter_map(|patch| match patch {
+                Patch::Image { image, .. } => Some(image),
+                _ => None,
+            });
+
+        let mut draw_data = words(&encoding.draw_data);
+        for (tag, path) in encoding.draw_tags.iter().zip(decode_paths(encoding)) {
+            // The size of a draw's data, in words, is packed into its tag
+            let size = ((tag.0 >> 2) & 0x7) as usize;
+            let data: Vec<u32> = draw_data.by_ref().take(size).collect();
+            match tag.0 {
+                COLOR => {
+                    let paint = premultiplied_color(data[0]);
+                    self.write_path(&path, &paint);
+                }
+                LINEAR_GRADIENT => {
+                    let Some((stops, extend)) = ramps.next() else {
+                        continue;
+                    };
+                    let [x1, y1, x2, y2] = [1, 2, 3, 4].map(|i| f32::from_bits(data[i]));
+                    let id = self.id("gradient");
+                    let _ = write!(
+                        self.defs,
+                        r#"<linearGradient id="{id}" gradientUnits="userSpaceOnUse" x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}""#
+                    );
+                    self.write_gradient_end(&path, stops, extend, "linearGradient");
+                    self.write_path(&path, &format!("url(#{id})"));
+                }
+                RADIAL_GRADIENT => {
+                    let Some((stops, extend)) = ramps.next() else {
+                        continue;
+                    };
+                    let [fx, fy, cx, cy, fr, r] =
+                        [1, 2, 3, 4, 5, 6].map(|i| f32::from_bits(data[i]));
+                    let id = self.id("gradient");
+                    let _ = write!(
+                        self.defs,
+                        r#"<radialGradient id="{id}" gradientUnits="userSpaceOnUse" fx="{fx}" fy="{fy}" fr="{fr}" cx="{cx}" cy="{cy}" r="{r}""#
+                    );
+                    self.write_gradient_end(&path, stops, extend, "radialGradient");
+                    self.write_path(&path, &format!("url(#{id})"));
+                }
+                IMAGE => {
+                    let Some(image) = images.next() else {
+                        continue;
+                    };
+                    let Some(href) = png_data_uri(image) else {
+                        continue;
+                    };
+                    let _ = write!(
+                        self.body,
+                        r#"<image width="{}" height="{}" transform="{}" opacity="{}" href="{href}"/>"#,
+                        image.width,
+                        image.height,
+                        matrix(path.brush_transform),
+                        image.alpha,
+                    );
+                }
+                BEGIN_CLIP => {
+                    let (blend, alpha) = (data[0], f32::from_bits(data[1]));
+                    let id = self.id("clip");
+                    let rule = fill_rule(&path.style);
+                    let _ = write!(
+                        self.defs,
+                        r#"<clipPath id="{id}"><path d="{}" transform="{}" clip-rule="{rule}"/></clipPath>"#,
+                        path.data,
+                        matrix(path.transform),
+                    );
+                    let _ = write!(self.body, r#"<g clip-path="url(#{id})""#);
+                    if alpha < 1.0 {
+                        let _ = write!(self.body, r#" opacity="{alpha}""#);
+                    }
+                    if let Some(mode) = blend_mode(blend) {
+                        let _ = write!(self.body, r#" style="mix-blend-mode:{mode}""#);
+                    }
+                    self.body.push('>');
+                }
+                END_CLIP => self.body.push_str("</g>"),
+                _ => {}
+            }
+        }
+    }
+
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}{}", self.next_id)
+    }
+
+    fn write_path(&mut self, path: &EncodedPath, paint: &str) {
+        let flags = path.style.flags_and_miter_limit;
+        let _ = write!(
+            self.body,
+            r#"<path d="{}" transform="{}""#,
+            path.data,
+            matrix(path.transform)
+        );
+        if flags & STROKE != 0 {
+            let join = match flags & JOIN_MASK {
+                JOIN_MITER => "miter",
+                JOIN_ROUND => "round",
+                _ => "bevel",
+            };
+            let cap = match flags & CAP_MASK {
+                CAP_SQUARE => "square",
+                CAP_ROUND => "round",
+                _ => "butt",
+            };
+            let _ = write!(
+                self.body,
+                r#" fill="none" stroke="{paint}" stroke-width="{}" stroke-linejoin="{join}" stroke-linecap="{cap}""#,
+                path.style.line_width,
+            );
+        } else {
+            let _ = write!(
+                self.body,
+                r#" fill="{paint}" fill-rule="{}""#,
+                fill_rule(&path.style)
+            );
+        }
+        self.body.push_str("/>");
+    }
+
+    /// Finish a gradient definition with its transform, spread and stops.
+    fn write_gradient_end(
+        &mut self,
+        path: &EncodedPath,
+        stops: &[vello::peniko::ColorStop],
+        extend: Extend,
+        element: &str,
+    ) {
+        // Gradients are placed by the brush transform, paths by their own
+        let transform = path.transform.inverse() * path.brush_transform;
+        let spread = match extend {
+            Extend::Pad => "pad",
+            Extend::Repeat => "repeat",
+            Extend::Reflect => "reflect",
+        };
+        let _ = write!(
+            self.defs,
+            r#" gradientTransform="{}" spreadMethod="{spread}">"#,
+            matrix(transform)
+        );
+        for stop in stops {
+            let Color { r, g, b, a } = stop.color;
+            let _ = write!(
+                self.defs,
+                r#"<stop offset="{}" stop-color="rgb({r},{g},{b})" stop-opacity="{}"/>"#,
+                stop.offset,
+                a as f32 / 255.0,
+            );
+        }
+        let _ = write!(self.defs, "</{element}>");
+    }
+}
+
+/// Decode the paths of an encoding into SVG path data, one per draw.
+fn decode_paths(encoding: &Encoding) -> Vec<EncodedPath> {
+    let points: Vec<[f32; 2]> = encoding
+        .path_data
+        .chunks_exact(8)
+        .map(|chunk| {
+            let x = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let y = f32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            [x, y]
+        })
+        .collect();
+    let mut transforms = encoding.transforms.iter().map(to_affine);
+    let mut styles = encoding.styles.iter();
+
+    let mut paths = vec![];
+    let mut transform = Affine::IDENTITY;
+    let mut path_transform = None;
+    let mut style = Style::from_fill(vello::peniko::Fill::NonZero);
+    let mut data = String::new();
+    let (mut cursor, mut subpath_start) = (0, None);
+    for tag in &encoding.path_tags {
+        let tag = tag.0;
+        let segment = (tag & SEGMENT_TYPE) as usize;
+        if segment != 0 {
+            // Scenes only encode f32 coordinates
+            let Some(segment_points) = points.get(cursor..=cursor + segment) else {
+                break;
+            };
+            if tag & F32 == 0 {
+                break;
+            }
+            path_transform.get_or_insert(transform);
+            let start = segment_points[0];
+            if subpath_start.is_none() {
+                let _ = write!(data, "M{} {}", start[0], start[1]);
+                subpath_start = Some(start);
+            }
+            let command = match segment {
+                1 => 'L',
+                2 => 'Q',
+                _ => 'C',
+            };
+            data.push(command);
+            for (i, [x, y]) in segment_points[1..].iter().enumerate() {
+                let separator = if i == 0 { "" } else { " " };
+                let _ = write!(data, "{separator}{x} {y}");
+            }
+            cursor += segment;
+            if tag & SUBPATH_END != 0 {
+                if subpath_start == Some(segment_points[segment]) {
+                    data.push('Z');
+                }
+                // The next subpath starts at a new point
+                cursor += 1;
+                subpath_start = None;
+            }
+        } else if tag & PATH != 0 {
+            paths.push(EncodedPath {
+                data: std::mem::take(&mut data),
+                transform: path_transform.take().unwrap_or(transform),
+                brush_transform: transform,
+                style,
+            });
+            subpath_start = None;
+        } else if tag & TRANSFORM != 0 {
+            transform = transforms.next().unwrap_or(Affine::IDENTITY);
+        } else if tag & STYLE != 0 {
+            style = styles.next().copied().unwrap_or(style);
+        }
+    }
+    paths
+}
+
+fn words(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+fn to_affine(transform: &EncodedTransform) -> Affine {
+    let [a, b, c, d] = transform.matrix;
+    let [e, f] = transform.translation;
+    Affine::new([a, b, c, d, e, f].map(f64::from))
+}
+
+fn matrix(transform: Affine) -> String {
+    let [a, b, c, d, e, f] = transform.as_coeffs();
+    format!("matrix({a} {b} {c} {d} {e} {f})")
+}
+
+fn fill_rule(style: &Style) -> &'static str {
+    if style.flags_and_miter_limit & EVEN_ODD != 0 {
+        "evenodd"
+    } else {
+        "nonzero"
+    }
+}
+
+/// A CSS color from a premultiplied RGBA color, packed as in the encoding.
+fn premultiplied_color(rgba: u32) -> String {
+    let [r, g, b, a] = rgba.to_be_bytes();
+    if a == 0 {
+        return "none".to_string();
+    }
+    let unpremultiply = |c: u8| (c as f32 * 255.0 / a as f32).round().min(255.0) as u8;
+    format!(
+        "rgba({},{},{},{})",
+        unpremultiply(r),
+        unpremultiply(g),
+        unpremultiply(b),
+        a as f32 / 255.0
+    )
+}
+
+/// The CSS blend mode of a layer, or `None` for normal blending.
+fn blend_mode(blend: u32) -> Option<&'static str> {
+    let mode = match blend >> 8 {
+        1 => "multiply",
+        2 => "screen",
+        3 => "overlay",
+        4 => "darken",
+        5 => "lighten",
+        6 => "color-dodge",
+        7 => "color-burn",
+        8 => "hard-light",
+        9 => "soft-light",
+        10 => "difference",
+        11 => "exclusion",
+        12 => "hue",
+        13 => "saturation",
+        14 => "color",
+        15 => "luminosity",
+        _ => return None,
+    };
+    Some(mode)
+}
+
+/// Encode an image as a PNG data URI.
+fn png_data_uri(image: &Image) -> Option<String> {
+    let pixels = image::RgbaImage::from_raw(image.width, image.height, image.data.data().to_vec())?;
+    let mut png = vec![];
+    pixels
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .ok()?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(png);
+    Some(format!("data:image/png;base64,{encoded}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::load_svg_from_bytes;
+    use std::sync::Arc;
+    use vello::glyph::Glyph;
+    use vello::kurbo::{Cap, Join, Rect, Stroke};
+    use vello::peniko::{Blob, Fill, Font, Gradient, Mix};
+
+    const SIZE: Vec2 = Vec2::new(20.0, 20.0);
+
+    fn rect() -> Rect {
+        Rect::new(0.0, 0.0, 10.0, 10.0)
+    }
+
+    fn shapes() -> Scene {
+        let mut scene = Scene::new();
+        let red = Color::rgba8(255, 0, 0, 255);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, red, None, &rect());
+        let offset = Affine::translate((5.0, 5.0));
+        scene.fill(Fill::EvenOdd, offset, Color::BLACK, None, &rect());
+        let stroke = Stroke::new(3.0)
+            .with_join(Join::Round)
+            .with_caps(Cap::Round);
+        scene.stroke(&stroke, Affine::IDENTITY, Color::BLACK, None, &rect());
+        scene
+    }
+
+    #[test]
+    fn writes_fills_and_strokes() {
+        let svg = scene_to_svg(&shapes(), SIZE);
+        assert!(svg.contains(r#"fill="rgba(255,0,0,1)" fill-rule="nonzero""#));
+        assert!(svg.contains(r#"fill-rule="evenodd""#));
+        assert!(svg.contains(r#"transform="matrix(1 0 0 1 5 5)""#));
+        assert!(svg.contains(
+            r#"fill="none" stroke="rgba(0,0,0,1)" stroke-width="3" stroke-linejoin="round" stroke-linecap="round""#
+        ));
+        assert!(svg.contains(r#"d="M0 0L10 0L10 10L0 10"#));
+    }
+
+    #[test]
+    fn writes_gradients() {
+        let stops = [Color::BLACK, Color::WHITE];
+        let mut scene = Scene::new();
+        let linear = Gradient::new_linear((0.0, 0.0), (10.0, 0.0)).with_stops(&stops[..]);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, &linear, None, &rect());
+        let radial = Gradient::new_radial((5.0, 5.0), 5.0).with_stops(&stops[..]);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, &radial, None, &rect());
+        let svg = scene_to_svg(&scene, SIZE);
+        assert!(svg.contains(r#"<linearGradient id="gradient1" gradientUnits="userSpaceOnUse" x1="0" y1="0" x2="10" y2="0""#));
+        assert!(svg.contains(r#"<radialGradient id="gradient2""#));
+        assert_eq!(svg.matches("<stop ").count(), 4);
+        assert!(svg.contains(r#"fill="url(#gradient1)""#));
+        assert!(svg.contains(r#"fill="url(#gradient2)""#));
+    }
+
+    #[test]
+    fn writes_clips_and_blend_modes() {
+        let mut scene = Scene::new();
+        scene.push_layer(Mix::Multiply, 0.5, Affine::IDENTITY, &rect());
+        scene.fill(Fill::NonZero, Affine::IDENTITY, Color::BLACK, None, &rect());
+        scene.pop_layer();
+        let svg = scene_to_svg(&scene, SIZE);
+        assert!(svg.contains(r#"<clipPath id="clip1">"#));
+        assert!(svg.contains(
+            r#"<g clip-path="url(#clip1)" opacity="0.5" style="mix-blend-mode:multiply">"#
+        ));
+        assert!(svg.ends_with("</g></svg>"));
+    }
+
+    #[test]
+    fn round_trips_through_the_svg_loader() {
+        let svg = scene_to_svg(&shapes(), SIZE);
+        let asset = load_svg_from_bytes(svg.as_bytes()).unwrap();
+        let VectorFile::Svg { scene, .. } = &asset.data else {
+            panic!("expected an SVG asset");
+        };
+        let reexported = scene_to_svg(scene, SIZE);
+        let paints = |svg: &str| -> Vec<String> {
+            svg.split(' ')
+                .filter(|attribute| {
+                    attribute.starts_with("fill=") || attribute.starts_with("stroke=")
+                })
+                .map(str::to_owned)
+                .collect()
+        };
+        assert_eq!(paints(&reexported), paints(&svg));
+        assert_eq!(
+            reexported.matches("<path ").count(),
+            svg.matches("<path ").count()
+        );
+    }
+
+    #[test]
+    fn skips_glyph_runs() {
+        let font_data = std::fs::read("examples/text/src/assets/Rubik-Medium.ttf").unwrap();
+        let font = Font::new(Blob::new(Arc::new(font_data)), 0);
+        let glyph = Glyph {
+            id: 1,
+            x: 0.0,
+            y: 0.0,
+        };
+        let mut scene = Scene::new();
+        scene
+            .draw_glyphs(&font)
+            .font_size(12.0)
+            .brush(Color::BLACK)
+            .draw(Fill::NonZero, [glyph].into_iter());
+        let svg = scene_to_svg(&scene, SIZE);
+        assert!(!svg.contains("<path "));
+    }
+
+    #[test]
+    fn writes_svg_assets_from_their_source() {
+        // Pattern fills aren't encoded into the scene, but are kept in the tree
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <defs><pattern id="dots" width="2" height="2" patternUnits="userSpaceOnUse">
+                <circle cx="1" cy="1" r="1"/>
+            </pattern></defs>
+            <rect width="10" height="10" fill="url(#dots)"/>
+        </svg>"#;
+        let mut asset = load_svg_from_bytes(svg.as_bytes()).unwrap();
+        let exported = asset_to_svg(&asset, 0.0, None);
+        assert!(exported.contains("<pattern"));
+        assert!(usvg::Tree::from_str(
+            &exported,
+            &usvg::Options::default(),
+            &usvg::fontdb::Database::new()
+        )
+        .is_ok());
+
+        asset.bake_scale(2.0);
+        let scaled = asset_to_svg(&asset, 0.0, None);
+        assert!(scaled.starts_with(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20" viewBox="0 0 10 10""#
+        ));
+    }
+}
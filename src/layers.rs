@@ -0,0 +1,55 @@
+use crate::VelloAsset;
+use bevy::prelude::*;
+
+/// Additional assets composited on top of an entity's [`VelloAsset`], in
+/// order. Add this component to a `VelloAssetBundle` entity to compose, e.g.,
+/// an avatar out of base, clothing and accessory assets without managing an
+/// entity hierarchy.
+///
+/// Every layer shares the entity's transform, z-index, theme and playhead.
+/// The playhead is driven by the entity's own asset.
+#[derive(Component, Default, Clone, Debug)]
+pub struct VelloLayers(pub Vec<VelloLayer>);
+
+impl VelloLayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a layer on top of the existing layers.
+    pub fn with_layer(mut self, layer: VelloLayer) -> Self {
+        self.0.push(layer);
+        self
+    }
+}
+
+/// A single layer of [`VelloLayers`].
+#[derive(Clone, Debug)]
+pub struct VelloLayer {
+    /// Asset data to render
+    pub asset: Handle<VelloAsset>,
+    /// An offset from the entity's origin, in the entity's local space.
+    pub offset: Vec2,
+    /// An opacity multiplier for this layer.
+    pub alpha: f32,
+}
+
+impl VelloLayer {
+    pub fn new(asset: Handle<VelloAsset>) -> Self {
+        Self {
+            asset,
+            offset: Vec2::ZERO,
+            alpha: 1.0,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+}
@@ -8,39 +8,100 @@ use bevy::prelude::*;
 mod plugin;
 pub use plugin::VelloPlugin;
 
+mod anchor;
+pub use anchor::VelloAnchor;
+
+mod layers;
+pub use layers::{VelloLayer, VelloLayers};
+
+mod placeholder;
+pub use placeholder::{VelloDefaultPlaceholder, VelloPlaceholder};
+
+mod quad;
+pub use quad::{VelloQuad, VelloQuadBundle};
+
+mod ui;
+pub use ui::{UiVelloAssetBundle, VelloUiNode};
+
+mod units;
+pub use units::{
+    VelloDefaultUnits, VelloDefaultYAxis, VelloSize, VelloSizeMode, VelloSkew, VelloUnits,
+    VelloYAxis, VelloZoomScaling,
+};
+
 pub mod assets;
+pub mod color_scheme;
+pub mod convert;
 pub mod debug;
+#[cfg(feature = "export")]
+pub mod export;
 pub mod playback;
 pub mod player;
 pub mod render;
 pub mod text;
 pub mod theme;
+#[cfg(feature = "tweening")]
+pub mod tweening;
 
 // Re-exports
 pub use {velato, vello, vello_svg};
 
 pub mod prelude {
-    pub use crate::assets::{VectorFile, VelloAsset};
+    pub use crate::assets::{
+        ImportDiagnostic, LottieAudio, LottieCompatibility, LottieMarker, LottieSaver,
+        SvgShapeRendering, VectorFile, VelloAsset, VelloAssetError, VelloAssetLoadFailed,
+        VelloAssetLoaderSettings, VelloAssetOrigin, VelloAssetReady, VelloAssetReport,
+        VelloAssetsReady, VelloLoadProgress, VelloLoadingStatus, VelloLottieFontMap,
+        VelloParseMode, VelloSvgFontMap,
+    };
+    pub use crate::color_scheme::{
+        ColorScheme, ColorSchemeChanged, ColorSchemeThemes, FollowWindowTheme,
+    };
+    pub use crate::convert::{ToBevy, ToKurbo, ToPeniko};
     pub use crate::debug::DebugVisualizations;
     pub use crate::playback::{
-        PlaybackAlphaOverride, PlaybackDirection, PlaybackLoopBehavior, PlaybackOptions,
-        PlaybackPlayMode, Playhead,
+        ColorOverrideMode, PlaybackAlphaOverride, PlaybackColorOverride, PlaybackCompleteBehavior,
+        PlaybackCompleted, PlaybackDirection, PlaybackEvent, PlaybackLoopBehavior, PlaybackOptions,
+        PlaybackPlayMode, Playhead, PlayheadBucketing, PlayheadCallbacks, Segment,
     };
     pub use crate::player::{LottiePlayer, PlayerState, PlayerTransition};
     pub use crate::plugin::VelloPlugin;
-    pub use crate::render::{VelloCanvasMaterial, ZFunction};
+    pub use crate::render::{
+        NineSlice, VelloAntialiasing, VelloBlendMode, VelloCameraStage, VelloCameraStages,
+        VelloCanvas, VelloCanvasDepths, VelloCanvasFormat, VelloCanvasMaterial, VelloCanvasStage,
+        VelloClip, VelloCpuFallback, VelloDropShadow, VelloEffects, VelloEmissive,
+        VelloPostProcess, VelloQuadMaterial, VelloRasterTask, VelloRasterizer, VelloRedraw,
+        VelloRenderSettings, VelloRenderTarget, VelloRendererError, VelloRendererOptions,
+        VelloSceneFragment, VelloSceneProvider, VelloSceneProviders, VelloScreenshot,
+        VelloStrokeScaling, VelloTiling, VelloView, VelloZIndex, ZFunction,
+    };
     pub use crate::text::{VelloFont, VelloText};
-    pub use crate::theme::Theme;
+    pub use crate::theme::{Theme, VelloPalette};
     pub use crate::{
-        CoordinateSpace, VelloAssetBundle, VelloScene, VelloSceneBundle, VelloTextBundle,
+        CoordinateSpace, UiVelloAssetBundle, VelloAnchor, VelloAssetBundle,
+        VelloDefaultPlaceholder, VelloDefaultUnits, VelloDefaultYAxis, VelloLayer, VelloLayers,
+        VelloPlaceholder, VelloQuad, VelloQuadBundle, VelloScene, VelloSceneBundle, VelloSize,
+        VelloSizeMode, VelloSkew, VelloTextBundle, VelloUiNode, VelloUnits, VelloYAxis,
+        VelloZoomScaling,
     };
 }
 
+/// Where a vello entity is placed, chosen per entity so HUD and gameplay
+/// vectors can be mixed freely.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Component, Default, Copy, Clone, Debug, Reflect)]
 #[reflect(Component)]
 pub enum CoordinateSpace {
+    /// Placed in the world by its transform, and moved by the camera.
     #[default]
     WorldSpace,
+    /// Placed at the projection of its translation, for 3D cameras: always
+    /// facing the camera and scaled by distance, like health bars, markers and
+    /// speech bubbles over characters. Rotation is ignored, and billboards are
+    /// drawn over world space content, far ones first.
+    Billboard3d,
+    /// Placed in logical pixels from the top left of the camera viewport,
+    /// with Y pointing down, ignoring the camera transform. Drawn on top of
+    /// world space content.
     ScreenSpace,
 }
 
@@ -0,0 +1,67 @@
+use crate::convert::ToPeniko;
+use crate::{VelloAsset, VelloScene};
+use bevy::prelude::*;
+use std::f64::consts::{FRAC_PI_2, TAU};
+use vello::kurbo::{self, Affine, Point, Rect, Stroke};
+use vello::peniko::Fill;
+
+/// Add this component to a `VelloAssetBundle` entity to show a placeholder
+/// until the entity's asset finishes loading. Entities without the component
+/// use [`VelloDefaultPlaceholder`].
+#[derive(Component, Clone, Debug)]
+pub enum VelloPlaceholder {
+    /// A solid rectangle, centered on the entity.
+    Rect { size: Vec2, color: Color },
+    /// A spinning arc, centered on the entity.
+    Spinner { radius: f32, color: Color },
+    /// Another asset, typically one which is already loaded. Lottie
+    /// placeholders loop at their own frame rate.
+    Asset(Handle<VelloAsset>),
+}
+
+impl VelloPlaceholder {
+    /// Build the scene for primitive placeholders. Returns `None` for
+    /// [`VelloPlaceholder::Asset`].
+    pub fn scene(&self, elapsed_seconds: f64) -> Option<VelloScene> {
+        let mut scene = VelloScene::new();
+        match self {
+            VelloPlaceholder::Rect { size, color } => {
+                let rect = Rect::from_center_size(
+                    Point::ZERO,
+                    kurbo::Size::new(size.x as f64, size.y as f64),
+                );
+                scene.fill(
+                    Fill::NonZero,
+                    Affine::IDENTITY,
+                    color.to_peniko(),
+                    None,
+                    &rect,
+                );
+            }
+            VelloPlaceholder::Spinner { radius, color } => {
+                let radius = *radius as f64;
+                let arc = kurbo::Arc {
+                    center: Point::ZERO,
+                    radii: kurbo::Vec2::new(radius, radius),
+                    start_angle: (elapsed_seconds * TAU) % TAU,
+                    sweep_angle: 3.0 * FRAC_PI_2,
+                    x_rotation: 0.0,
+                };
+                scene.stroke(
+                    &Stroke::new(radius / 4.0),
+                    Affine::IDENTITY,
+                    color.to_peniko(),
+                    None,
+                    &arc,
+                );
+            }
+            VelloPlaceholder::Asset(_) => return None,
+        }
+        Some(scene)
+    }
+}
+
+/// The [`VelloPlaceholder`] shown by entities without the component while
+/// their asset loads. Nothing is shown by default.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct VelloDefaultPlaceholder(pub Option<VelloPlaceholder>);
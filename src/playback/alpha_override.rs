@@ -1,4 +1,6 @@
 use bevy::ecs::component::Component;
 
+/// Fades a vello asset, from 0 (invisible) to 1. Entities at 0 are skipped
+/// entirely: they are neither encoded nor rendered.
 #[derive(Component)]
 pub struct PlaybackAlphaOverride(pub f32);
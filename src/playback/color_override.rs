@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+
+/// Tints the rendered asset of an entity, e.g. for damage flashes and
+/// team-colored icons, without a full [`Theme`](crate::Theme). The alpha of
+/// the color is the strength of the tint, and the asset's own alpha is kept.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct PlaybackColorOverride {
+    pub color: Color,
+    pub mode: ColorOverrideMode,
+}
+
+/// How a [`PlaybackColorOverride`] applies its color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum ColorOverrideMode {
+    /// Multiply the asset's colors, e.g. red for a team color.
+    #[default]
+    Multiply,
+    /// Replace the asset's colors, e.g. white for a damage flash.
+    Replace,
+}
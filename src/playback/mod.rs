@@ -3,10 +3,23 @@
 mod alpha_override;
 pub use alpha_override::PlaybackAlphaOverride;
 
+mod color_override;
+pub use color_override::{ColorOverrideMode, PlaybackColorOverride};
+
+mod playback_event;
+pub use playback_event::{PlaybackCompleted, PlaybackEvent};
+
 mod playback_options;
 pub use playback_options::{
-    PlaybackDirection, PlaybackLoopBehavior, PlaybackOptions, PlaybackPlayMode,
+    PlaybackCompleteBehavior, PlaybackDirection, PlaybackLoopBehavior, PlaybackOptions,
+    PlaybackPlayMode, Segment,
 };
 
 mod playhead;
 pub use playhead::Playhead;
+
+mod playhead_bucketing;
+pub use playhead_bucketing::PlayheadBucketing;
+
+mod playhead_callbacks;
+pub use playhead_callbacks::{PlayheadCallbackInput, PlayheadCallbacks};
@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+use std::borrow::Cow;
+
+/// An event emitted when a playhead crosses a frame registered in
+/// [`PlaybackOptions::events`](super::PlaybackOptions::events).
+///
+/// Crossings are detected across loop wraps, bounces and reverse playback.
+#[derive(Event, PartialEq, Clone, Debug)]
+pub struct PlaybackEvent {
+    /// The entity whose playhead crossed the frame.
+    pub entity: Entity,
+    /// The user-provided identifier of the event.
+    pub id: Cow<'static, str>,
+    /// The frame the event was registered at.
+    pub frame: f64,
+}
+
+/// An event emitted once when a bounded playback finishes its last loop.
+///
+/// See [`PlaybackOptions::on_complete`](super::PlaybackOptions::on_complete).
+#[derive(Event, PartialEq, Eq, Clone, Copy, Debug)]
+pub struct PlaybackCompleted {
+    /// The entity whose playback completed.
+    pub entity: Entity,
+}
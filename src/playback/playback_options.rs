@@ -1,4 +1,8 @@
+use crate::VelloAsset;
 use bevy::prelude::*;
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 use std::ops::Range;
 use std::time::Duration;
 
@@ -18,14 +22,43 @@ pub struct PlaybackOptions {
     pub speed: f64,
     /// A duration of time spent idle between loops.
     pub intermission: Duration,
+    /// Up to this much time is randomly added to every intermission, which
+    /// keeps many instances from looking synchronized.
+    pub intermission_jitter: Duration,
     /// Whether to reset the playhead every loop (normal) or to reverse
     /// directions (bounce).
     pub play_mode: PlaybackPlayMode,
     /// Whether to loop, and how many.
     pub looping: PlaybackLoopBehavior,
-    /// The segments (frames) of the animation to play. Values out of range
-    /// will be ignored.
-    pub segments: Range<f64>,
+    /// What to do when a bounded playback finishes its last loop.
+    pub on_complete: PlaybackCompleteBehavior,
+    /// The segments (frames) of the animation to play, as frames or a named
+    /// marker. Frames out of range will be ignored.
+    pub segments: Segment,
+    /// Frames paired with an identifier, e.g. `(12.0, "footstep".into())`
+    /// or an id built at runtime. A [`PlaybackEvent`] is emitted whenever the
+    /// playhead crosses one of these frames.
+    ///
+    /// [`PlaybackEvent`]: super::PlaybackEvent
+    pub events: Vec<(f64, Cow<'static, str>)>,
+    /// Whether to hold the playhead while the entity is hidden, e.g. by a
+    /// hidden parent. Hidden entities are never drawn either way.
+    pub pause_when_hidden: bool,
+}
+
+impl PlaybackOptions {
+    /// Sample an intermission duration, between
+    /// [`PlaybackOptions::intermission`] and that plus
+    /// [`PlaybackOptions::intermission_jitter`].
+    pub fn sample_intermission(&self) -> Duration {
+        if self.intermission_jitter.is_zero() {
+            return self.intermission;
+        }
+        // Every `RandomState` is seeded differently
+        let random = RandomState::new().build_hasher().finish();
+        let unit = (random >> 11) as f64 / (1u64 << 53) as f64;
+        self.intermission + self.intermission_jitter.mul_f64(unit)
+    }
 }
 
 impl Default for PlaybackOptions {
@@ -35,13 +68,66 @@ impl Default for PlaybackOptions {
             direction: Default::default(),
             speed: 1.0,
             intermission: Duration::ZERO,
+            intermission_jitter: Duration::ZERO,
             play_mode: Default::default(),
             looping: Default::default(),
-            segments: f64::MIN..f64::MAX,
+            on_complete: Default::default(),
+            segments: Default::default(),
+            events: vec![],
+            pause_when_hidden: false,
         }
     }
 }
 
+/// The frames of a lottie animation to play, see
+/// [`PlaybackOptions::segments`].
+#[derive(PartialEq, Clone, Debug, Reflect)]
+pub enum Segment {
+    /// A range of frames.
+    Frames(Range<f64>),
+    /// The frames of a [`LottieMarker`](crate::LottieMarker) of the
+    /// animation, by name, e.g. `Segment::marker("attack")`. Single frame
+    /// markers play their frame, and animations without the marker play whole.
+    Marker(Cow<'static, str>),
+}
+
+impl Default for Segment {
+    fn default() -> Self {
+        Self::Frames(f64::MIN..f64::MAX)
+    }
+}
+
+impl From<Range<f64>> for Segment {
+    fn from(frames: Range<f64>) -> Self {
+        Self::Frames(frames)
+    }
+}
+
+impl Segment {
+    /// The frames of a named marker, e.g. from a level file or a
+    /// [`LottieMarker`](crate::LottieMarker) name.
+    pub fn marker(name: impl Into<Cow<'static, str>>) -> Self {
+        Self::Marker(name.into())
+    }
+
+    /// The frames of an asset this segment plays, within the in-point and
+    /// out-point of the animation. Empty for assets which aren't Lottie
+    /// animations.
+    pub fn frames(&self, asset: &VelloAsset) -> Range<f64> {
+        let Some(frames) = asset.frames() else {
+            return 0.0..0.0;
+        };
+        let segment = match self {
+            Segment::Frames(segment) => segment.clone(),
+            Segment::Marker(name) => match asset.marker(name) {
+                Some(marker) => marker.start..marker.start + marker.duration.max(1.0),
+                None => frames.clone(),
+            },
+        };
+        segment.start.max(frames.start)..segment.end.min(frames.end)
+    }
+}
+
 /// The direction to play the segments of a lottie animation.
 #[derive(PartialEq, Component, Default, Clone, Copy, Debug, Reflect)]
 pub enum PlaybackDirection {
@@ -64,6 +150,24 @@ pub enum PlaybackLoopBehavior {
     Loop,
 }
 
+/// What to do when a bounded playback (see [`PlaybackLoopBehavior`]) finishes.
+///
+/// A [`PlaybackCompleted`](super::PlaybackCompleted) event is emitted in every
+/// case.
+#[derive(PartialEq, Component, Default, Clone, Copy, Debug, Reflect)]
+pub enum PlaybackCompleteBehavior {
+    /// Stop on the last frame played.
+    #[default]
+    HoldLastFrame,
+    /// Stop on the first frame of the playback direction.
+    ReturnToFirstFrame,
+    /// Stop on the last frame played and hide the entity.
+    Hide,
+    /// Only emit the event. The playhead keeps advancing and stays clamped to
+    /// the segment bounds.
+    EmitOnly,
+}
+
 /// Whether to reset (normal) the playhead every loop or to reverse directions
 /// (bounce).
 #[derive(PartialEq, Component, Default, Clone, Copy, Debug, Reflect)]
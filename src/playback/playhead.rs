@@ -15,6 +15,8 @@ pub struct Playhead {
     /// Used by play mode to track current direction. Only set to -1.0
     /// (reverse) or 1.0 (normal).
     pub(crate) playmode_dir: f64,
+    /// Whether a bounded playback has finished its last loop.
+    pub(crate) completed: bool,
 }
 
 impl Playhead {
@@ -23,6 +25,11 @@ impl Playhead {
         self.frame
     }
 
+    /// Whether a bounded playback has finished its last loop.
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
     /// Seek to a given frame
     pub fn seek(&mut self, frame: f64) {
         self.frame = frame;
@@ -35,6 +42,7 @@ impl Playhead {
             intermission: None,
             loops_completed: 0,
             playmode_dir: 1.0,
+            completed: false,
         }
     }
 }
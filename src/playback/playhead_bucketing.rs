@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use std::ops::Range;
+
+/// An opt-in crowd mode for Lottie assets. Adding this component quantizes
+/// the rendered frame into a number of shared buckets, so instances of the
+/// same composition landing in the same bucket reuse one encoding.
+///
+/// This trades a little visual variety for the ability to render hundreds of
+/// animated instances. Instances with a [`Theme`](crate::Theme) are never
+/// shared.
+#[derive(PartialEq, Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct PlayheadBucketing {
+    /// The amount of buckets to split the composition frames into.
+    pub buckets: u32,
+}
+
+impl Default for PlayheadBucketing {
+    fn default() -> Self {
+        Self { buckets: 16 }
+    }
+}
+
+impl PlayheadBucketing {
+    pub fn new(buckets: u32) -> Self {
+        Self { buckets }
+    }
+
+    /// Snap a frame to the start of its bucket.
+    pub fn quantize(&self, frame: f64, frames: &Range<f64>) -> f64 {
+        let length = frames.end - frames.start;
+        if self.buckets == 0 || length <= 0.0 {
+            return frame;
+        }
+        let bucket_length = length / self.buckets as f64;
+        let bucket = ((frame - frames.start) / bucket_length).floor();
+        frames.start + bucket.clamp(0.0, (self.buckets - 1) as f64) * bucket_length
+    }
+}
@@ -0,0 +1,44 @@
+use bevy::ecs::system::SystemId;
+use bevy::prelude::*;
+
+/// The input given to a [`PlayheadCallbacks`] system: the entity owning the
+/// playhead, and the frame which triggered the callback.
+pub type PlayheadCallbackInput = (Entity, f64);
+
+/// One-shot systems to run when this entity's playhead changes or crosses a
+/// frame, useful for quick prototyping without writing dedicated query
+/// systems.
+///
+/// Systems are registered with [`World::register_system`], e.g.
+///
+/// ```ignore
+/// let id = world.register_system(|In((entity, frame)): In<(Entity, f64)>| {
+///     info!("{entity:?} crossed frame {frame}");
+/// });
+/// commands.entity(e).insert(PlayheadCallbacks::new().on_frame(12.0, id));
+/// ```
+#[derive(Component, Default, Clone, Debug)]
+pub struct PlayheadCallbacks {
+    pub(crate) on_change: Vec<SystemId<PlayheadCallbackInput>>,
+    pub(crate) on_frame: Vec<(f64, SystemId<PlayheadCallbackInput>)>,
+}
+
+impl PlayheadCallbacks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run a system every time the playhead frame changes. The input frame is
+    /// the new playhead frame.
+    pub fn on_change(mut self, system: SystemId<PlayheadCallbackInput>) -> Self {
+        self.on_change.push(system);
+        self
+    }
+
+    /// Run a system every time the playhead crosses the given frame, including
+    /// across loop wraps and reverse playback.
+    pub fn on_frame(mut self, frame: f64, system: SystemId<PlayheadCallbackInput>) -> Self {
+        self.on_frame.push((frame, system));
+        self
+    }
+}
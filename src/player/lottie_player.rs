@@ -1,4 +1,5 @@
 use super::PlayerState;
+use crate::{PlaybackDirection, PlaybackPlayMode};
 use bevy::prelude::*;
 use bevy::utils::hashbrown::HashMap;
 
@@ -18,10 +19,23 @@ pub struct LottiePlayer {
     pub(crate) playing: bool,
     /// Stopped. Doesn't run state machines.
     pub(crate) stopped: bool,
+    /// Whether the pointer is over the player's asset, for mouse leave
+    /// transitions.
+    pub(crate) hovered: bool,
+    /// A direction for the current playback, see
+    /// [`LottiePlayer::set_direction`].
+    pub(crate) direction: Option<PlaybackDirection>,
+    /// A play mode for the current playback, see
+    /// [`LottiePlayer::set_playmode`].
+    pub(crate) play_mode: Option<PlaybackPlayMode>,
 }
 
 impl LottiePlayer {
     /// Retrieve an immutable reference to the current state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player has no state, or its current state was removed.
     pub fn state(&self) -> &PlayerState {
         self.states
             .get(
@@ -33,6 +47,10 @@ impl LottiePlayer {
     }
 
     /// Retrieve a mutable reference to the current state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the player has no state, or its current state was removed.
     pub fn state_mut(&mut self) -> &mut PlayerState {
         self.states
             .get_mut(
@@ -83,6 +101,21 @@ impl LottiePlayer {
         self.stopped = true;
     }
 
+    /// Set the direction of the current playback. The playhead turns around
+    /// on its current frame, without jumping. Transitions to a state with its
+    /// own playback options replace it.
+    pub fn set_direction(&mut self, direction: PlaybackDirection) {
+        self.direction.replace(direction);
+    }
+
+    /// Set whether the current playback resets (normal) or reverses (bounce)
+    /// every loop. Bouncing playbacks switched to normal play on in their
+    /// direction. Transitions to a state with its own playback options replace
+    /// it.
+    pub fn set_playmode(&mut self, play_mode: PlaybackPlayMode) {
+        self.play_mode.replace(play_mode);
+    }
+
     pub fn is_playing(&self) -> bool {
         self.playing
     }
@@ -101,6 +134,9 @@ impl LottiePlayer {
             started: false,
             playing: false,
             stopped: false,
+            hovered: false,
+            direction: None,
+            play_mode: None,
         }
     }
 
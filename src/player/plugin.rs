@@ -1,11 +1,22 @@
 use super::systems;
+use crate::{PlaybackCompleted, PlaybackEvent};
 use bevy::prelude::*;
 
 pub struct LottiePlayerPlugin;
 
 impl Plugin for LottiePlayerPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_systems(First, systems::advance_playheads)
+        app.add_event::<PlaybackEvent>()
+            .add_event::<PlaybackCompleted>()
+            .add_systems(
+                First,
+                (
+                    systems::preserve_playheads_on_reload,
+                    systems::apply_player_changes,
+                    systems::advance_playheads,
+                )
+                    .chain(),
+            )
             .add_systems(
                 Last,
                 (
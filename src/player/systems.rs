@@ -1,16 +1,21 @@
-use crate::playback::PlaybackPlayMode;
+use crate::playback::{PlaybackCompleteBehavior, PlaybackPlayMode};
 use crate::player::LottiePlayer;
+use crate::render::{vello_views, VelloView};
 use crate::{
-    PlaybackDirection, PlaybackLoopBehavior, PlaybackOptions, PlayerTransition, Playhead,
-    VectorFile, VelloAsset,
+    PlaybackCompleted, PlaybackDirection, PlaybackEvent, PlaybackLoopBehavior, PlaybackOptions,
+    PlayerTransition, Playhead, PlayheadCallbacks, VectorFile, VelloAnchor, VelloAsset,
+    VelloDefaultUnits, VelloDefaultYAxis, VelloSize, VelloSkew, VelloUnits, VelloYAxis,
 };
 use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::view::RenderLayers;
 use bevy::utils::Instant;
+use bevy::window::PrimaryWindow;
 use std::time::Duration;
 use vello_svg::usvg::strict_num::Ulps;
 
-/// Spawn playheads for Lotties. Every Lottie gets exactly 1 playhead.
-/// Only
+/// Spawn playheads for vector assets. Every entity gets its own playhead, so
+/// entities sharing an asset play independently.
 pub fn spawn_playheads(
     mut commands: Commands,
     query: Query<(Entity, &Handle<VelloAsset>, Option<&PlaybackOptions>), Without<Playhead>>,
@@ -18,19 +23,18 @@ pub fn spawn_playheads(
 ) {
     for (entity, handle, options) in query.iter() {
         if let Some(asset) = assets.get(handle) {
-            let VectorFile::Lottie { composition } = &asset.data else {
+            let VectorFile::Lottie { composition, .. } = &asset.data else {
                 commands.entity(entity).insert(Playhead::new(0.0));
-                return;
+                continue;
             };
             let frame = match options {
-                Some(options) => match options.direction {
-                    PlaybackDirection::Normal => {
-                        options.segments.start.max(composition.frames.start)
-                    }
-                    PlaybackDirection::Reverse => {
-                        options.segments.end.min(composition.frames.end).prev()
+                Some(options) => {
+                    let frames = options.segments.frames(asset);
+                    match options.direction {
+                        PlaybackDirection::Normal => frames.start,
+                        PlaybackDirection::Reverse => frames.end.prev(),
                     }
-                },
+                }
                 None => composition.frames.start,
             };
             commands.entity(entity).insert(Playhead::new(frame));
@@ -38,28 +42,113 @@ pub fn spawn_playheads(
     }
 }
 
+/// Keep playheads (and with them, loop counts and player state) when their
+/// asset is hot-reloaded, only clamping the frame into the new composition.
+pub fn preserve_playheads_on_reload(
+    mut asset_events: EventReader<AssetEvent<VelloAsset>>,
+    mut query: Query<(&Handle<VelloAsset>, &mut Playhead)>,
+    assets: Res<Assets<VelloAsset>>,
+) {
+    for event in asset_events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        let Some(VelloAsset {
+            data: VectorFile::Lottie { composition, .. },
+            ..
+        }) = assets.get(*id)
+        else {
+            continue;
+        };
+        for (_, mut playhead) in query.iter_mut().filter(|(handle, _)| handle.id() == *id) {
+            let frame = playhead
+                .frame
+                .clamp(composition.frames.start, composition.frames.end.prev());
+            if frame != playhead.frame {
+                debug!(
+                    "clamping reloaded playhead from {} to {frame}",
+                    playhead.frame
+                );
+                playhead.frame = frame;
+            }
+        }
+    }
+}
+
+/// Apply playback changes requested through [`LottiePlayer`]s to their
+/// entity's [`PlaybackOptions`].
+pub fn apply_player_changes(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &mut LottiePlayer,
+            Option<&mut PlaybackOptions>,
+            Option<&mut Playhead>,
+        ),
+        Changed<LottiePlayer>,
+    >,
+) {
+    for (entity, mut player, options, playhead) in query.iter_mut() {
+        // Read first, so players aren't marked changed again
+        if player.direction.is_none() && player.play_mode.is_none() {
+            continue;
+        }
+        let mut changed = options.as_deref().cloned().unwrap_or_default();
+        if let Some(direction) = player.direction.take() {
+            changed.direction = direction;
+        }
+        if let Some(play_mode) = player.play_mode.take() {
+            changed.play_mode = play_mode;
+            // Only bounces play against the direction
+            if let (PlaybackPlayMode::Normal, Some(mut playhead)) = (play_mode, playhead) {
+                playhead.playmode_dir = 1.0;
+            }
+        }
+        match options {
+            Some(mut options) => {
+                options.set_if_neq(changed);
+            }
+            None => {
+                commands.entity(entity).insert(changed);
+            }
+        }
+    }
+}
+
 /// Advance all the playheads in the scene
 pub fn advance_playheads(
+    mut commands: Commands,
     mut query: Query<(
+        Entity,
         &Handle<VelloAsset>,
         &mut Playhead,
         Option<&mut LottiePlayer>,
         Option<&PlaybackOptions>,
+        Option<&PlayheadCallbacks>,
+        Option<&InheritedVisibility>,
     )>,
-    mut assets: ResMut<Assets<VelloAsset>>,
+    assets: Res<Assets<VelloAsset>>,
     time: Res<Time>,
+    mut events: EventWriter<PlaybackEvent>,
+    mut completed_events: EventWriter<PlaybackCompleted>,
 ) {
-    for (asset_handle, mut playhead, player, options) in query.iter_mut() {
+    for (entity, asset_handle, mut playhead, player, options, callbacks, visibility) in
+        query.iter_mut()
+    {
         // Get asset
-        let Some(VelloAsset {
-            data: VectorFile::Lottie { composition },
-            ..
-        }) = assets.get_mut(asset_handle.id())
-        else {
+        let Some(asset) = assets.get(asset_handle) else {
+            continue;
+        };
+        let VectorFile::Lottie { composition, .. } = &asset.data else {
             continue;
         };
 
         let options = options.cloned().unwrap_or_default();
+        let hidden = visibility.is_some_and(|visibility| !visibility.get());
+        if options.pause_when_hidden && hidden {
+            continue;
+        }
         if let Some(mut player) = player {
             if player.stopped {
                 continue;
@@ -74,10 +163,19 @@ pub fn advance_playheads(
                 continue;
             }
         }
+        // Frozen after completion
+        if playhead.completed && options.on_complete != PlaybackCompleteBehavior::EmitOnly {
+            continue;
+        }
+
+        let frames = options.segments.frames(asset);
+        let start_frame = frames.start;
+        let end_frame = frames.end.prev();
+        let prev_frame = playhead.frame;
+        let was_completed = playhead.completed;
 
-        let start_frame = options.segments.start.max(composition.frames.start);
-        let end_frame = options.segments.end.min(composition.frames.end).prev();
-        let length = end_frame - start_frame;
+        // The spans of frames travelled this tick, in order of travel
+        let mut travelled = vec![];
 
         // Handle intermissions
         if let Some(ref mut intermission) = playhead.intermission {
@@ -87,143 +185,290 @@ pub fn advance_playheads(
                 match options.direction {
                     PlaybackDirection::Normal => {
                         playhead.frame = start_frame;
+                        travelled.push((start_frame.prev(), start_frame));
                     }
                     PlaybackDirection::Reverse => {
                         playhead.frame = end_frame;
+                        travelled.push((end_frame.next(), end_frame));
                     }
                 }
             }
-            return;
+        } else {
+            // Set first render
+            playhead.first_render.get_or_insert(Instant::now());
+
+            let frames = time.delta_seconds_f64() * options.speed * composition.frame_rate;
+            advance_playhead(
+                &mut playhead,
+                &options,
+                frames,
+                start_frame,
+                end_frame,
+                &mut travelled,
+            );
+        }
+
+        // Notify listeners
+        if playhead.completed && !was_completed {
+            completed_events.send(PlaybackCompleted { entity });
+            if options.on_complete == PlaybackCompleteBehavior::Hide {
+                commands.entity(entity).insert(Visibility::Hidden);
+            }
+        }
+        let crossed = crossed_frames(&options.events, &travelled);
+        events.send_batch(crossed.into_iter().map(|(frame, id)| PlaybackEvent {
+            entity,
+            id: id.clone(),
+            frame: *frame,
+        }));
+        if let Some(callbacks) = callbacks {
+            if playhead.frame != prev_frame {
+                for &system in callbacks.on_change.iter() {
+                    commands.run_system_with_input(system, (entity, playhead.frame));
+                }
+            }
+            for &(frame, system) in crossed_frames(&callbacks.on_frame, &travelled) {
+                commands.run_system_with_input(system, (entity, frame));
+            }
         }
+    }
+}
 
-        // Set first render
-        playhead.first_render.get_or_insert(Instant::now());
+/// Advance a playhead by an amount of frames, keeping it bounded between
+/// segments. The spans of frames travelled are appended to `travelled`.
+fn advance_playhead(
+    playhead: &mut Playhead,
+    options: &PlaybackOptions,
+    frames: f64,
+    start_frame: f64,
+    end_frame: f64,
+    travelled: &mut Vec<(f64, f64)>,
+) {
+    let length = end_frame - start_frame;
+    let prev_frame = playhead.frame;
 
-        // Advance playhead
-        playhead.frame += (time.delta_seconds_f64()
-            * options.speed
-            * composition.frame_rate
-            * (options.direction as i32 as f64)
-            * playhead.playmode_dir)
-            % length;
+    // Advance playhead
+    playhead.frame += (frames * (options.direction as i32 as f64) * playhead.playmode_dir) % length;
 
-        // Keep the playhead bounded between segments
-        let looping = match options.looping {
-            PlaybackLoopBehavior::Loop => true,
-            PlaybackLoopBehavior::Amount(amt) => playhead.loops_completed < amt,
-            PlaybackLoopBehavior::DoNotLoop => false,
-        };
-        if playhead.frame > end_frame {
-            if looping {
-                playhead.loops_completed += 1;
-                if let PlaybackPlayMode::Bounce = options.play_mode {
-                    playhead.playmode_dir *= -1.0;
-                }
-                // Trigger intermission, if applicable
-                if options.intermission > Duration::ZERO {
-                    playhead
-                        .intermission
-                        .replace(Timer::new(options.intermission, TimerMode::Once));
-                    playhead.frame = end_frame;
-                } else {
-                    // Wrap around to the beginning of the segment
-                    playhead.frame = start_frame + (playhead.frame - end_frame);
-                }
-            } else {
+    // Keep the playhead bounded between segments
+    let looping = match options.looping {
+        PlaybackLoopBehavior::Loop => true,
+        PlaybackLoopBehavior::Amount(amt) => playhead.loops_completed < amt,
+        PlaybackLoopBehavior::DoNotLoop => false,
+    };
+    if playhead.frame > end_frame {
+        travelled.push((prev_frame, end_frame));
+        if looping {
+            playhead.loops_completed += 1;
+            if let PlaybackPlayMode::Bounce = options.play_mode {
+                playhead.playmode_dir *= -1.0;
+            }
+            // Trigger intermission, if applicable
+            let intermission = options.sample_intermission();
+            if intermission > Duration::ZERO {
+                playhead
+                    .intermission
+                    .replace(Timer::new(intermission, TimerMode::Once));
                 playhead.frame = end_frame;
+            } else {
+                // Wrap around to the beginning of the segment
+                playhead.frame = start_frame + (playhead.frame - end_frame);
+                if let PlaybackPlayMode::Normal = options.play_mode {
+                    travelled.push((start_frame.prev(), playhead.frame));
+                }
             }
-            // Obey play mode
+        } else {
+            playhead.frame = end_frame;
+            playhead.completed = true;
+        }
+        // Obey play mode
+        if let PlaybackPlayMode::Bounce = options.play_mode {
+            playhead.frame = end_frame;
+        }
+    } else if playhead.frame < start_frame {
+        travelled.push((prev_frame, start_frame));
+        if looping {
+            playhead.loops_completed += 1;
             if let PlaybackPlayMode::Bounce = options.play_mode {
-                playhead.frame = end_frame;
+                playhead.playmode_dir *= -1.0;
             }
-        } else if playhead.frame < start_frame {
-            if looping {
-                playhead.loops_completed += 1;
-                if let PlaybackPlayMode::Bounce = options.play_mode {
-                    playhead.playmode_dir *= -1.0;
+            // Trigger intermission, if applicable
+            let intermission = options.sample_intermission();
+            if intermission > Duration::ZERO {
+                playhead
+                    .intermission
+                    .replace(Timer::new(intermission, TimerMode::Once));
+                playhead.frame = start_frame;
+            } else {
+                // Wrap around to the beginning of the segment
+                playhead.frame = end_frame - (start_frame - playhead.frame);
+                if let PlaybackPlayMode::Normal = options.play_mode {
+                    travelled.push((end_frame.next(), playhead.frame));
                 }
-                // Trigger intermission, if applicable
-                if options.intermission > Duration::ZERO {
-                    playhead
-                        .intermission
-                        .replace(Timer::new(options.intermission, TimerMode::Once));
-                    playhead.frame = start_frame;
+            }
+        } else {
+            playhead.frame = start_frame;
+            playhead.completed = true;
+        }
+        // Obey play mode
+        if let PlaybackPlayMode::Bounce = options.play_mode {
+            playhead.frame = start_frame;
+        }
+    } else {
+        travelled.push((prev_frame, playhead.frame));
+    }
+
+    // Rest on the first frame if requested
+    if playhead.completed && options.on_complete == PlaybackCompleteBehavior::ReturnToFirstFrame {
+        playhead.frame = match options.direction {
+            PlaybackDirection::Normal => start_frame,
+            PlaybackDirection::Reverse => end_frame,
+        };
+    }
+}
+
+/// Returns the marked frames inside the travelled spans, in order of travel.
+/// Each span excludes its starting frame and includes its ending frame, so a
+/// frame is never reported twice for consecutive ticks.
+fn crossed_frames<'a, T>(marks: &'a [(f64, T)], travelled: &[(f64, f64)]) -> Vec<&'a (f64, T)> {
+    let mut crossed = vec![];
+    for &(from, to) in travelled {
+        let mut in_span: Vec<&(f64, T)> = marks
+            .iter()
+            .filter(|(frame, _)| {
+                if to >= from {
+                    from < *frame && *frame <= to
                 } else {
-                    // Wrap around to the beginning of the segment
-                    playhead.frame = end_frame - (start_frame - playhead.frame);
+                    to <= *frame && *frame < from
                 }
+            })
+            .collect();
+        in_span.sort_by(|(a, _), (b, _)| {
+            let ord = a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal);
+            if to >= from {
+                ord
             } else {
-                playhead.frame = start_frame;
+                ord.reverse()
             }
-            // Obey play mode
-            if let PlaybackPlayMode::Bounce = options.play_mode {
-                playhead.frame = start_frame;
-            }
-        }
+        });
+        crossed.extend(in_span);
     }
+    crossed
 }
 
+/// Trigger player transitions. Mouse transitions hit test the cursor through
+/// every camera whose viewport it is over, so each split-screen view hovers
+/// the content it draws.
+#[allow(clippy::too_many_arguments)]
 pub fn run_transitions(
     mut query_player: Query<(
         &mut LottiePlayer,
         &Playhead,
         &PlaybackOptions,
         &GlobalTransform,
-        &mut Handle<VelloAsset>,
+        &Handle<VelloAsset>,
+        (Option<&VelloUnits>, Option<&VelloSize>),
+        (Option<&VelloYAxis>, Option<&VelloSkew>),
+        Option<&VelloAnchor>,
+        Option<&RenderLayers>,
+    )>,
+    assets: Res<Assets<VelloAsset>>,
+    default_units: Res<VelloDefaultUnits>,
+    default_y_axis: Res<VelloDefaultYAxis>,
+    windows: Query<(Entity, &Window, Has<PrimaryWindow>)>,
+    cameras: Query<(
+        Entity,
+        &Camera,
+        &GlobalTransform,
+        Option<&RenderLayers>,
+        Has<VelloView>,
     )>,
-    mut assets: ResMut<Assets<VelloAsset>>,
-    windows: Query<&Window>,
-    query_view: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
     buttons: Res<ButtonInput<MouseButton>>,
-    mut hovered: Local<bool>,
 ) {
-    let Ok(window) = windows.get_single() else {
-        return;
-    };
-    let Ok((camera, view)) = query_view.get_single() else {
-        return;
-    };
-
-    let pointer_pos = window
-        .cursor_position()
-        .and_then(|cursor| camera.viewport_to_world(view, cursor))
-        .map(|ray| ray.origin.truncate());
+    let primary_window = windows
+        .iter()
+        .find_map(|(entity, _, primary)| primary.then_some(entity));
+    let views = vello_views(
+        cameras
+            .iter()
+            .filter(|(_, camera, ..)| camera.is_active)
+            .map(|(entity, _, _, layers, marked)| (entity, layers, marked)),
+    );
+    // The cursor's ray into the world of each view it is over
+    let pointer_rays: Vec<_> = views
+        .into_iter()
+        .filter_map(|(entity, layers)| {
+            let (_, camera, view, ..) = cameras.get(entity).ok()?;
+            let RenderTarget::Window(window_ref) = camera.target else {
+                return None;
+            };
+            let window_entity = window_ref.normalize(primary_window)?.entity();
+            let (_, window, _) = windows.get(window_entity).ok()?;
+            let cursor = window.cursor_position()?;
+            let viewport = camera.logical_viewport_rect()?;
+            if !viewport.contains(cursor) {
+                return None;
+            }
+            let ray = camera.viewport_to_world(view, cursor - viewport.min)?;
+            Some((layers, ray))
+        })
+        .collect();
 
-    for (mut player, playhead, options, gtransform, current_asset_handle) in query_player.iter_mut()
+    for (
+        mut player,
+        playhead,
+        options,
+        gtransform,
+        current_asset_handle,
+        (units, size),
+        (y_axis, skew),
+        anchor,
+        render_layers,
+    ) in query_player.iter_mut()
     {
         if player.stopped {
             continue;
         }
 
-        let current_asset = assets
-            .get_mut(current_asset_handle.id())
-            .unwrap_or_else(|| {
-                panic!(
-                    "asset not found for state: '{}'",
-                    player.current_state.unwrap()
-                )
-            });
+        // The asset may still be loading, or may have failed to load. Assets
+        // are shared between entities, so they are only read here
+        let Some(current_asset) = assets.get(current_asset_handle) else {
+            continue;
+        };
 
         let is_inside = {
-            match pointer_pos {
-                Some(pointer_pos) => {
-                    let local_transform = current_asset
-                        .local_transform_center
-                        .compute_matrix()
-                        .inverse();
-                    let transform = gtransform.compute_matrix() * local_transform;
-                    let mouse_local = transform
-                        .inverse()
-                        .transform_point3(pointer_pos.extend(0.0));
-                    mouse_local.x <= current_asset.width
-                        && mouse_local.x >= 0.0
-                        && mouse_local.y >= -current_asset.height
-                        && mouse_local.y <= 0.0
-                }
-                None => false,
-            }
+            let gtransform = y_axis.unwrap_or(&default_y_axis.0).apply(gtransform);
+            let gtransform = skew.map_or(gtransform, |skew| skew.apply(&gtransform));
+            let gtransform = match size {
+                Some(size) => size.apply(current_asset, &gtransform),
+                None => units
+                    .unwrap_or(&default_units.0)
+                    .apply(current_asset, &gtransform),
+            };
+            let gtransform = match anchor {
+                Some(anchor) => anchor.apply_to_transform(current_asset, &gtransform),
+                None => gtransform,
+            };
+            let to_local = gtransform.compute_matrix().inverse();
+            let render_layers = render_layers.copied().unwrap_or_default();
+            pointer_rays
+                .iter()
+                .filter(|(layers, _)| layers.intersects(&render_layers))
+                .any(|(_, ray)| {
+                    // Where the ray crosses the asset's plane
+                    let origin = to_local.transform_point3(ray.origin);
+                    let direction = to_local.transform_vector3(*ray.direction);
+                    if direction.z.abs() <= f32::EPSILON {
+                        return false;
+                    }
+                    let distance = -origin.z / direction.z;
+                    let mouse_local = origin + direction * distance;
+                    distance >= 0.0 && current_asset.local_rect().contains(mouse_local.truncate())
+                })
         };
 
+        // Hover state is kept per player, so players don't share it
+        let mut hovered = player.hovered;
         for transition in player.state().transitions.iter() {
             match transition {
                 PlayerTransition::OnAfter { state, secs } => {
@@ -234,7 +479,12 @@ pub fn run_transitions(
                     }
                 }
                 PlayerTransition::OnComplete { state } => {
-                    if let VectorFile::Lottie { composition } = &current_asset.data {
+                    if playhead.completed {
+                        player.next_state = Some(state);
+                        break;
+                    }
+                    if let VectorFile::Lottie { .. } = &current_asset.data {
+                        let frames = options.segments.frames(current_asset);
                         let loops_needed = match options.looping {
                             PlaybackLoopBehavior::DoNotLoop => Some(0),
                             PlaybackLoopBehavior::Amount(amt) => Some(amt),
@@ -242,8 +492,7 @@ pub fn run_transitions(
                         };
                         match options.direction {
                             PlaybackDirection::Normal => {
-                                let end_frame =
-                                    options.segments.end.min(composition.frames.end).prev();
+                                let end_frame = frames.end.prev();
                                 if playhead.frame == end_frame
                                     && loops_needed
                                         .is_some_and(|needed| playhead.loops_completed >= needed)
@@ -253,8 +502,7 @@ pub fn run_transitions(
                                 }
                             }
                             PlaybackDirection::Reverse => {
-                                let start_frame =
-                                    options.segments.start.max(composition.frames.start);
+                                let start_frame = frames.start;
                                 if playhead.frame == start_frame
                                     && loops_needed
                                         .is_some_and(|needed| playhead.loops_completed >= needed)
@@ -269,7 +517,7 @@ pub fn run_transitions(
                 PlayerTransition::OnMouseEnter { state } => {
                     if is_inside {
                         player.next_state = Some(state);
-                        *hovered = true;
+                        hovered = true;
                         break;
                     }
                 }
@@ -280,12 +528,12 @@ pub fn run_transitions(
                     }
                 }
                 PlayerTransition::OnMouseLeave { state } => {
-                    if *hovered && !is_inside {
+                    if hovered && !is_inside {
                         player.next_state = Some(state);
-                        *hovered = false;
+                        hovered = false;
                         break;
                     } else if is_inside {
-                        *hovered = true;
+                        hovered = true;
                     }
                 }
                 PlayerTransition::OnShow { state } => {
@@ -296,6 +544,7 @@ pub fn run_transitions(
                 }
             }
         }
+        player.hovered = hovered;
     }
 }
 
@@ -320,10 +569,11 @@ pub fn transition_state(
         }
         info!("animation controller transitioning to={next_state}");
 
-        let target_state = player
-            .states
-            .get(&next_state)
-            .unwrap_or_else(|| panic!("state not found: '{}'", next_state));
+        let Some(target_state) = player.states.get(&next_state) else {
+            warn!("animation controller has no state '{next_state}', ignoring transition");
+            player.next_state.take();
+            continue;
+        };
         let target_options = target_state
             .options
             .as_ref()
@@ -339,18 +589,9 @@ pub fn transition_state(
             };
             *cur_handle = target_handle.clone();
             // Keep playhead bounded
-            if let VelloAsset {
-                data: VectorFile::Lottie { composition },
-                ..
-            } = asset
-            {
-                let start_frame = target_options.segments.start.max(composition.frames.start);
-                let end_frame = target_options
-                    .segments
-                    .end
-                    .min(composition.frames.end)
-                    .prev();
-                playhead.frame = playhead.frame.clamp(start_frame, end_frame);
+            if let VectorFile::Lottie { .. } = asset.data {
+                let frames = target_options.segments.frames(asset);
+                playhead.frame = playhead.frame.clamp(frames.start, frames.end.prev());
             }
         }
         // Swap theme
@@ -359,24 +600,17 @@ pub fn transition_state(
         }
         // Reset playheads if requested
         if player.state().reset_playhead_on_exit || target_state.reset_playhead_on_start {
-            // SAFETY: Asset check happens earlier
-            let asset = assets
-                .get(target_state.asset.as_ref().unwrap_or(&cur_handle))
-                .unwrap();
-            if let VelloAsset {
-                data: VectorFile::Lottie { composition },
-                ..
-            } = asset
+            if let Some(
+                asset @ VelloAsset {
+                    data: VectorFile::Lottie { .. },
+                    ..
+                },
+            ) = assets.get(target_state.asset.as_ref().unwrap_or(&cur_handle))
             {
+                let frames = target_options.segments.frames(asset);
                 let frame = match target_options.direction {
-                    PlaybackDirection::Normal => {
-                        target_options.segments.start.max(composition.frames.start)
-                    }
-                    PlaybackDirection::Reverse => target_options
-                        .segments
-                        .end
-                        .min(composition.frames.end)
-                        .prev(),
+                    PlaybackDirection::Normal => frames.start,
+                    PlaybackDirection::Reverse => frames.end.prev(),
                 };
                 // Reset playhead
                 playhead.frame = frame;
@@ -392,6 +626,7 @@ pub fn transition_state(
         playhead.loops_completed = 0;
         playhead.first_render.take();
         playhead.playmode_dir = 1.0;
+        playhead.completed = false;
 
         // Reset player state
         player.started = false;
@@ -399,3 +634,34 @@ pub fn transition_state(
         player.current_state.replace(next_state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKS: [(f64, char); 3] = [(5.0, 'a'), (10.0, 'b'), (15.0, 'c')];
+
+    fn crossed(travelled: &[(f64, f64)]) -> Vec<char> {
+        crossed_frames(&MARKS, travelled)
+            .into_iter()
+            .map(|(_, mark)| *mark)
+            .collect()
+    }
+
+    #[test]
+    fn crosses_frames_forwards_and_backwards() {
+        assert_eq!(crossed(&[(0.0, 10.0)]), ['a', 'b']);
+        assert_eq!(crossed(&[(12.0, 4.0)]), ['b', 'a']);
+    }
+
+    #[test]
+    fn spans_exclude_their_starting_frame() {
+        assert_eq!(crossed(&[(0.0, 5.0), (5.0, 10.0)]), ['a', 'b']);
+        assert!(crossed(&[(10.0, 10.0)]).is_empty());
+    }
+
+    #[test]
+    fn crosses_frames_in_order_of_travel_when_looping() {
+        assert_eq!(crossed(&[(12.0, 20.0), (0.0, 6.0)]), ['c', 'a']);
+    }
+}
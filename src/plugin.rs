@@ -1,9 +1,26 @@
-use crate::assets::VelloAssetLoader;
+use crate::assets::{
+    prune_load_progress, report_load_failures, report_ready_assets, update_loading_status,
+    LottieMinifier, VelloAssetLoadFailed, VelloAssetLoader, VelloAssetReady, VelloAssetsReady,
+    VelloLoadFailures, VelloLoadProgress, VelloLoadingStatus, VelloLottieFontMap, VelloSvgFontMap,
+};
+use crate::color_scheme::{
+    apply_color_scheme, follow_window_theme, ColorScheme, ColorSchemeChanged, ColorSchemeThemes,
+};
 use crate::debug::DebugVisualizationsPlugin;
 use crate::player::LottiePlayerPlugin;
-use crate::render::VelloRenderPlugin;
+use crate::quad::setup_quads;
+use crate::render::{
+    NineSlice, VelloBlendMode, VelloEffects, VelloRenderPlugin, VelloStrokeScaling, VelloTiling,
+    VelloZIndex,
+};
 use crate::text::VelloFontLoader;
-use crate::{VelloAsset, VelloFont};
+use crate::theme::{Theme, ThemeLoader};
+use crate::ui::update_ui_content_size;
+use crate::{
+    CoordinateSpace, PlaybackColorOverride, VelloAnchor, VelloAsset, VelloDefaultPlaceholder,
+    VelloDefaultUnits, VelloDefaultYAxis, VelloFont, VelloQuad, VelloSize, VelloSkew, VelloUnits,
+    VelloYAxis, VelloZoomScaling,
+};
 use bevy::prelude::*;
 
 pub struct VelloPlugin;
@@ -12,9 +29,57 @@ impl Plugin for VelloPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(VelloRenderPlugin)
             .add_plugins((LottiePlayerPlugin, DebugVisualizationsPlugin))
+            .init_resource::<VelloLoadProgress>()
+            .init_resource::<VelloSvgFontMap>()
+            .init_resource::<VelloLottieFontMap>()
+            .init_resource::<VelloLoadFailures>()
             .init_asset::<VelloAsset>()
             .init_asset_loader::<VelloAssetLoader>()
+            .register_asset_processor(LottieMinifier)
             .init_asset::<VelloFont>()
-            .init_asset_loader::<VelloFontLoader>();
+            .init_asset_loader::<VelloFontLoader>()
+            .init_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .init_resource::<VelloLoadingStatus>()
+            .init_resource::<VelloDefaultPlaceholder>()
+            .init_resource::<VelloDefaultUnits>()
+            .init_resource::<VelloDefaultYAxis>()
+            .register_type::<CoordinateSpace>()
+            .register_type::<VelloUnits>()
+            .register_type::<VelloSize>()
+            .register_type::<VelloYAxis>()
+            .register_type::<VelloSkew>()
+            .register_type::<VelloZoomScaling>()
+            .register_type::<VelloAnchor>()
+            .register_type::<VelloZIndex>()
+            .register_type::<VelloQuad>()
+            .register_type::<VelloBlendMode>()
+            .register_type::<VelloEffects>()
+            .register_type::<PlaybackColorOverride>()
+            .register_type::<NineSlice>()
+            .register_type::<VelloTiling>()
+            .register_type::<VelloStrokeScaling>()
+            .add_event::<VelloAssetReady>()
+            .add_event::<VelloAssetsReady>()
+            .add_event::<VelloAssetLoadFailed>()
+            .add_systems(
+                Update,
+                (
+                    report_load_failures,
+                    report_ready_assets,
+                    update_loading_status,
+                    prune_load_progress,
+                ),
+            )
+            .add_systems(Update, setup_quads)
+            .init_resource::<ColorScheme>()
+            .register_type::<ColorScheme>()
+            .register_type::<ColorSchemeThemes>()
+            .add_event::<ColorSchemeChanged>()
+            .add_systems(Update, (follow_window_theme, apply_color_scheme).chain())
+            .add_systems(
+                PostUpdate,
+                update_ui_content_size.before(bevy::ui::UiSystem::Layout),
+            );
     }
 }
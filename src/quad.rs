@@ -0,0 +1,76 @@
+use crate::{VelloAsset, VelloQuadMaterial, VelloRenderTarget};
+use bevy::prelude::*;
+
+/// Draws a vector asset onto a quad in 3D space, e.g. for signage and
+/// decals. The quad is depth tested like other 3D geometry, so it is occluded
+/// by what is in front of it.
+///
+/// The quad is sized to the asset, in world units scaled by the
+/// [`Transform`], and faces +Z. The asset is rendered into an image through a
+/// [`VelloRenderTarget`], so its [`Playhead`](crate::Playhead) and
+/// [`Theme`](crate::Theme) apply. The quad is unlit and alpha blended.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct VelloQuad {
+    /// Pixels of the rendered image per unit of the asset. Raise it for
+    /// quads seen up close.
+    pub resolution: f32,
+}
+
+impl Default for VelloQuad {
+    fn default() -> Self {
+        Self { resolution: 1.0 }
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct VelloQuadBundle {
+    /// Asset data to render
+    pub vector: Handle<VelloAsset>,
+    /// How the asset is drawn onto the quad
+    pub quad: VelloQuad,
+    /// A transform to apply to the quad
+    pub transform: Transform,
+    /// The global transform managed by Bevy
+    pub global_transform: GlobalTransform,
+    /// User indication of whether an entity is visible. Propagates down the entity hierarchy.
+    pub visibility: Visibility,
+    /// Whether or not an entity is visible in the hierarchy.
+    pub inherited_visibility: InheritedVisibility,
+    /// Algorithmically-computed indication of whether an entity is visible. Should be extracted for rendering.
+    pub view_visibility: ViewVisibility,
+}
+
+/// Give quads a mesh, a material and an image to render into, once their
+/// asset loads, and again when the image size changes.
+pub(crate) fn setup_quads(
+    mut commands: Commands,
+    quads: Query<(
+        Entity,
+        &VelloQuad,
+        &Handle<VelloAsset>,
+        Option<&VelloRenderTarget>,
+    )>,
+    assets: Res<Assets<VelloAsset>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<VelloQuadMaterial>>,
+) {
+    for (entity, quad, handle, target) in quads.iter() {
+        let Some(asset) = assets.get(handle) else {
+            continue;
+        };
+        let size = Vec2::new(asset.width, asset.height);
+        let pixels = (size * quad.resolution).ceil().as_uvec2().max(UVec2::ONE);
+        let current = target.and_then(|target| images.get(&target.0));
+        if current.is_some_and(|image| image.size() == pixels) {
+            continue;
+        }
+        let image = images.add(VelloRenderTarget::image(pixels));
+        commands.entity(entity).insert((
+            VelloRenderTarget(image.clone()),
+            meshes.add(Rectangle::from_size(size)),
+            materials.add(VelloQuadMaterial { texture: image }),
+        ));
+    }
+}
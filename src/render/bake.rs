@@ -0,0 +1,223 @@
+use super::image_renderer::{
+    image_from_pixels, read_pixels, render_asset, render_to_readback_buffer, ImageRenderError,
+};
+use super::systems::{pop_tint_layer, push_tint_layer};
+use super::{BevyVelloRenderer, LottieRenderer, VelloRenderSettings};
+use crate::{PlaybackColorOverride, VelloAsset};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Buffer, Maintain, MapMode};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::Mix;
+use vello::Scene;
+
+struct BakeRequest {
+    asset: VelloAsset,
+    frame: f64,
+    size: UVec2,
+    alpha: f32,
+    tint: Option<PlaybackColorOverride>,
+    slot: BakeSlot,
+}
+
+#[derive(Default)]
+pub(super) struct BakeState {
+    result: Option<Result<Image, ImageRenderError>>,
+    waker: Option<Waker>,
+}
+
+pub(super) type BakeSlot = Arc<Mutex<BakeState>>;
+
+pub(super) fn complete(slot: &BakeSlot, result: Result<Image, ImageRenderError>) {
+    let mut state = slot.lock().unwrap();
+    state.result = Some(result);
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+}
+
+/// A frame of a [`VelloAsset`] being baked into an [`Image`], see
+/// [`VelloRasterizer::rasterize`], or a capture of
+/// [`VelloScreenshot`](super::VelloScreenshot).
+///
+/// Await it in a task, or check on it from a system with
+/// `block_on(poll_once(&mut task))`.
+pub struct VelloRasterTask(pub(super) BakeSlot);
+
+impl Future for VelloRasterTask {
+    type Output = Result<Image, ImageRenderError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Bakes frames of assets into [`Image`]s, e.g. for a `TextureAtlas`, a
+/// sprite or an icon where full vector rendering is overkill.
+///
+/// The same queue is shared by the main and render worlds, so frames can be
+/// requested from either.
+#[derive(Resource, Clone, Default)]
+pub struct VelloRasterizer(Arc<Mutex<Vec<BakeRequest>>>);
+
+impl VelloRasterizer {
+    /// Bake a frame of an asset, stretched to `size` pixels, into an sRGB
+    /// [`Image`].
+    ///
+    /// The frame is rendered by the render world on the next frame, with the
+    /// renderer and antialiasing of [`VelloRenderSettings`], and read back a
+    /// frame or so later. The task never finishes without the
+    /// [`VelloPlugin`](crate::VelloPlugin).
+    pub fn rasterize(&self, asset: &VelloAsset, frame: f64, size: UVec2) -> VelloRasterTask {
+        self.request(asset.clone(), frame, size, 1.0, None)
+    }
+
+    /// Queue a frame of an asset for the render world, faded by `alpha` and
+    /// tinted by `tint`.
+    pub(super) fn request(
+        &self,
+        asset: VelloAsset,
+        frame: f64,
+        size: UVec2,
+        alpha: f32,
+        tint: Option<PlaybackColorOverride>,
+    ) -> VelloRasterTask {
+        let slot = BakeSlot::default();
+        self.0.lock().unwrap().push(BakeRequest {
+            asset,
+            frame,
+            size: size.max(UVec2::ONE),
+            alpha,
+            tint,
+            slot: slot.clone(),
+        });
+        VelloRasterTask(slot)
+    }
+}
+
+/// A frame rendered on the GPU, waiting to be read back.
+struct PendingBake {
+    buffer: Buffer,
+    size: UVec2,
+    mapped: Arc<Mutex<Option<Result<(), String>>>>,
+    slot: BakeSlot,
+}
+
+#[derive(Resource, Default)]
+pub struct PendingBakes(Vec<PendingBake>);
+
+/// Render requested frames, and finish reading back frames rendered earlier.
+pub fn bake_frames(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    mut vello_renderer: Option<NonSendMut<BevyVelloRenderer>>,
+    mut velottie_renderer: ResMut<LottieRenderer>,
+    settings: Res<VelloRenderSettings>,
+    rasterizer: Res<VelloRasterizer>,
+    mut pending: ResMut<PendingBakes>,
+) {
+    if !pending.0.is_empty() {
+        device.poll(Maintain::Poll);
+    }
+    pending.0.retain(|bake| {
+        let Some(mapped) = bake.mapped.lock().unwrap().take() else {
+            return true;
+        };
+        let result = match mapped {
+            Ok(()) => {
+                let pixels = read_pixels(&bake.buffer, bake.size);
+                bake.buffer.unmap();
+                Ok(image_from_pixels(pixels, bake.size))
+            }
+            Err(err) => Err(ImageRenderError::Readback(err)),
+        };
+        complete(&bake.slot, result);
+        false
+    });
+
+    let requests = std::mem::take(&mut *rasterizer.0.lock().unwrap());
+    for request in requests {
+        let asset = &request.asset;
+        let size = request.size;
+        let baked = asset.baked_scale();
+        let transform = Affine::scale_non_uniform(
+            size.x as f64 / asset.width as f64 * baked.x as f64,
+            size.y as f64 / asset.height as f64 * baked.y as f64,
+        );
+        let rect = Rect::new(0.0, 0.0, size.x as f64, size.y as f64);
+        let mut scene = Scene::new();
+        if request.alpha < 1.0 {
+            scene.push_layer(Mix::Normal, request.alpha, Affine::IDENTITY, &rect);
+        }
+        if request.tint.is_some() {
+            push_tint_layer(&mut scene, &rect);
+        }
+        render_asset(
+            &mut velottie_renderer.0,
+            asset,
+            request.frame,
+            transform,
+            None,
+            &mut scene,
+        );
+        if let Some(tint) = &request.tint {
+            pop_tint_layer(&mut scene, tint, &rect);
+        }
+        if request.alpha < 1.0 {
+            scene.pop_layer();
+        }
+        // Vello panics on empty encodings
+        if scene.encoding().is_empty() {
+            let pixels = vec![0; (size.x * size.y * 4) as usize];
+            complete(&request.slot, Ok(image_from_pixels(pixels, size)));
+            continue;
+        }
+
+        let Some(renderer) = vello_renderer.as_mut() else {
+            #[cfg(feature = "cpu_fallback")]
+            let result = super::cpu::rasterize(&scene, size)
+                .map(|pixels| image_from_pixels(pixels, size))
+                .map_err(ImageRenderError::Render);
+            #[cfg(not(feature = "cpu_fallback"))]
+            let result = Err(ImageRenderError::Renderer("no vello renderer".into()));
+            complete(&request.slot, result);
+            continue;
+        };
+        let buffer = match render_to_readback_buffer(
+            &mut renderer.0,
+            &device,
+            &queue,
+            &scene,
+            size,
+            settings.antialiasing.config(),
+        ) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                complete(&request.slot, Err(err));
+                continue;
+            }
+        };
+        let mapped = Arc::new(Mutex::new(None));
+        let on_mapped = mapped.clone();
+        device.map_buffer(&buffer.slice(..), MapMode::Read, move |result| {
+            *on_mapped.lock().unwrap() = Some(result.map_err(|err| err.to_string()));
+        });
+        pending.0.push(PendingBake {
+            buffer,
+            size,
+            mapped,
+            slot: request.slot,
+        });
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use vello::peniko::{BlendMode, Compose, Mix};
+
+/// How an entity's rendering is composited with the content underneath it,
+/// e.g. [`VelloBlendMode::Multiply`] for shadows and
+/// [`VelloBlendMode::Screen`] or [`VelloBlendMode::Plus`] for glows.
+///
+/// Only content on the same canvas is underneath, see
+/// [`VelloCanvasDepths`](crate::VelloCanvasDepths).
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component)]
+pub enum VelloBlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+    /// Adds the colors together.
+    Plus,
+}
+
+impl From<VelloBlendMode> for BlendMode {
+    fn from(mode: VelloBlendMode) -> Self {
+        let mix = match mode {
+            VelloBlendMode::Normal | VelloBlendMode::Plus => Mix::Normal,
+            VelloBlendMode::Multiply => Mix::Multiply,
+            VelloBlendMode::Screen => Mix::Screen,
+            VelloBlendMode::Overlay => Mix::Overlay,
+            VelloBlendMode::Darken => Mix::Darken,
+            VelloBlendMode::Lighten => Mix::Lighten,
+            VelloBlendMode::ColorDodge => Mix::ColorDodge,
+            VelloBlendMode::ColorBurn => Mix::ColorBurn,
+            VelloBlendMode::HardLight => Mix::HardLight,
+            VelloBlendMode::SoftLight => Mix::SoftLight,
+            VelloBlendMode::Difference => Mix::Difference,
+            VelloBlendMode::Exclusion => Mix::Exclusion,
+            VelloBlendMode::Hue => Mix::Hue,
+            VelloBlendMode::Saturation => Mix::Saturation,
+            VelloBlendMode::Color => Mix::Color,
+            VelloBlendMode::Luminosity => Mix::Luminosity,
+        };
+        let compose = match mode {
+            VelloBlendMode::Plus => Compose::Plus,
+            _ => Compose::SrcOver,
+        };
+        BlendMode::new(mix, compose)
+    }
+}
@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use vello::Scene;
+
+/// Custom vello content, drawn over everything else vello draws for a
+/// camera this frame.
+///
+/// This is a render world resource. Append to it from systems added to the
+/// [`RenderApp`](bevy::render::RenderApp) before
+/// [`RenderSet::Render`](bevy::render::RenderSet::Render), e.g. in
+/// `RenderSet::Queue`. Scenes are drawn into each camera's topmost canvas in
+/// the physical pixels of its viewport, with Y down, and emptied once drawn.
+///
+/// In [`VelloCanvasStage::MainPass`](crate::VelloCanvasStage::MainPass),
+/// canvases are hidden while there are no vello entities, and this content
+/// with them.
+#[derive(Resource, Default)]
+pub struct VelloCanvas(pub HashMap<Entity, Scene>);
+
+impl VelloCanvas {
+    /// The scene drawn over a camera's vello content this frame.
+    pub fn scene(&mut self, camera: Entity) -> &mut Scene {
+        self.0.entry(camera).or_insert_with(Scene::new)
+    }
+}
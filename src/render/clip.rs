@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+use vello::kurbo::{Affine, BezPath, Rect};
+use vello::peniko::Mix;
+use vello::Scene;
+
+/// Clips an entity's rendering to a shape, e.g. for scrolling lists,
+/// progress reveals and minimap masks.
+///
+/// The shape is in the coordinates the entity's content is drawn in, so it
+/// moves, scales and rotates with the entity: asset units from the top left
+/// for assets (`0,0` to `width,height`), and scene or text coordinates
+/// otherwise. [`VelloLayers`](crate::VelloLayers) are clipped to the same
+/// shape in their own coordinates.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum VelloClip {
+    /// Clip to a rectangle, e.g. a scroll view or the filled part of a
+    /// progress bar.
+    Rect(Rect),
+    /// Clip to an arbitrary path, e.g. a round minimap.
+    Path(BezPath),
+}
+
+impl VelloClip {
+    /// Start clipping what is drawn into `scene` next. The layer must be
+    /// popped afterwards.
+    pub(crate) fn push_layer(&self, scene: &mut Scene, transform: Affine) {
+        match self {
+            VelloClip::Rect(rect) => scene.push_layer(Mix::Clip, 1.0, transform, rect),
+            VelloClip::Path(path) => scene.push_layer(Mix::Clip, 1.0, transform, path),
+        }
+    }
+}
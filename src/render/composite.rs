@@ -0,0 +1,325 @@
+use super::extract::{ExtractedVelloView, SSRenderTarget};
+use super::{
+    VelloCameraStages, VelloCanvasDepths, VelloCanvasStage, VelloRenderSettings,
+    COMPOSITE_SHADER_HANDLE,
+};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::{Has, QueryItem};
+use bevy::prelude::*;
+use bevy::render::camera::ExtractedCamera;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_graph::{NodeRunError, RenderGraphContext, RenderLabel, ViewNode};
+use bevy::render::render_resource::binding_types::{sampler, texture_2d};
+use bevy::render::render_resource::{
+    BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BlendState, CachedRenderPipelineId,
+    ColorTargetState, ColorWrites, CommandEncoderDescriptor, Extent3d, FragmentState, LoadOp,
+    MultisampleState, Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderStages, SpecializedRenderPipeline, SpecializedRenderPipelines, StoreOp,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
+use bevy::render::view::ViewTarget;
+use bevy::utils::{HashMap, HashSet};
+
+/// Render graph nodes which draw vello canvases outside the main pass.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub enum VelloNode {
+    /// After post-processing, before `bevy_ui`.
+    BeforeUi,
+    /// After `bevy_ui`.
+    AfterUi,
+}
+
+/// Draws a camera's canvases onto its view target, when their
+/// [`VelloCanvasStage`] is this node's. 3D cameras have no 2D main pass, so
+/// they draw canvases before `bevy_ui` for [`VelloCanvasStage::MainPass`].
+pub struct VelloCompositeNode<const AFTER_UI: bool> {
+    canvases: QueryState<&'static SSRenderTarget>,
+}
+
+impl<const AFTER_UI: bool> FromWorld for VelloCompositeNode<AFTER_UI> {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            canvases: world.query(),
+        }
+    }
+}
+
+impl<const AFTER_UI: bool> ViewNode for VelloCompositeNode<AFTER_UI> {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static ExtractedCamera,
+        &'static VelloCompositePipelineId,
+        Has<Camera3d>,
+    );
+
+    fn update(&mut self, world: &mut World) {
+        self.canvases.update_archetypes(world);
+    }
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (view_target, camera, pipeline_ids, is_3d): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let stage = match AFTER_UI {
+            false => VelloCanvasStage::BeforeUi,
+            true => VelloCanvasStage::AfterUi,
+        };
+        let settings = world.resource::<VelloRenderSettings>();
+        let stages = world.resource::<VelloCameraStages>();
+        let canvas_depths = world.resource::<VelloCanvasDepths>();
+        let view_entity = graph.view_entity();
+        let canvas_stage = |canvas: usize| {
+            let screen = canvas_depths.is_screen_canvas(canvas);
+            match stages.stage(settings, view_entity, screen) {
+                VelloCanvasStage::MainPass if is_3d => VelloCanvasStage::BeforeUi,
+                canvas_stage => canvas_stage,
+            }
+        };
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let composite_pipeline = world.resource::<VelloCompositePipeline>();
+        let gpu_images = world.resource::<RenderAssets<Image>>();
+
+        // This view's canvases at this stage, bottom to top
+        let mut canvases: Vec<&SSRenderTarget> = self
+            .canvases
+            .iter_manual(world)
+            .filter(|target| target.camera == view_entity && canvas_stage(target.canvas) == stage)
+            .collect();
+        if canvases.is_empty() {
+            return Ok(());
+        }
+        canvases.sort_by_key(|target| target.canvas);
+
+        for target in canvases {
+            let Some(gpu_image) = gpu_images.get(&target.image) else {
+                continue;
+            };
+            // Float canvases hold linear color already
+            let pipeline_id = match gpu_image.texture_format {
+                TextureFormat::Rgba8Unorm => pipeline_ids.srgb,
+                _ => pipeline_ids.linear,
+            };
+            let Some(pipeline) = pipeline_cache.get_render_pipeline(pipeline_id) else {
+                continue;
+            };
+            let bind_group = render_context.render_device().create_bind_group(
+                "vello_composite_bind_group",
+                &composite_pipeline.layout,
+                &BindGroupEntries::sequential((
+                    &gpu_image.texture_view,
+                    &composite_pipeline.sampler,
+                )),
+            );
+            let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                label: Some("vello_composite_pass"),
+                color_attachments: &[Some(view_target.get_unsampled_color_attachment())],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            if let Some(viewport) = camera.viewport.as_ref() {
+                render_pass.set_camera_viewport(viewport);
+            }
+            render_pass.set_render_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+pub struct VelloCompositePipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+impl FromWorld for VelloCompositePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout = device.create_bind_group_layout(
+            "vello_composite_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+        let sampler = device.create_sampler(&SamplerDescriptor::default());
+        Self { layout, sampler }
+    }
+}
+
+/// A variant of the composite pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VelloCompositeKey {
+    /// The format of the texture drawn to.
+    pub format: TextureFormat,
+    /// Whether the canvas holds sRGB encoded color, to decode.
+    pub srgb: bool,
+    /// Whether to blend over the target, rather than replace it.
+    pub blend: bool,
+}
+
+impl SpecializedRenderPipeline for VelloCompositePipeline {
+    type Key = VelloCompositeKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let mut shader_defs = vec![];
+        if key.srgb {
+            shader_defs.push("DECODE_SRGB".into());
+        }
+        RenderPipelineDescriptor {
+            label: Some("vello_composite_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: COMPOSITE_SHADER_HANDLE,
+                shader_defs,
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: key.format,
+                    blend: key.blend.then_some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+        }
+    }
+}
+
+/// The composite pipelines of a view, for each kind of canvas.
+#[derive(Component)]
+pub struct VelloCompositePipelineId {
+    srgb: CachedRenderPipelineId,
+    linear: CachedRenderPipelineId,
+}
+
+/// Specialize the composite pipeline for the view target of each vello
+/// camera, and the pipeline decoding 8-bit renders into float canvases.
+pub fn prepare_composite_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<VelloCompositePipeline>>,
+    composite_pipeline: Res<VelloCompositePipeline>,
+    mut scratch: ResMut<VelloCanvasScratch>,
+    views: Query<(Entity, &ViewTarget), With<ExtractedVelloView>>,
+) {
+    let mut specialize = |format, srgb, blend| {
+        let key = VelloCompositeKey {
+            format,
+            srgb,
+            blend,
+        };
+        pipelines.specialize(&pipeline_cache, &composite_pipeline, key)
+    };
+    for (entity, view_target) in views.iter() {
+        let format = view_target.main_texture_format();
+        commands.entity(entity).insert(VelloCompositePipelineId {
+            srgb: specialize(format, true, true),
+            linear: specialize(format, false, true),
+        });
+    }
+    scratch.pipeline = Some(specialize(TextureFormat::Rgba16Float, true, false));
+}
+
+/// The 8-bit textures vello renders into for float canvases, by size, see
+/// [`VelloCanvasFormat::Rgba16Float`](super::VelloCanvasFormat).
+#[derive(Resource, Default)]
+pub struct VelloCanvasScratch {
+    pipeline: Option<CachedRenderPipelineId>,
+    textures: HashMap<UVec2, TextureView>,
+    used: HashSet<UVec2>,
+}
+
+impl VelloCanvasScratch {
+    /// A texture of `size` for vello to render into.
+    pub(crate) fn texture(&mut self, device: &RenderDevice, size: UVec2) -> TextureView {
+        self.used.insert(size);
+        self.textures
+            .entry(size)
+            .or_insert_with(|| {
+                device
+                    .create_texture(&TextureDescriptor {
+                        label: Some("vello_canvas_scratch"),
+                        size: Extent3d {
+                            width: size.x,
+                            height: size.y,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format: TextureFormat::Rgba8Unorm,
+                        usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                    .create_view(&TextureViewDescriptor::default())
+            })
+            .clone()
+    }
+
+    /// Decode the scratch texture of `size` into a float canvas. Returns
+    /// `false` when skipped, while the pipeline is compiling.
+    pub(crate) fn decode(
+        &self,
+        (device, queue): (&RenderDevice, &RenderQueue),
+        (pipeline_cache, composite_pipeline): (&PipelineCache, &VelloCompositePipeline),
+        size: UVec2,
+        canvas: &TextureView,
+    ) -> bool {
+        let (Some(pipeline), Some(scratch)) = (
+            self.pipeline
+                .and_then(|id| pipeline_cache.get_render_pipeline(id)),
+            self.textures.get(&size),
+        ) else {
+            return false;
+        };
+        let bind_group = device.create_bind_group(
+            "vello_canvas_decode_bind_group",
+            &composite_pipeline.layout,
+            &BindGroupEntries::sequential((scratch, &composite_pipeline.sampler)),
+        );
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("vello_canvas_decode"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("vello_canvas_decode_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: canvas,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(default()),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit([encoder.finish()]);
+        true
+    }
+
+    /// Drop the textures of sizes which weren't used since the last call.
+    pub(crate) fn retain_used(&mut self) {
+        let used = std::mem::take(&mut self.used);
+        self.textures.retain(|size, _| used.contains(size));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::export::scene_to_svg;
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d, TextureAspect,
+};
+use bevy::render::renderer::RenderQueue;
+use bevy::render::texture::GpuImage;
+use resvg::{tiny_skia, usvg};
+use vello::Scene;
+
+/// Rasterize a scene on the CPU and upload it to a texture, for adapters
+/// without the compute shaders vello needs. The scene is drawn through its SVG
+/// export, so text can't be drawn, and an error is logged when it's skipped.
+pub(crate) fn render_to_texture(scene: &Scene, gpu_image: &GpuImage, queue: &RenderQueue) {
+    let size = gpu_image.size.as_uvec2();
+    if has_text(scene) {
+        error_once!(
+            "the CPU fallback can't draw text, so Lottie text layers and VelloText aren't drawn"
+        );
+    }
+    let data = match rasterize_shapes(scene, size) {
+        Ok(data) => data,
+        Err(err) => {
+            error!("{err}");
+            return;
+        }
+    };
+    queue.write_texture(
+        ImageCopyTexture {
+            texture: &gpu_image.texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        &data,
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size.x),
+            rows_per_image: None,
+        },
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+/// Rasterize a scene into `size` pixels on the CPU. Returns tightly packed,
+/// non-premultiplied RGBA8 pixels in sRGB, like vello's output. Scenes with
+/// text fail, rather than being drawn without it.
+pub(crate) fn rasterize(scene: &Scene, size: UVec2) -> Result<Vec<u8>, String> {
+    if has_text(scene) {
+        return Err("the CPU fallback can't draw text".to_owned());
+    }
+    rasterize_shapes(scene, size)
+}
+
+/// Whether a scene draws glyph runs, which its SVG export skips.
+fn has_text(scene: &Scene) -> bool {
+    !scene.encoding().resources.glyph_runs.is_empty()
+}
+
+/// Rasterize everything in a scene but its text.
+fn rasterize_shapes(scene: &Scene, size: UVec2) -> Result<Vec<u8>, String> {
+    let mut pixmap = tiny_skia::Pixmap::new(size.x, size.y)
+        .ok_or_else(|| format!("can't rasterize a vello scene of {size} pixels on the CPU"))?;
+    // The export has no text, so no fonts are needed
+    let svg = scene_to_svg(scene, size.as_vec2());
+    let fontdb = usvg::fontdb::Database::new();
+    let tree = usvg::Tree::from_str(&svg, &usvg::Options::default(), &fontdb)
+        .map_err(|err| format!("failed to rasterize vello scene on the CPU: {err}"))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::identity(),
+        &mut pixmap.as_mut(),
+    );
+
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use vello::glyph::Glyph;
+    use vello::kurbo::{Affine, Rect};
+    use vello::peniko::{Blob, Color, Fill, Font};
+
+    #[test]
+    fn text_fails_instead_of_disappearing() {
+        let mut scene = Scene::new();
+        let rect = Rect::new(0.0, 0.0, 2.0, 2.0);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, Color::WHITE, None, &rect);
+        let size = UVec2::new(2, 2);
+        assert_eq!(rasterize(&scene, size).unwrap(), vec![255; 16]);
+
+        let font_data = std::fs::read("examples/text/src/assets/Rubik-Medium.ttf").unwrap();
+        let font = Font::new(Blob::new(Arc::new(font_data)), 0);
+        let glyph = Glyph {
+            id: 1,
+            x: 0.0,
+            y: 0.0,
+        };
+        scene
+            .draw_glyphs(&font)
+            .brush(Color::WHITE)
+            .draw(Fill::NonZero, [glyph].into_iter());
+        assert!(rasterize(&scene, size).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use super::effects::VelloEffects;
+use super::nine_slice::NineSlice;
+use super::tiling::VelloTiling;
+use super::VelloRenderTarget;
+use crate::{
+    CoordinateSpace, VelloAnchor, VelloAsset, VelloDefaultUnits, VelloDefaultYAxis, VelloLayers,
+    VelloSize, VelloSkew, VelloUnits, VelloYAxis, VelloZoomScaling,
+};
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+
+/// How far a gaussian blur reaches, in standard deviations.
+const BLUR_REACH: f32 = 3.0;
+
+/// Give world space assets an [`Aabb`] of everything they draw, so Bevy's
+/// frustum culling hides off-screen entities and they are never encoded.
+///
+/// Entities whose bounds can't be expressed in their local space (screen
+/// space, billboards, [`VelloYAxis::Down`] and [`VelloZoomScaling::Fixed`])
+/// have their `Aabb` removed and are never culled. Add `NoFrustumCulling`
+/// to opt an entity out.
+pub fn update_aabbs(
+    mut commands: Commands,
+    query: Query<
+        (
+            Entity,
+            &Handle<VelloAsset>,
+            &CoordinateSpace,
+            (Option<&VelloUnits>, Option<&VelloSize>),
+            (
+                Option<&VelloYAxis>,
+                Option<&VelloSkew>,
+                Option<&VelloZoomScaling>,
+            ),
+            Option<&VelloAnchor>,
+            (
+                Option<&NineSlice>,
+                Option<&VelloTiling>,
+                Option<&VelloEffects>,
+            ),
+            Option<&VelloLayers>,
+            Option<&Aabb>,
+        ),
+        (Without<Node>, Without<VelloRenderTarget>),
+    >,
+    assets: Res<Assets<VelloAsset>>,
+    default_units: Res<VelloDefaultUnits>,
+    default_y_axis: Res<VelloDefaultYAxis>,
+) {
+    for (
+        entity,
+        handle,
+        coord_space,
+        (units, size),
+        (y_axis, skew, zoom),
+        anchor,
+        (nine_slice, tiling, effects),
+        layers,
+        current,
+    ) in query.iter()
+    {
+        // Unloaded assets show a placeholder of unknown size
+        let Some(asset) = assets.get(handle) else {
+            continue;
+        };
+        let y_up = *y_axis.unwrap_or(&default_y_axis.0) == VelloYAxis::Up;
+        let fixed_zoom = zoom == Some(&VelloZoomScaling::Fixed);
+        if *coord_space != CoordinateSpace::WorldSpace || !y_up || fixed_zoom {
+            if current.is_some() {
+                commands.entity(entity).remove::<Aabb>();
+            }
+            continue;
+        }
+
+        // The drawn area of the asset, from its top left with Y pointing down
+        let content_size = Vec2::new(asset.width, asset.height) / asset.baked_scale();
+        let mut drawn = Rect::from_corners(Vec2::ZERO, content_size);
+        let stretched_size = match (nine_slice, tiling) {
+            (Some(nine_slice), _) => Some(nine_slice.stretched_size()),
+            (None, Some(tiling)) => Some(tiling.size),
+            (None, None) => None,
+        };
+        let stretched = stretched_size.map_or(drawn, |size| {
+            Rect::from_center_size(content_size / 2.0, size)
+        });
+        drawn = drawn.union(stretched);
+        if let Some(shadow) = effects.and_then(|effects| effects.drop_shadow) {
+            let shadow = Rect::from_center_size(
+                stretched.center() + shadow.offset,
+                stretched.size() + 2.0 * BLUR_REACH * shadow.blur,
+            );
+            drawn = drawn.union(shadow);
+        }
+
+        // Into the entity's local space, through the (anchored) origin
+        let mut local_center = asset.local_transform_center;
+        if let Some(anchor) = anchor {
+            let origin = anchor.origin(asset);
+            local_center.translation.x = origin.x;
+            local_center.translation.y = -origin.y;
+        }
+        let to_local = local_center.compute_matrix().inverse();
+        let a = to_local.transform_point3(Vec3::new(drawn.min.x, -drawn.max.y, 0.0));
+        let b = to_local.transform_point3(Vec3::new(drawn.max.x, -drawn.min.y, 0.0));
+        let mut bounds = Rect::from_corners(a.truncate(), b.truncate());
+
+        // Layers are placed from the entity's origin, ignoring the anchor
+        for layer in layers.iter().flat_map(|layers| layers.0.iter()) {
+            if let Some(layer_asset) = assets.get(&layer.asset) {
+                let rect = layer_asset.local_rect();
+                bounds = bounds.union(Rect::from_corners(
+                    rect.min + layer.offset,
+                    rect.max + layer.offset,
+                ));
+            }
+        }
+
+        let scale = match size {
+            Some(size) => size.scale(asset),
+            None => units.unwrap_or(&default_units.0).scale(asset),
+        };
+        let mut bounds = Rect::from_corners(bounds.min * scale, bounds.max * scale);
+        // Skews apply before the entity's transform, so they widen the box
+        if let Some(skew) = skew {
+            let skew = skew.affine();
+            let corners = [
+                bounds.min,
+                bounds.max,
+                Vec2::new(bounds.min.x, bounds.max.y),
+                Vec2::new(bounds.max.x, bounds.min.y),
+            ]
+            .map(|corner| skew.transform_point3(corner.extend(0.0)).truncate());
+            bounds = corners.iter().fold(
+                Rect::from_center_size(corners[0], Vec2::ZERO),
+                |rect, corner| rect.union_point(*corner),
+            );
+        }
+        let aabb = Aabb::from_min_max(bounds.min.extend(0.0), bounds.max.extend(0.0));
+        if current != Some(&aabb) {
+            commands.entity(entity).insert(aabb);
+        }
+    }
+}
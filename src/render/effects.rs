@@ -0,0 +1,235 @@
+use super::bake::{VelloRasterTask, VelloRasterizer};
+use super::raster::render_raster;
+use crate::{VectorFile, VelloAsset};
+use bevy::prelude::*;
+use bevy::tasks::block_on;
+use bevy::tasks::futures_lite::future::poll_once;
+use bevy::utils::{HashMap, HashSet};
+use std::sync::Arc;
+use vello::kurbo::Affine;
+use vello::peniko::{Blob, Format, Image};
+use vello::Scene;
+
+/// Effects applied to an entity's rendered asset, e.g. soft shadows under UI
+/// panels and tooltips without baking them into the SVG.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct VelloEffects {
+    /// A blurred shadow of the asset's shape, drawn underneath it.
+    pub drop_shadow: Option<VelloDropShadow>,
+}
+
+/// A soft shadow of an asset's shape. Lengths are in the units the asset is
+/// drawn in, before its transform.
+///
+/// The asset's alpha is rendered offscreen and blurred, so the shadow appears
+/// a frame or two after the asset, and after each new frame of an animation.
+/// Nine-sliced and tiled assets cast the shadow of the whole asset, stretched
+/// to their bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct VelloDropShadow {
+    /// How far the shadow is offset from the asset, with Y pointing down.
+    pub offset: Vec2,
+    /// The standard deviation of the gaussian blur.
+    pub blur: f32,
+    pub color: Color,
+}
+
+impl Default for VelloDropShadow {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::new(0.0, 4.0),
+            blur: 4.0,
+            color: Color::rgba(0.0, 0.0, 0.0, 0.5),
+        }
+    }
+}
+
+/// The largest side of an offscreen shadow, in pixels.
+const MAX_SHADOW_SIZE: f32 = 2048.0;
+
+/// A shadow, by the content it is cast by and how it is rendered.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct ShadowKey {
+    content: usize,
+    frame: u64,
+    size: UVec2,
+    blur: u32,
+    color: [u8; 4],
+}
+
+enum Shadow {
+    /// The asset's alpha, being rendered offscreen.
+    Baking(VelloRasterTask),
+    /// The blurred shadow, if the asset could be rendered.
+    Ready(Option<Image>),
+}
+
+/// Drop shadows rendered from the alpha of their assets, cached by asset,
+/// frame and shadow. Shadows which weren't drawn in a frame are dropped.
+#[derive(Resource)]
+pub struct VelloShadows {
+    rasterizer: VelloRasterizer,
+    shadows: HashMap<ShadowKey, Shadow>,
+    used: HashSet<ShadowKey>,
+}
+
+impl FromWorld for VelloShadows {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            rasterizer: world.resource::<VelloRasterizer>().clone(),
+            shadows: HashMap::default(),
+            used: HashSet::default(),
+        }
+    }
+}
+
+impl VelloShadows {
+    /// Finish shadows whose alpha was rendered, returning whether any were,
+    /// so canvases are redrawn with them.
+    pub(crate) fn poll(&mut self) -> bool {
+        let mut finished = false;
+        for (key, shadow) in self.shadows.iter_mut() {
+            let Shadow::Baking(task) = shadow else {
+                continue;
+            };
+            if let Some(result) = block_on(poll_once(task)) {
+                let image = result
+                    .map_err(|err| warn!("could not render drop shadow: {err}"))
+                    .ok();
+                *shadow = Shadow::Ready(image.map(|image| shadow_image(&image, key)));
+                finished = true;
+            }
+        }
+        finished
+    }
+
+    /// Draw the shadow of a frame of an asset, stretched to `size` and
+    /// placed with `transform`, which maps to `scale` pixels per unit. The
+    /// shadow is requested on first use, and drawn once it is ready.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn render(
+        &mut self,
+        shadow: &VelloDropShadow,
+        asset: &VelloAsset,
+        frame: f64,
+        scene: &mut Scene,
+        transform: Affine,
+        size: Vec2,
+        scale: f32,
+    ) {
+        let content_size = Vec2::new(asset.width, asset.height) / asset.baked_scale();
+        // Rendered at a power of two scale, so zooming doesn't re-render it
+        // every frame
+        let scale = scale.max(f32::EPSILON).log2().round().exp2();
+        let scale = scale.min(MAX_SHADOW_SIZE / content_size.max_element().max(1.0));
+        let pixels = (content_size * scale).ceil().max(Vec2::ONE).as_uvec2();
+        let key = ShadowKey {
+            content: content_key(asset),
+            frame: frame.to_bits(),
+            size: pixels,
+            blur: (shadow.blur.max(0.0) * scale).to_bits(),
+            color: shadow.color.as_rgba_u8(),
+        };
+        self.used.insert(key);
+        let rasterizer = &self.rasterizer;
+        let entry = self.shadows.entry(key).or_insert_with(|| {
+            Shadow::Baking(rasterizer.request(asset.clone(), frame, pixels, 1.0, None))
+        });
+        let Shadow::Ready(Some(image)) = entry else {
+            return;
+        };
+        // The image is padded by the blur on every side
+        let pad = (image.width - pixels.x) as f64 / 2.0;
+        let to_size = Affine::scale_non_uniform(
+            size.x as f64 / pixels.x as f64,
+            size.y as f64 / pixels.y as f64,
+        );
+        let offset = Affine::translate((shadow.offset.x as f64, shadow.offset.y as f64));
+        let to_padded = Affine::translate((-pad, -pad));
+        render_raster(image, transform * offset * to_size * to_padded, 1.0, scene);
+    }
+
+    /// Drop the shadows which weren't drawn since the last call.
+    pub(crate) fn prune(&mut self) {
+        let used = std::mem::take(&mut self.used);
+        self.shadows.retain(|key, _| used.contains(key));
+    }
+}
+
+/// Identifies the content of an asset, shared by its copies.
+fn content_key(asset: &VelloAsset) -> usize {
+    match &asset.data {
+        VectorFile::Svg { scene, .. } => Arc::as_ptr(scene) as usize,
+        VectorFile::Lottie { composition, .. } => Arc::as_ptr(composition) as usize,
+        VectorFile::Image { image } => image.data.id() as usize,
+    }
+}
+
+/// Blur the alpha of a rendered asset into a shadow of its color, padded so
+/// the blur isn't cut off.
+fn shadow_image(rendered: &bevy::render::texture::Image, key: &ShadowKey) -> Image {
+    let sigma = f32::from_bits(key.blur);
+    let pad = (sigma * 3.0).ceil() as usize;
+    let (width, height) = (key.size.x as usize, key.size.y as usize);
+    let (padded_width, padded_height) = (width + pad * 2, height + pad * 2);
+    let mut alpha = vec![0.0; padded_width * padded_height];
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = rendered.data[(y * width + x) * 4 + 3];
+            alpha[(y + pad) * padded_width + x + pad] = pixel as f32 / 255.0;
+        }
+    }
+    gaussian_blur(&mut alpha, padded_width, padded_height, sigma);
+
+    let [r, g, b, a] = key.color;
+    let pixels = alpha
+        .iter()
+        .flat_map(|coverage| [r, g, b, (coverage * a as f32).round() as u8])
+        .collect();
+    Image::new(
+        Blob::new(Arc::new(pixels)),
+        Format::Rgba8,
+        padded_width as u32,
+        padded_height as u32,
+    )
+}
+
+/// Approximate a gaussian blur with three box blurs in each direction.
+fn gaussian_blur(values: &mut [f32], width: usize, height: usize, sigma: f32) {
+    // Three boxes of width w have a variance of 3 * (w² - 1) / 12
+    let radius = (((4.0 * sigma * sigma + 1.0).sqrt() - 1.0) / 2.0).round() as usize;
+    if radius == 0 {
+        return;
+    }
+    let mut line = vec![];
+    for _ in 0..3 {
+        for y in 0..height {
+            let row = &mut values[y * width..(y + 1) * width];
+            line.clear();
+            line.extend_from_slice(row);
+            box_blur(&line, row.iter_mut(), radius);
+        }
+        for x in 0..width {
+            line.clear();
+            line.extend((0..height).map(|y| values[y * width + x]));
+            box_blur(&line, values.iter_mut().skip(x).step_by(width), radius);
+        }
+    }
+}
+
+/// Average each value with its neighbors within `radius`, treating values
+/// past the ends as 0.
+fn box_blur<'a>(line: &[f32], out: impl Iterator<Item = &'a mut f32>, radius: usize) {
+    let diameter = (radius * 2 + 1) as f32;
+    let mut sum: f32 = line.iter().take(radius).sum();
+    for (index, value) in out.enumerate() {
+        if let Some(entering) = line.get(index + radius) {
+            sum += entering;
+        }
+        *value = sum / diameter;
+        if let Some(leaving) = index.checked_sub(radius).and_then(|index| line.get(index)) {
+            sum -= leaving;
+        }
+    }
+}
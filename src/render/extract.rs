@@ -1,74 +1,408 @@
-use super::z_function::ZFunction;
+use super::blend::VelloBlendMode;
+use super::clip::VelloClip;
+use super::effects::VelloEffects;
+use super::nine_slice::NineSlice;
+use super::provider::VelloSceneFragments;
+use super::strokes::VelloStrokeScaling;
+use super::tiling::VelloTiling;
+use super::view::{vello_views, VelloView};
+use super::z_function::{VelloZIndex, ZFunction};
+use super::{LottieRenderer, VelloRenderTarget};
 use crate::theme::Theme;
 use crate::{
-    CoordinateSpace, PlaybackAlphaOverride, Playhead, VelloAsset, VelloFont, VelloScene, VelloText,
+    CoordinateSpace, PlaybackAlphaOverride, PlaybackColorOverride, Playhead, PlayheadBucketing,
+    VectorFile, VelloAnchor, VelloAsset, VelloDefaultPlaceholder, VelloDefaultUnits,
+    VelloDefaultYAxis, VelloFont, VelloLayers, VelloPlaceholder, VelloScene, VelloSize, VelloSkew,
+    VelloText, VelloUnits, VelloYAxis, VelloZoomScaling,
 };
 use bevy::prelude::*;
 use bevy::render::extract_component::ExtractComponent;
+use bevy::render::view::RenderLayers;
 use bevy::render::Extract;
-use bevy::window::PrimaryWindow;
+use bevy::ui::{CalculatedClip, UiStack};
+use bevy::utils::HashMap;
 
 #[derive(Component, Clone)]
 pub struct ExtractedRenderAsset {
     pub asset: VelloAsset,
     pub transform: GlobalTransform,
     pub z_index: f32,
+    /// The entity's explicit draw layer.
+    pub z_layer: VelloZIndex,
+    /// The main world entity, which orders ties.
+    pub entity: Entity,
+    /// The cameras which draw this entity.
+    pub render_layers: RenderLayers,
     pub theme: Option<Theme>,
     pub render_mode: CoordinateSpace,
     pub playhead: f64,
     pub alpha: f32,
+    pub tint: Option<PlaybackColorOverride>,
     pub ui_node: Option<Node>,
+    /// The rect UI nodes are clipped to, in logical pixels.
+    pub ui_clip: Option<Rect>,
+    pub clip: Option<VelloClip>,
+    /// `None` for [`VelloBlendMode::Normal`].
+    pub blend_mode: Option<VelloBlendMode>,
+    pub effects: Option<VelloEffects>,
+    /// Sized to the UI node, if any.
+    pub nine_slice: Option<NineSlice>,
+    /// Sized to the UI node, if any.
+    pub tiling: Option<VelloTiling>,
+    pub strokes: VelloStrokeScaling,
+    /// The world position kept at a fixed size while zooming, for
+    /// [`VelloZoomScaling::Fixed`].
+    pub zoom_pivot: Option<Vec3>,
+    /// Whether the encoding may be shared with other instances at the same
+    /// playhead.
+    pub bucketed: bool,
+}
+
+/// The pivot of [`VelloZoomScaling::Fixed`] content, its translation.
+fn zoom_pivot(zoom: Option<&VelloZoomScaling>, transform: &GlobalTransform) -> Option<Vec3> {
+    (zoom == Some(&VelloZoomScaling::Fixed)).then(|| transform.translation())
+}
+
+/// Normal blending needs no layer.
+fn normal_to_none(blend_mode: Option<&VelloBlendMode>) -> Option<VelloBlendMode> {
+    blend_mode
+        .copied()
+        .filter(|blend_mode| *blend_mode != VelloBlendMode::Normal)
 }
 
 pub fn asset_instances(
     mut commands: Commands,
     query_vectors: Extract<
-        Query<(
-            &Handle<VelloAsset>,
-            &CoordinateSpace,
-            &ZFunction,
-            &GlobalTransform,
-            Option<&Playhead>,
-            Option<&Theme>,
-            Option<&PlaybackAlphaOverride>,
-            Option<&Node>,
-            &ViewVisibility,
-            &InheritedVisibility,
-        )>,
+        Query<
+            (
+                &Handle<VelloAsset>,
+                &CoordinateSpace,
+                (&ZFunction, Option<&VelloZIndex>, Option<&RenderLayers>),
+                &GlobalTransform,
+                Option<&Playhead>,
+                (Option<&Theme>, Option<&Handle<Theme>>),
+                (
+                    Option<&PlaybackAlphaOverride>,
+                    Option<&PlaybackColorOverride>,
+                ),
+                (
+                    Entity,
+                    Option<&Node>,
+                    Option<&CalculatedClip>,
+                    Option<&VelloClip>,
+                    Option<&VelloBlendMode>,
+                    Option<&VelloEffects>,
+                    Option<&NineSlice>,
+                    Option<&VelloTiling>,
+                    Option<&VelloAnchor>,
+                    Option<&VelloStrokeScaling>,
+                ),
+                Option<&VelloLayers>,
+                Option<&PlayheadBucketing>,
+                Option<&VelloPlaceholder>,
+                (Option<&VelloUnits>, Option<&VelloSize>),
+                (
+                    Option<&VelloYAxis>,
+                    Option<&VelloSkew>,
+                    Option<&VelloZoomScaling>,
+                ),
+                &ViewVisibility,
+                &InheritedVisibility,
+            ),
+            // Render targets are drawn on their own
+            Without<VelloRenderTarget>,
+        >,
     >,
     assets: Extract<Res<Assets<VelloAsset>>>,
+    themes: Extract<Res<Assets<Theme>>>,
+    time: Extract<Res<Time>>,
+    default_units: Extract<Res<VelloDefaultUnits>>,
+    default_y_axis: Extract<Res<VelloDefaultYAxis>>,
+    default_placeholder: Extract<Res<VelloDefaultPlaceholder>>,
+    ui_stack: Extract<Res<UiStack>>,
 ) {
+    let ui_order: HashMap<Entity, usize> = ui_stack
+        .uinodes
+        .iter()
+        .enumerate()
+        .map(|(index, entity)| (*entity, index))
+        .collect();
     for (
         vello_vector_handle,
         coord_space,
-        z_function,
+        (z_function, z_layer, render_layers),
         transform,
         playhead,
-        theme,
-        alpha,
-        ui_node,
+        (theme, theme_handle),
+        (alpha, tint),
+        (entity, ui_node, ui_clip, clip, blend_mode, effects, nine_slice, tiling, anchor, strokes),
+        layers,
+        bucketing,
+        placeholder,
+        (units, size),
+        (y_axis, skew, zoom),
         view_visibility,
         inherited_visibility,
     ) in query_vectors.iter()
     {
+        // UI nodes are clipped to their box and to clipping ancestors, and
+        // drawn in UI stack order
+        let ui_clip = ui_node.map(|node| {
+            let rect = node.logical_rect(transform);
+            ui_clip.map_or(rect, |clip| rect.intersect(clip.clip))
+        });
+        let ui_index = ui_node.and(ui_order.get(&entity));
+        let z_layer = z_layer.copied().unwrap_or_default();
+        // Faded out entities are invisible, so aren't encoded or rendered
+        let alpha = alpha.map(|a| a.0).unwrap_or(1.0);
+        if alpha <= 0.0 {
+            continue;
+        }
+        let blend_mode = normal_to_none(blend_mode);
+        let render_layers = render_layers.copied().unwrap_or_default();
+        let units = units.unwrap_or(&default_units.0);
+        let theme = theme.or_else(|| theme_handle.and_then(|handle| themes.get(handle)));
+        let transform = &y_axis.unwrap_or(&default_y_axis.0).apply(transform);
+        let transform = &skew.map_or(*transform, |skew| skew.apply(transform));
+        let strokes = strokes.copied().unwrap_or_default();
+        let zoom_pivot = zoom_pivot(zoom, transform);
         if let Some(asset) = assets.get(vello_vector_handle) {
             if view_visibility.get() && inherited_visibility.get() {
-                let playhead = match asset.data {
-                    crate::VectorFile::Svg { .. } => 0.0,
-                    crate::VectorFile::Lottie { .. } => playhead.unwrap().frame(),
+                let transform = &match size {
+                    Some(size) => size.apply(asset, transform),
+                    None => units.apply(asset, transform),
+                };
+                // Anchors move the origin of this entity's copy of the asset
+                let mut asset = asset.to_owned();
+                if let (Some(anchor), None) = (anchor, ui_node) {
+                    anchor.apply(&mut asset);
+                }
+                let playhead = match &asset.data {
+                    VectorFile::Svg { .. } | VectorFile::Image { .. } => 0.0,
+                    VectorFile::Lottie { composition, .. } => {
+                        // Playheads are spawned after the asset loads
+                        let frame = playhead
+                            .map(Playhead::frame)
+                            .unwrap_or(composition.frames.start);
+                        match bucketing {
+                            Some(bucketing) => bucketing.quantize(frame, &composition.frames),
+                            None => frame,
+                        }
+                    }
                 };
+                let bucketed = bucketing.is_some() && theme.is_none();
+                let z_index = match ui_index {
+                    Some(index) => *index as f32,
+                    None => z_function.compute(&asset, transform),
+                };
+                // UI nodes stretch the slices, or repeat the tiles, to fill the node
+                let nine_slice = nine_slice.map(|nine_slice| NineSlice {
+                    size: ui_node.map_or(nine_slice.size, |node| node.size() / asset.baked_scale()),
+                    ..*nine_slice
+                });
+                let tiling = tiling.map(|tiling| VelloTiling {
+                    size: ui_node.map_or(tiling.size, |node| node.size() / asset.baked_scale()),
+                    ..*tiling
+                });
                 commands.spawn(ExtractedRenderAsset {
-                    asset: asset.to_owned(),
+                    asset,
                     transform: *transform,
-                    z_index: z_function.compute(asset, transform),
+                    z_index,
+                    z_layer,
+                    entity,
+                    render_layers,
                     theme: theme.cloned(),
                     render_mode: *coord_space,
                     playhead,
-                    alpha: alpha.map(|a| a.0).unwrap_or(1.0),
+                    alpha,
+                    tint: tint.copied(),
                     ui_node: ui_node.cloned(),
+                    ui_clip,
+                    clip: clip.cloned(),
+                    blend_mode,
+                    effects: effects.copied(),
+                    nine_slice,
+                    tiling,
+                    strokes,
+                    zoom_pivot,
+                    bucketed,
                 });
+
+                // Layers share the z-index of the entity, and are spawned in
+                // order so the (stable) depth sort keeps them on top.
+                for layer in layers.iter().flat_map(|layers| layers.0.iter()) {
+                    let Some(layer_asset) = assets.get(&layer.asset) else {
+                        continue;
+                    };
+                    if layer.alpha <= 0.0 {
+                        continue;
+                    }
+                    commands.spawn(ExtractedRenderAsset {
+                        asset: layer_asset.to_owned(),
+                        transform: *transform
+                            * Transform::from_translation(layer.offset.extend(0.0)),
+                        z_index,
+                        z_layer,
+                        entity,
+                        render_layers,
+                        theme: theme.cloned(),
+                        render_mode: *coord_space,
+                        playhead,
+                        alpha: alpha * layer.alpha,
+                        tint: tint.copied(),
+                        ui_node: ui_node.cloned(),
+                        ui_clip,
+                        clip: clip.cloned(),
+                        blend_mode,
+                        // The shadow is drawn once, under the entity's asset
+                        effects: None,
+                        nine_slice: None,
+                        tiling: None,
+                        strokes,
+                        zoom_pivot,
+                        bucketed,
+                    });
+                }
             }
+        } else if let Some(placeholder) = placeholder.or(default_placeholder.0.as_ref()) {
+            if !(view_visibility.get() && inherited_visibility.get()) {
+                continue;
+            }
+            // Show the placeholder until the asset is loaded
+            let elapsed = time.elapsed_seconds_f64();
+            if let Some(scene) = placeholder.scene(elapsed) {
+                commands.spawn(ExtractedRenderScene {
+                    scene,
+                    transform: *transform,
+                    render_mode: *coord_space,
+                    z_layer,
+                    entity,
+                    render_layers,
+                    clip: clip.cloned(),
+                    blend_mode,
+                    zoom_pivot,
+                });
+            } else if let VelloPlaceholder::Asset(handle) = placeholder {
+                let Some(asset) = assets.get(handle) else {
+                    continue;
+                };
+                let transform = &match size {
+                    Some(size) => size.apply(asset, transform),
+                    None => units.apply(asset, transform),
+                };
+                let mut asset = asset.to_owned();
+                if let (Some(anchor), None) = (anchor, ui_node) {
+                    anchor.apply(&mut asset);
+                }
+                let playhead = match &asset.data {
+                    VectorFile::Svg { .. } | VectorFile::Image { .. } => 0.0,
+                    VectorFile::Lottie { composition, .. } => {
+                        let length = composition.frames.end - composition.frames.start;
+                        composition.frames.start
+                            + (elapsed * composition.frame_rate) % length.max(1.0)
+                    }
+                };
+                let z_index = match ui_index {
+                    Some(index) => *index as f32,
+                    None => z_function.compute(&asset, transform),
+                };
+                commands.spawn(ExtractedRenderAsset {
+                    asset,
+                    transform: *transform,
+                    z_index,
+                    z_layer,
+                    entity,
+                    render_layers,
+                    theme: None,
+                    render_mode: *coord_space,
+                    playhead,
+                    alpha,
+                    tint: tint.copied(),
+                    ui_node: ui_node.cloned(),
+                    ui_clip,
+                    clip: clip.cloned(),
+                    blend_mode,
+                    effects: effects.copied(),
+                    nine_slice: None,
+                    tiling: None,
+                    strokes,
+                    zoom_pivot,
+                    bucketed: false,
+                });
+            }
+        }
+    }
+}
+
+/// A vector asset rendered into an image, see [`VelloRenderTarget`].
+#[derive(Component, Clone)]
+pub struct ExtractedRenderTarget {
+    pub asset: VelloAsset,
+    pub target: Handle<Image>,
+    pub theme: Option<Theme>,
+    pub playhead: f64,
+    pub alpha: f32,
+    pub tint: Option<PlaybackColorOverride>,
+}
+
+pub fn render_target_instances(
+    mut commands: Commands,
+    query_targets: Extract<
+        Query<(
+            &Handle<VelloAsset>,
+            &VelloRenderTarget,
+            Option<&Playhead>,
+            (Option<&Theme>, Option<&Handle<Theme>>),
+            (
+                Option<&PlaybackAlphaOverride>,
+                Option<&PlaybackColorOverride>,
+            ),
+            Option<&InheritedVisibility>,
+        )>,
+    >,
+    assets: Extract<Res<Assets<VelloAsset>>>,
+    themes: Extract<Res<Assets<Theme>>>,
+) {
+    for (handle, target, playhead, (theme, theme_handle), (alpha, tint), visibility) in
+        query_targets.iter()
+    {
+        // Hidden targets keep their last image
+        if visibility.is_some_and(|visibility| !visibility.get()) {
+            continue;
         }
+        let Some(asset) = assets.get(handle) else {
+            continue;
+        };
+        let theme = theme.or_else(|| theme_handle.and_then(|handle| themes.get(handle)));
+        let playhead = match &asset.data {
+            VectorFile::Svg { .. } | VectorFile::Image { .. } => 0.0,
+            VectorFile::Lottie { composition, .. } => playhead
+                .map(Playhead::frame)
+                .unwrap_or(composition.frames.start),
+        };
+        commands.spawn(ExtractedRenderTarget {
+            asset: asset.to_owned(),
+            target: target.0.clone(),
+            theme: theme.cloned(),
+            playhead,
+            alpha: alpha.map(|a| a.0).unwrap_or(1.0),
+            tint: tint.copied(),
+        });
+    }
+}
+
+/// Release renderer-side memory when vector assets are removed. The Lottie
+/// renderer keeps its buffers between frames, sized for the largest
+/// composition it has drawn, so it is replaced to let them go.
+pub fn release_removed_assets(
+    mut events: Extract<EventReader<AssetEvent<VelloAsset>>>,
+    mut lottie_renderer: ResMut<LottieRenderer>,
+) {
+    let removed = events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Removed { .. }));
+    if removed {
+        *lottie_renderer = LottieRenderer::default();
     }
 }
 
@@ -77,31 +411,80 @@ pub struct ExtractedRenderScene {
     pub scene: VelloScene,
     pub transform: GlobalTransform,
     pub render_mode: CoordinateSpace,
+    /// The entity's explicit draw layer.
+    pub z_layer: VelloZIndex,
+    /// The main world entity, which orders ties.
+    pub entity: Entity,
+    /// The cameras which draw this entity.
+    pub render_layers: RenderLayers,
+    pub clip: Option<VelloClip>,
+    pub blend_mode: Option<VelloBlendMode>,
+    /// See [`ExtractedRenderAsset::zoom_pivot`].
+    pub zoom_pivot: Option<Vec3>,
 }
 
 pub fn scene_instances(
     mut commands: Commands,
     query_scenes: Extract<
         Query<(
+            Entity,
             &VelloScene,
             &CoordinateSpace,
             &GlobalTransform,
+            Option<&VelloZIndex>,
+            Option<&RenderLayers>,
+            Option<&VelloClip>,
+            Option<&VelloBlendMode>,
+            (Option<&VelloSkew>, Option<&VelloZoomScaling>),
             &ViewVisibility,
             &InheritedVisibility,
         )>,
     >,
+    fragments: Extract<Res<VelloSceneFragments>>,
 ) {
-    for (scene, coord_space, transform, view_visibility, inherited_visibility) in
-        query_scenes.iter()
+    for (
+        entity,
+        scene,
+        coord_space,
+        transform,
+        z_layer,
+        render_layers,
+        clip,
+        blend_mode,
+        (skew, zoom),
+        view_visibility,
+        inherited_visibility,
+    ) in query_scenes.iter()
     {
         if view_visibility.get() && inherited_visibility.get() {
             commands.spawn(ExtractedRenderScene {
-                transform: *transform,
+                transform: skew.map_or(*transform, |skew| skew.apply(transform)),
                 render_mode: *coord_space,
                 scene: scene.clone(),
+                z_layer: z_layer.copied().unwrap_or_default(),
+                entity,
+                render_layers: render_layers.copied().unwrap_or_default(),
+                clip: clip.cloned(),
+                blend_mode: normal_to_none(blend_mode),
+                zoom_pivot: zoom_pivot(zoom, transform),
             });
         }
     }
+
+    // Provided fragments aren't entities, and tie after every entity
+    for fragment in fragments.0.iter() {
+        commands.spawn(ExtractedRenderScene {
+            transform: fragment.transform,
+            render_mode: fragment.coordinate_space,
+            scene: fragment.scene.clone(),
+            z_layer: fragment.z_index,
+            entity: Entity::PLACEHOLDER,
+            render_layers: fragment.render_layers,
+            clip: None,
+            blend_mode: None,
+            zoom_pivot: None,
+        });
+    }
 }
 
 #[derive(Component, Clone)]
@@ -110,14 +493,32 @@ pub struct ExtractedRenderText {
     pub text: VelloText,
     pub transform: GlobalTransform,
     pub render_mode: CoordinateSpace,
+    /// The entity's explicit draw layer.
+    pub z_layer: VelloZIndex,
+    /// The main world entity, which orders ties.
+    pub entity: Entity,
+    /// The cameras which draw this entity.
+    pub render_layers: RenderLayers,
+    pub clip: Option<VelloClip>,
+    pub blend_mode: Option<VelloBlendMode>,
+    /// See [`ExtractedRenderAsset::zoom_pivot`].
+    pub zoom_pivot: Option<Vec3>,
 }
 
 impl ExtractComponent for ExtractedRenderText {
     type QueryData = (
+        Entity,
         &'static Handle<VelloFont>,
         &'static VelloText,
         &'static GlobalTransform,
         &'static CoordinateSpace,
+        Option<&'static VelloZIndex>,
+        Option<&'static RenderLayers>,
+        Option<&'static VelloClip>,
+        Option<&'static VelloBlendMode>,
+        Option<&'static VelloZoomScaling>,
+        &'static ViewVisibility,
+        &'static InheritedVisibility,
     );
 
     type QueryFilter = ();
@@ -125,22 +526,49 @@ impl ExtractComponent for ExtractedRenderText {
     type Out = Self;
 
     fn extract_component(
-        (vello_font_handle, text, transform, render_mode): bevy::ecs::query::QueryItem<
-            '_,
-            Self::QueryData,
-        >,
+        (
+            entity,
+            vello_font_handle,
+            text,
+            transform,
+            render_mode,
+            z_layer,
+            render_layers,
+            clip,
+            blend_mode,
+            zoom,
+            view_visibility,
+            inherited_visibility,
+        ): bevy::ecs::query::QueryItem<'_, Self::QueryData>,
     ) -> Option<Self> {
+        if !(view_visibility.get() && inherited_visibility.get()) {
+            return None;
+        }
         Some(Self {
             font: vello_font_handle.clone(),
             text: text.clone(),
             transform: *transform,
             render_mode: *render_mode,
+            z_layer: z_layer.copied().unwrap_or_default(),
+            entity,
+            render_layers: render_layers.copied().unwrap_or_default(),
+            clip: clip.cloned(),
+            blend_mode: normal_to_none(blend_mode),
+            zoom_pivot: zoom_pivot(zoom, transform),
         })
     }
 }
 
-#[derive(Component, Default)]
-pub struct SSRenderTarget(pub Handle<Image>);
+/// A canvas that vello content is rendered into.
+#[derive(Component, Clone)]
+pub struct SSRenderTarget {
+    pub image: Handle<Image>,
+    /// The index of the [`VelloCanvasDepths`](super::VelloCanvasDepths)
+    /// canvas this draws.
+    pub canvas: usize,
+    /// The camera this draws for.
+    pub camera: Entity,
+}
 
 impl ExtractComponent for SSRenderTarget {
     type QueryData = &'static SSRenderTarget;
@@ -152,21 +580,35 @@ impl ExtractComponent for SSRenderTarget {
     fn extract_component(
         ss_render_target: bevy::ecs::query::QueryItem<'_, Self::QueryData>,
     ) -> Option<Self> {
-        Some(Self(ss_render_target.0.clone()))
+        Some(ss_render_target.clone())
     }
 }
 
-#[derive(Resource)]
-pub struct ExtractedPixelScale(pub f32);
+/// A camera which renders vello content.
+#[derive(Component, Clone, Copy)]
+pub struct ExtractedVelloView {
+    pub render_layers: RenderLayers,
+    /// The scale factor of the camera's render target, e.g. its window.
+    pub pixel_scale: f32,
+}
 
-pub fn extract_pixel_scale(
-    mut pixel_scale: ResMut<ExtractedPixelScale>,
-    windows: Extract<Query<&Window, With<PrimaryWindow>>>,
+pub fn extract_vello_views(
+    mut commands: Commands,
+    cameras: Extract<Query<(Entity, &Camera, Option<&RenderLayers>, Has<VelloView>)>>,
 ) {
-    let scale_factor = windows
-        .get_single()
-        .map(|window| window.resolution.scale_factor())
-        .unwrap_or(1.0);
-
-    pixel_scale.0 = scale_factor;
+    let views = vello_views(
+        cameras
+            .iter()
+            .filter(|(_, camera, ..)| camera.is_active)
+            .map(|(entity, _, layers, marked)| (entity, layers, marked)),
+    );
+    for (entity, render_layers) in views {
+        let Ok((_, camera, ..)) = cameras.get(entity) else {
+            continue;
+        };
+        commands.get_or_spawn(entity).insert(ExtractedVelloView {
+            render_layers,
+            pixel_scale: camera.target_scaling_factor().unwrap_or(1.0),
+        });
+    }
 }
@@ -0,0 +1,280 @@
+use super::lottie::render_lottie;
+use super::raster::render_raster;
+use crate::{Theme, VectorFile, VelloAsset};
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+    ImageDataLayout, Maintain, MapMode, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDescriptor,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::utils::thiserror::{self, Error};
+use vello::kurbo::Affine;
+use vello::{AaConfig, AaSupport, RenderParams, Renderer, RendererOptions, Scene};
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum ImageRenderError {
+    #[error("Could not create renderer: {0}")]
+    Renderer(String),
+    #[error("Could not render image: {0}")]
+    Render(String),
+    #[error("Could not read image back: {0}")]
+    Readback(String),
+    #[error("Nothing to capture: {0}")]
+    Missing(String),
+    #[error("Could not save image: {0}")]
+    Save(String),
+}
+
+/// Renders single frames of vector assets to images on demand, outside of the
+/// normal ECS flow. Useful for thumbnails and asset browsers.
+///
+/// The [`RenderDevice`] and [`RenderQueue`] resources are available in both
+/// the main world and the render world.
+pub struct VelloImageRenderer {
+    renderer: Renderer,
+    lottie_renderer: velato::Renderer,
+}
+
+impl VelloImageRenderer {
+    pub fn new(device: &RenderDevice) -> Result<Self, ImageRenderError> {
+        let renderer = Renderer::new(
+            device.wgpu_device(),
+            RendererOptions {
+                surface_format: None,
+                use_cpu: false,
+                antialiasing_support: AaSupport {
+                    area: true,
+                    msaa8: false,
+                    msaa16: false,
+                },
+                num_init_threads: None,
+            },
+        )
+        .map_err(|err| ImageRenderError::Renderer(err.to_string()))?;
+        Ok(Self {
+            renderer,
+            lottie_renderer: velato::Renderer::new(),
+        })
+    }
+
+    /// Render a frame of an asset, stretched to `size` pixels, into an sRGB
+    /// [`Image`].
+    pub fn render_asset_to_image(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        asset: &VelloAsset,
+        frame: f64,
+        size: UVec2,
+        theme: Option<&Theme>,
+    ) -> Result<Image, ImageRenderError> {
+        let pixels = self.render_asset_to_pixels(device, queue, asset, frame, size, theme)?;
+        Ok(image_from_pixels(pixels, size))
+    }
+
+    /// Render a frame of an asset, stretched to `size` pixels. Returns tightly
+    /// packed, non-premultiplied RGBA8 pixels in sRGB.
+    pub fn render_asset_to_pixels(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        asset: &VelloAsset,
+        frame: f64,
+        size: UVec2,
+        theme: Option<&Theme>,
+    ) -> Result<Vec<u8>, ImageRenderError> {
+        let mut scene = Scene::new();
+        let baked = asset.baked_scale();
+        let transform = Affine::scale_non_uniform(
+            size.x as f64 / asset.width as f64 * baked.x as f64,
+            size.y as f64 / asset.height as f64 * baked.y as f64,
+        );
+        render_asset(
+            &mut self.lottie_renderer,
+            asset,
+            frame,
+            transform,
+            theme,
+            &mut scene,
+        );
+        self.render_scene_to_pixels(device, queue, &scene, size)
+    }
+
+    /// Render a scene into `size` pixels. Returns tightly packed,
+    /// non-premultiplied RGBA8 pixels in sRGB.
+    pub fn render_scene_to_pixels(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        scene: &Scene,
+        size: UVec2,
+    ) -> Result<Vec<u8>, ImageRenderError> {
+        let buffer = render_to_readback_buffer(
+            &mut self.renderer,
+            device,
+            queue,
+            scene,
+            size,
+            AaConfig::Area,
+        )?;
+
+        // Wait for the copy
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        device.map_buffer(&slice, MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|err| ImageRenderError::Readback(err.to_string()))?
+            .map_err(|err| ImageRenderError::Readback(err.to_string()))?;
+        let pixels = read_pixels(&buffer, size);
+        buffer.unmap();
+
+        Ok(pixels)
+    }
+}
+
+/// Wrap sRGB pixels, as read back from vello, in an [`Image`].
+pub(crate) fn image_from_pixels(pixels: Vec<u8>, size: UVec2) -> Image {
+    Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixels,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// Render a scene into a new texture of `size` pixels, and copy it into a
+/// mappable buffer. Rows in the buffer are padded for the copy, see
+/// [`read_pixels`].
+pub(crate) fn render_to_readback_buffer(
+    renderer: &mut Renderer,
+    device: &RenderDevice,
+    queue: &RenderQueue,
+    scene: &Scene,
+    size: UVec2,
+    antialiasing_method: AaConfig,
+) -> Result<Buffer, ImageRenderError> {
+    let extent = Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("vello_image_target"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    renderer
+        .render_to_texture(
+            device.wgpu_device(),
+            queue,
+            scene,
+            &view,
+            &RenderParams {
+                base_color: vello::peniko::Color::TRANSPARENT,
+                width: size.x,
+                height: size.y,
+                antialiasing_method,
+            },
+        )
+        .map_err(|err| ImageRenderError::Render(err.to_string()))?;
+
+    // Copy the image into a mappable buffer
+    let padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(size.x as usize * 4);
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("vello_image_readback"),
+        size: (padded_bytes_per_row * size.y as usize) as u64,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("vello_image_copy"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row as u32),
+                rows_per_image: None,
+            },
+        },
+        extent,
+    );
+    queue.submit([encoder.finish()]);
+    Ok(buffer)
+}
+
+/// Read tightly packed pixels from a mapped readback buffer, stripping row
+/// padding.
+pub(crate) fn read_pixels(buffer: &Buffer, size: UVec2) -> Vec<u8> {
+    read_rows(buffer, size, 4)
+}
+
+/// Read tightly packed pixels of `bytes_per_pixel` from a mapped readback
+/// buffer, stripping row padding.
+pub(crate) fn read_rows(buffer: &Buffer, size: UVec2, bytes_per_pixel: usize) -> Vec<u8> {
+    let unpadded_bytes_per_row = size.x as usize * bytes_per_pixel;
+    let padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(unpadded_bytes_per_row);
+    let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * size.y as usize);
+    let data = buffer.slice(..).get_mapped_range();
+    for row in data.chunks(padded_bytes_per_row) {
+        pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+    }
+    pixels
+}
+
+/// Draw a frame of an asset into a scene.
+pub(crate) fn render_asset(
+    lottie_renderer: &mut velato::Renderer,
+    asset: &VelloAsset,
+    frame: f64,
+    transform: Affine,
+    theme: Option<&Theme>,
+    scene: &mut Scene,
+) {
+    match &asset.data {
+        VectorFile::Svg { scene: svg, .. } => {
+            scene.append(svg, Some(transform));
+        }
+        VectorFile::Image { image } => {
+            render_raster(image, transform, 1.0, scene);
+        }
+        VectorFile::Lottie {
+            composition,
+            images,
+            texts,
+            ..
+        } => {
+            render_lottie(
+                lottie_renderer,
+                composition,
+                images,
+                texts,
+                frame,
+                transform,
+                1.0,
+                scene,
+                |layers| theme.map(|theme| theme.recolor(layers)),
+            );
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::assets::{LottieImageLayer, LottieTextLayer};
+use bevy::utils::HashMap;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex, Weak};
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::{Fill, Mix};
+use vello::Scene;
+
+/// Render a Lottie composition with its image and text layers. Velato draws
+/// layers from last to first, so image and text layers split the composition
+/// into runs of layers drawn in between. `restyle` may return a changed copy
+/// of each composition velato draws, e.g. recolored by a theme.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn render_lottie(
+    renderer: &mut velato::Renderer,
+    composition: &Arc<velato::Composition>,
+    images: &[LottieImageLayer],
+    texts: &[LottieTextLayer],
+    frame: f64,
+    transform: Affine,
+    alpha: f64,
+    scene: &mut Scene,
+    restyle: impl Fn(&velato::Composition) -> Option<velato::Composition>,
+) {
+    let mut render_layers = |layers: &velato::Composition, scene: &mut Scene| {
+        let restyled = restyle(layers);
+        let layers = restyled.as_ref().unwrap_or(layers);
+        renderer.render(layers, frame, transform, alpha, scene);
+    };
+    if images.is_empty() && texts.is_empty() {
+        render_layers(composition, scene);
+        return;
+    }
+
+    for run in lottie_runs(composition, images, texts).iter() {
+        match run {
+            LottieRun::Layers(layers) => render_layers(layers, scene),
+            LottieRun::Image(image) => {
+                render_image(composition, &images[*image], frame, transform, alpha, scene);
+            }
+            LottieRun::Text(text) => {
+                render_text(composition, &texts[*text], frame, transform, alpha, scene);
+            }
+        }
+    }
+}
+
+/// Part of a Lottie composition, in drawing order.
+enum LottieRun {
+    /// Layers velato draws, with the others hidden. Hidden layers have no
+    /// content, but still parent the transforms of visible ones.
+    Layers(velato::Composition),
+    /// An image layer, by its index in the asset's image layers.
+    Image(usize),
+    /// A text layer, by its index in the asset's text layers.
+    Text(usize),
+}
+
+/// The split compositions, by the composition and the layers they were split
+/// at. Entries are weak, so they're dropped with their composition, and a
+/// composition edited in place (which moves it out of `Arc::make_mut`) is
+/// split again.
+#[allow(clippy::type_complexity)]
+static LOTTIE_RUNS: Lazy<
+    Mutex<HashMap<(usize, Vec<usize>), (Weak<velato::Composition>, Arc<[LottieRun]>)>>,
+> = Lazy::new(Default::default);
+
+/// A composition split into runs at its image and text layers. Compositions
+/// are only split once, rather than copied for every run of every frame.
+fn lottie_runs(
+    composition: &Arc<velato::Composition>,
+    images: &[LottieImageLayer],
+    texts: &[LottieTextLayer],
+) -> Arc<[LottieRun]> {
+    let split_at = images
+        .iter()
+        .map(|image| image.layer)
+        .chain(texts.iter().map(|text| text.layer))
+        .collect();
+    let key = (Arc::as_ptr(composition) as usize, split_at);
+    let cached = LOTTIE_RUNS.lock().ok().and_then(|cache| {
+        let (split_from, runs) = cache.get(&key)?;
+        Weak::ptr_eq(split_from, &Arc::downgrade(composition)).then(|| runs.clone())
+    });
+    if let Some(runs) = cached {
+        return runs;
+    }
+    let runs: Arc<[LottieRun]> = split_runs(composition, images, texts).into();
+    if let Ok(mut cache) = LOTTIE_RUNS.lock() {
+        cache.retain(|_, (split_from, _)| split_from.strong_count() > 0);
+        cache.insert(key, (Arc::downgrade(composition), runs.clone()));
+    }
+    runs
+}
+
+fn split_runs(
+    composition: &velato::Composition,
+    images: &[LottieImageLayer],
+    texts: &[LottieTextLayer],
+) -> Vec<LottieRun> {
+    let mut runs = vec![];
+    let mut run = vec![];
+    for index in (0..composition.layers.len()).rev() {
+        let image = images.iter().position(|image| image.layer == index);
+        let text = texts.iter().position(|text| text.layer == index);
+        if image.is_none() && text.is_none() {
+            run.push(index);
+            continue;
+        }
+        if !run.is_empty() {
+            runs.push(LottieRun::Layers(only_layers(composition, &run)));
+            run.clear();
+        }
+        runs.extend(image.map(LottieRun::Image));
+        runs.extend(text.map(LottieRun::Text));
+    }
+    if !run.is_empty() {
+        runs.push(LottieRun::Layers(only_layers(composition, &run)));
+    }
+    runs
+}
+
+/// A copy of a composition showing only the given layers.
+fn only_layers(composition: &velato::Composition, layers: &[usize]) -> velato::Composition {
+    let mut run = composition.clone();
+    for (index, layer) in run.layers.iter_mut().enumerate() {
+        if !layers.contains(&index) {
+            layer.frames = 0.0..0.0;
+            layer.content = velato::model::Content::None;
+        }
+    }
+    run
+}
+
+fn render_image(
+    composition: &velato::Composition,
+    image: &LottieImageLayer,
+    frame: f64,
+    transform: Affine,
+    alpha: f64,
+    scene: &mut Scene,
+) {
+    let layer = &composition.layers[image.layer];
+    if !layer.frames.contains(&frame) {
+        return;
+    }
+    let transform = transform * layer_transform(composition, image.layer, frame);
+    let alpha = alpha * layer.opacity.evaluate(frame) / 100.0;
+    let image_transform = transform
+        * Affine::scale_non_uniform(
+            image.size.x as f64 / image.image.width as f64,
+            image.size.y as f64 / image.image.height as f64,
+        );
+    if alpha < 1.0 {
+        let bounds = Rect::new(0.0, 0.0, image.size.x as f64, image.size.y as f64);
+        scene.push_layer(Mix::Normal, alpha as f32, transform, &bounds);
+        scene.draw_image(&image.image, image_transform);
+        scene.pop_layer();
+    } else {
+        scene.draw_image(&image.image, image_transform);
+    }
+}
+
+fn render_text(
+    composition: &velato::Composition,
+    text: &LottieTextLayer,
+    frame: f64,
+    transform: Affine,
+    alpha: f64,
+    scene: &mut Scene,
+) {
+    let layer = &composition.layers[text.layer];
+    let Some(document) = text.document(frame) else {
+        return;
+    };
+    if !layer.frames.contains(&frame) {
+        return;
+    }
+    let transform = transform * layer_transform(composition, text.layer, frame);
+    let alpha = alpha * layer.opacity.evaluate(frame) / 100.0;
+    let brush = document
+        .color
+        .with_alpha_factor((alpha as f32).clamp(0.0, 1.0));
+    // Lines are separated by carriage returns, and start at the baseline of
+    // the layer's origin
+    for (i, line) in document.text.split(['\r', '\n', '\u{3}']).enumerate() {
+        let (glyphs, width) = text.font.layout_line(line, document.size);
+        let line_transform = transform
+            * Affine::translate((
+                -(width * document.justify) as f64,
+                (i as f32 * document.line_height) as f64,
+            ));
+        scene
+            .draw_glyphs(&text.font.font)
+            .font_size(document.size)
+            .transform(line_transform)
+            .brush(brush)
+            .draw(Fill::NonZero, glyphs.into_iter());
+    }
+}
+
+/// The transform of a layer, including the transforms of its parents.
+fn layer_transform(composition: &velato::Composition, index: usize, frame: f64) -> Affine {
+    let layer = &composition.layers[index];
+    let transform = layer.transform.evaluate(frame).into_owned();
+    match layer.parent {
+        Some(parent) if parent != index => layer_transform(composition, parent, frame) * transform,
+        _ => transform,
+    }
+}
@@ -8,26 +8,82 @@ use bevy::render::render_resource::{
 };
 use bevy::render::renderer::RenderDevice;
 use bevy::sprite::{Material2d, Material2dKey};
-use vello::{Renderer, RendererOptions};
+use vello::{AaSupport, Renderer, RendererOptions};
 
+mod bake;
+mod blend;
+mod canvas;
+mod clip;
+mod composite;
+#[cfg(feature = "cpu_fallback")]
+mod cpu;
+mod culling;
+mod effects;
 mod extract;
+mod image_renderer;
+mod lottie;
+mod nine_slice;
 mod plugin;
+mod post_process;
 mod prepare;
+mod provider;
+mod raster;
+mod redraw;
+mod render_target;
+mod screenshot;
+mod settings;
+mod strokes;
 mod systems;
+mod tiling;
+mod view;
 mod z_function;
 
+pub use bake::{VelloRasterTask, VelloRasterizer};
+pub use blend::VelloBlendMode;
+pub use canvas::VelloCanvas;
+pub use clip::VelloClip;
+pub use composite::VelloNode;
+pub use effects::{VelloDropShadow, VelloEffects};
+pub(crate) use image_renderer::render_asset;
+pub use image_renderer::{ImageRenderError, VelloImageRenderer};
+pub use nine_slice::NineSlice;
 pub use plugin::VelloRenderPlugin;
-pub use z_function::ZFunction;
+pub use post_process::VelloPostProcess;
+pub use provider::{VelloSceneFragment, VelloSceneProvider, VelloSceneProviders};
+pub use render_target::VelloRenderTarget;
+pub use screenshot::VelloScreenshot;
+pub use settings::{
+    VelloAntialiasing, VelloCameraStage, VelloCameraStages, VelloCanvasFormat, VelloCanvasStage,
+    VelloRedraw, VelloRenderSettings, VelloRendererError, VelloRendererOptions,
+};
+pub use strokes::VelloStrokeScaling;
+pub use tiling::VelloTiling;
+pub(crate) use view::vello_views;
+pub use view::{VelloEmissive, VelloView};
+pub use z_function::{VelloCanvasDepths, VelloZIndex, ZFunction};
 
 /// A handle to the screen space render target shader.
 pub const SSRT_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(2314894693238056781);
 
+/// A handle to the shader drawing canvases outside the main pass.
+pub const COMPOSITE_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(8120391848104956317);
+
+/// A handle to the shader drawing [`VelloQuad`](crate::VelloQuad)s.
+pub const QUAD_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(5092741186503218846);
+
 /// A canvas material, with a shader that samples a texture with view-independent UV coordinates.
 #[derive(AsBindGroup, TypePath, Asset, Clone)]
 pub struct VelloCanvasMaterial {
     #[texture(0)]
     #[sampler(1)]
     pub texture: Handle<Image>,
+    /// A multiplier for the color of the canvas, see [`VelloEmissive`].
+    #[uniform(2)]
+    pub intensity: f32,
+    /// 1 when the texture holds sRGB encoded color to decode, and 0 when it
+    /// holds linear color, see [`VelloCanvasFormat`].
+    #[uniform(3)]
+    pub srgb: u32,
 }
 
 impl Material2d for VelloCanvasMaterial {
@@ -59,27 +115,87 @@ impl Material2d for VelloCanvasMaterial {
     }
 }
 
-pub struct BevyVelloRenderer(Renderer);
+/// A material drawing a [`VelloQuad`](crate::VelloQuad) image in 3D, decoding
+/// the sRGB colors vello writes.
+#[derive(AsBindGroup, TypePath, Asset, Clone)]
+pub struct VelloQuadMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    pub texture: Handle<Image>,
+}
+
+impl Material for VelloQuadMaterial {
+    fn fragment_shader() -> ShaderRef {
+        QUAD_SHADER_HANDLE.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+}
+
+/// Present when the adapter can't run vello's compute shaders (e.g. WebGL2)
+/// and the `cpu_fallback` feature rasterizes vello content on the CPU
+/// instead.
+///
+/// The fallback draws through the SVG export, so text isn't drawn, and it is
+/// much slower. Images for [`VelloRenderTarget`] must be created without
+/// `STORAGE_BINDING`, which WebGL2 doesn't support.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct VelloCpuFallback;
+
+/// The vello renderer, and the antialiasing methods it was built with.
+///
+/// This is a non-send render world resource, absent when vello can't run on
+/// the GPU. Use it to render scenes of your own into textures; to draw over
+/// vello content, see [`VelloCanvas`].
+pub struct BevyVelloRenderer(Renderer, AaSupport);
+
+impl BevyVelloRenderer {
+    /// The vello renderer.
+    pub fn renderer(&mut self) -> &mut Renderer {
+        &mut self.0
+    }
+
+    /// The antialiasing methods the renderer was built with. It is rebuilt
+    /// when [`VelloRenderSettings::antialiasing`] needs another.
+    pub fn antialiasing_support(&self) -> &AaSupport {
+        &self.1
+    }
+
+    pub(crate) fn new(
+        device: &RenderDevice,
+        options: &VelloRendererOptions,
+        antialiasing_support: AaSupport,
+    ) -> Result<Self, VelloRendererError> {
+        options.check(device)?;
+        let renderer = Renderer::new(
+            device.wgpu_device(),
+            RendererOptions {
+                surface_format: None,
+                use_cpu: options.use_cpu,
+                antialiasing_support,
+                num_init_threads: options.num_init_threads,
+            },
+        )
+        .map_err(|err| VelloRendererError::Renderer(err.to_string()))?;
+        Ok(BevyVelloRenderer(renderer, antialiasing_support))
+    }
+}
 
 impl FromWorld for BevyVelloRenderer {
     fn from_world(world: &mut World) -> Self {
         let device = world.get_resource::<RenderDevice>().unwrap();
-        BevyVelloRenderer(
-            Renderer::new(
-                device.wgpu_device(),
-                RendererOptions {
-                    surface_format: None,
-                    use_cpu: false,
-                    antialiasing_support: vello::AaSupport {
-                        area: true,
-                        msaa8: false,
-                        msaa16: false,
-                    },
-                    num_init_threads: None,
-                },
-            )
-            .expect("no gpu device"),
-        )
+        let settings = world
+            .get_resource::<VelloRenderSettings>()
+            .copied()
+            .unwrap_or_default();
+        let options = world
+            .get_resource::<VelloRendererOptions>()
+            .cloned()
+            .unwrap_or_default();
+        BevyVelloRenderer::new(device, &options, settings.antialiasing.support())
+            .expect("no gpu device")
     }
 }
 
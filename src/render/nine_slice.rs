@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use bevy::sprite::BorderRect;
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::Mix;
+use vello::Scene;
+
+/// Stretches an asset to `size` while keeping its corners, e.g. for vector UI
+/// panels of any size. Edges stretch along their length and the center
+/// stretches both ways.
+///
+/// Lengths are in the units the asset is drawn in, before its transform. The
+/// stretched asset stays centered where the asset would be. On a UI node,
+/// `size` is replaced by the node's size.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct NineSlice {
+    /// How far the corners reach in from each edge of the asset.
+    pub insets: BorderRect,
+    /// The size to stretch the asset to. It is never smaller than the
+    /// corners.
+    pub size: Vec2,
+}
+
+impl NineSlice {
+    /// The size the asset is stretched to, made to fit the corners.
+    pub(crate) fn stretched_size(&self) -> Vec2 {
+        let BorderRect {
+            left,
+            right,
+            top,
+            bottom,
+        } = self.insets;
+        self.size.max(Vec2::new(left + right, top + bottom))
+    }
+
+    /// Draw `content`, an asset of `content_size`, stretched into slices.
+    pub(crate) fn render(
+        &self,
+        scene: &mut Scene,
+        content: &Scene,
+        content_size: Vec2,
+        transform: Affine,
+    ) {
+        for (dst, slice) in self.slices(content_size) {
+            scene.push_layer(Mix::Clip, 1.0, transform, &dst);
+            scene.append(content, Some(transform * slice));
+            scene.pop_layer();
+        }
+    }
+
+    /// Where each slice of an asset of `content_size` is drawn, and the
+    /// transform stretching the asset into it.
+    fn slices(&self, content_size: Vec2) -> Vec<(Rect, Affine)> {
+        let BorderRect {
+            left,
+            right,
+            top,
+            bottom,
+        } = self.insets;
+        let size = self.stretched_size();
+        let origin = (content_size - size) / 2.0;
+        let src_x = [0.0, left, content_size.x - right, content_size.x];
+        let src_y = [0.0, top, content_size.y - bottom, content_size.y];
+        let dst_x = [0.0, left, size.x - right, size.x].map(|x| origin.x + x);
+        let dst_y = [0.0, top, size.y - bottom, size.y].map(|y| origin.y + y);
+
+        let mut slices = vec![];
+        for column in 0..3 {
+            for row in 0..3 {
+                let src = Rect::new(
+                    src_x[column] as f64,
+                    src_y[row] as f64,
+                    src_x[column + 1] as f64,
+                    src_y[row + 1] as f64,
+                );
+                let dst = Rect::new(
+                    dst_x[column] as f64,
+                    dst_y[row] as f64,
+                    dst_x[column + 1] as f64,
+                    dst_y[row + 1] as f64,
+                );
+                // Slices with no area, e.g. zero insets
+                if src.area() <= 0.0 || dst.area() <= 0.0 {
+                    continue;
+                }
+                let slice = Affine::translate(dst.origin().to_vec2())
+                    * Affine::scale_non_uniform(
+                        dst.width() / src.width(),
+                        dst.height() / src.height(),
+                    )
+                    * Affine::translate(-src.origin().to_vec2());
+                slices.push((dst, slice));
+            }
+        }
+        slices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vello::kurbo::Point;
+
+    fn nine_slice(inset: f32, size: Vec2) -> NineSlice {
+        NineSlice {
+            insets: BorderRect::square(inset),
+            size,
+        }
+    }
+
+    #[test]
+    fn stretched_size_fits_the_corners() {
+        let nine_slice = nine_slice(8.0, Vec2::new(10.0, 10.0));
+        assert_eq!(nine_slice.stretched_size(), Vec2::new(16.0, 16.0));
+    }
+
+    #[test]
+    fn corners_keep_their_size_and_the_center_stretches() {
+        let slices = nine_slice(10.0, Vec2::new(90.0, 60.0)).slices(Vec2::new(30.0, 30.0));
+        assert_eq!(slices.len(), 9);
+
+        // Centered on the asset, which is 30 units square
+        let (top_left, corner) = slices[0];
+        assert_eq!(top_left, Rect::new(-30.0, -15.0, -20.0, -5.0));
+        assert_eq!(corner * Point::new(0.0, 0.0), Point::new(-30.0, -15.0));
+        assert_eq!(corner * Point::new(10.0, 10.0), Point::new(-20.0, -5.0));
+
+        let (_, center) = slices[4];
+        assert_eq!(center * Point::new(10.0, 10.0), Point::new(-20.0, -5.0));
+        assert_eq!(center * Point::new(20.0, 20.0), Point::new(50.0, 35.0));
+    }
+
+    #[test]
+    fn zero_insets_stretch_the_whole_asset() {
+        let slices = nine_slice(0.0, Vec2::new(60.0, 60.0)).slices(Vec2::new(30.0, 30.0));
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].0, Rect::new(-15.0, -15.0, 45.0, 45.0));
+    }
+}
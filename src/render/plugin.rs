@@ -1,16 +1,40 @@
-use super::extract::{self, ExtractedPixelScale, SSRenderTarget};
-use super::{prepare, systems, BevyVelloRenderer, LottieRenderer};
+use super::bake::{self, PendingBakes, VelloRasterizer};
+use super::composite::{
+    self, VelloCanvasScratch, VelloCompositeNode, VelloCompositePipeline, VelloNode,
+};
+use super::culling;
+use super::effects::VelloShadows;
+use super::extract::{self, SSRenderTarget};
+use super::post_process::{
+    self, VelloPostProcess, VelloPostProcessPipeline, VelloPostProcessScratch,
+};
+use super::provider::{self, VelloSceneFragments, VelloSceneProviders};
+use super::redraw::{self, VelloContentChanged};
+use super::screenshot::{self, PendingScreenshots, ScreenshotRequests};
+use super::{
+    prepare, systems, BevyVelloRenderer, LottieRenderer, VelloCameraStages, VelloCanvas,
+    VelloCanvasDepths, VelloCpuFallback, VelloEmissive, VelloRenderSettings, VelloRendererOptions,
+    VelloView,
+};
 use crate::render::extract::ExtractedRenderText;
-use crate::render::SSRT_SHADER_HANDLE;
-use crate::{VelloCanvasMaterial, VelloFont};
+use crate::render::{COMPOSITE_SHADER_HANDLE, QUAD_SHADER_HANDLE, SSRT_SHADER_HANDLE};
+use crate::theme::VelloPalette;
+use crate::{VelloCanvasMaterial, VelloFont, VelloQuadMaterial};
 use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::core_2d::graph::{Core2d, Node2d};
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
 use bevy::prelude::*;
 use bevy::render::extract_component::ExtractComponentPlugin;
+use bevy::render::extract_resource::ExtractResourcePlugin;
 use bevy::render::render_asset::RenderAssetPlugin;
-use bevy::render::renderer::RenderDevice;
+use bevy::render::render_graph::{RenderGraph, RenderGraphApp, RenderSubGraph, ViewNodeRunner};
+use bevy::render::render_resource::SpecializedRenderPipelines;
+use bevy::render::renderer::{RenderAdapter, RenderDevice};
+use bevy::render::view::VisibilitySystems;
 use bevy::render::{Render, RenderApp, RenderSet};
 use bevy::sprite::Material2dPlugin;
-use vello::{AaSupport, Renderer, RendererOptions};
+use bevy::ui::graph::NodeUi;
+use vello::wgpu::DownlevelFlags;
 
 pub struct VelloRenderPlugin;
 
@@ -22,6 +46,24 @@ impl Plugin for VelloRenderPlugin {
             "../../shaders/vello_ss_rendertarget.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            COMPOSITE_SHADER_HANDLE,
+            "../../shaders/vello_canvas_composite.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            QUAD_SHADER_HANDLE,
+            "../../shaders/vello_quad.wgsl",
+            Shader::from_wgsl
+        );
+
+        // Frames to bake and screenshots are queued from both worlds
+        let rasterizer = VelloRasterizer::default();
+        let screenshots = ScreenshotRequests::default();
+        app.insert_resource(rasterizer.clone())
+            .insert_resource(screenshots.clone());
 
         let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
@@ -29,13 +71,24 @@ impl Plugin for VelloRenderPlugin {
 
         render_app
             .insert_resource(LottieRenderer::default())
-            .insert_resource(ExtractedPixelScale(1.0))
+            .insert_resource(rasterizer)
+            .insert_resource(screenshots)
+            .init_resource::<SpecializedRenderPipelines<VelloCompositePipeline>>()
+            .init_resource::<VelloCanvasScratch>()
+            .init_resource::<SpecializedRenderPipelines<VelloPostProcessPipeline>>()
+            .init_resource::<VelloPostProcessScratch>()
+            .init_resource::<VelloCanvas>()
+            .init_resource::<PendingBakes>()
+            .init_resource::<VelloShadows>()
+            .init_resource::<PendingScreenshots>()
             .add_systems(
                 ExtractSchedule,
                 (
-                    extract::extract_pixel_scale.in_set(RenderSet::ExtractCommands),
                     extract::asset_instances,
                     extract::scene_instances,
+                    extract::render_target_instances,
+                    extract::extract_vello_views,
+                    extract::release_removed_assets,
                 ),
             )
             .add_systems(
@@ -47,47 +100,215 @@ impl Plugin for VelloRenderPlugin {
                 )
                     .in_set(RenderSet::Prepare),
             )
-            .add_systems(Render, systems::render_scene.in_set(RenderSet::Render));
+            .add_systems(Render, systems::prepare_renderer.in_set(RenderSet::Prepare))
+            .add_systems(
+                Render,
+                (
+                    composite::prepare_composite_pipelines,
+                    post_process::prepare_post_process_pipelines,
+                )
+                    .in_set(RenderSet::Prepare),
+            )
+            .add_systems(
+                Render,
+                (
+                    systems::render_scene,
+                    systems::render_targets,
+                    bake::bake_frames,
+                    post_process::post_process_canvases.after(systems::render_scene),
+                    screenshot::capture_canvases.after(post_process::post_process_canvases),
+                )
+                    .in_set(RenderSet::Render),
+            )
+            // Canvases drawn outside the main pass, see `VelloCanvasStage`
+            .add_render_graph_node::<ViewNodeRunner<VelloCompositeNode<false>>>(
+                Core2d,
+                VelloNode::BeforeUi,
+            )
+            .add_render_graph_node::<ViewNodeRunner<VelloCompositeNode<true>>>(
+                Core2d,
+                VelloNode::AfterUi,
+            )
+            .add_render_graph_node::<ViewNodeRunner<VelloCompositeNode<false>>>(
+                Core3d,
+                VelloNode::BeforeUi,
+            )
+            .add_render_graph_node::<ViewNodeRunner<VelloCompositeNode<true>>>(
+                Core3d,
+                VelloNode::AfterUi,
+            );
 
         app.add_plugins((
             Material2dPlugin::<VelloCanvasMaterial>::default(),
+            MaterialPlugin::<VelloQuadMaterial>::default(),
             ExtractComponentPlugin::<ExtractedRenderText>::default(),
             ExtractComponentPlugin::<SSRenderTarget>::default(),
+            ExtractComponentPlugin::<VelloPostProcess>::default(),
             RenderAssetPlugin::<VelloFont>::default(),
+            ExtractResourcePlugin::<VelloPalette>::default(),
+            ExtractResourcePlugin::<VelloCanvasDepths>::default(),
+            ExtractResourcePlugin::<VelloRenderSettings>::default(),
+            ExtractResourcePlugin::<VelloCameraStages>::default(),
+            ExtractResourcePlugin::<VelloContentChanged>::default(),
         ))
-        .add_systems(Startup, systems::setup_ss_rendertarget)
+        .init_resource::<VelloRenderSettings>()
+        .register_type::<VelloRenderSettings>()
+        .init_resource::<VelloCameraStages>()
+        .register_type::<VelloCameraStages>()
+        .init_resource::<VelloPalette>()
+        .init_resource::<VelloCanvasDepths>()
+        .register_type::<VelloCanvasDepths>()
+        .register_type::<VelloView>()
+        .register_type::<VelloEmissive>()
+        .register_type::<VelloPostProcess>()
+        .init_resource::<VelloSceneProviders>()
+        .init_resource::<VelloSceneFragments>()
+        .init_resource::<VelloContentChanged>()
+        .add_systems(
+            PostUpdate,
+            (
+                provider::collect_fragments.after(TransformSystem::TransformPropagate),
+                redraw::detect_content_changes
+                    .after(provider::collect_fragments)
+                    .after(VisibilitySystems::VisibilityPropagate),
+            ),
+        )
+        .add_systems(Update, systems::setup_ss_rendertarget)
+        .add_systems(
+            PostUpdate,
+            (
+                culling::update_aabbs.in_set(VisibilitySystems::CalculateBounds),
+                systems::hide_foreign_canvases.after(VisibilitySystems::CheckVisibility),
+            ),
+        )
         .add_systems(
             Update,
-            (systems::resize_rendertargets, systems::clear_when_empty),
+            (
+                systems::resize_rendertargets,
+                systems::update_canvas_intensity,
+                systems::clear_when_empty,
+            ),
         );
     }
 
     fn finish(&self, app: &mut App) {
+        // Build the renderer with the antialiasing chosen at startup
+        let settings = app
+            .world
+            .get_resource::<VelloRenderSettings>()
+            .copied()
+            .unwrap_or_default();
+        let options = app
+            .world
+            .get_resource::<VelloRendererOptions>()
+            .cloned()
+            .unwrap_or_default();
         let render_app = match app.get_sub_app_mut(RenderApp) {
             Ok(render_app) => render_app,
             Err(_) => return,
         };
+        render_app
+            .init_resource::<VelloCompositePipeline>()
+            .init_resource::<VelloPostProcessPipeline>();
+        // Every plugin is built by now, so the UI pass exists if it will
+        add_composite_edges(render_app);
+
+        // Kept to rebuild the renderer when the antialiasing changes
+        render_app.insert_resource(options.clone());
+
+        // Vello needs compute shaders, which e.g. WebGL2 doesn't have
+        let compute_shaders = render_app
+            .world
+            .resource::<RenderAdapter>()
+            .get_downlevel_capabilities()
+            .flags
+            .contains(DownlevelFlags::COMPUTE_SHADERS);
+        if !compute_shaders && cfg!(feature = "cpu_fallback") {
+            warn!("bevy_vello: compute shaders are unavailable, rendering on the CPU");
+            render_app.insert_resource(VelloCpuFallback);
+            app.insert_resource(VelloCpuFallback);
+            return;
+        }
 
         let device = render_app
             .world
             .get_resource::<RenderDevice>()
             .expect("bevy_vello: unable to get render device");
 
-        render_app.insert_non_send_resource(BevyVelloRenderer(
-            Renderer::new(
-                device.wgpu_device(),
-                RendererOptions {
-                    surface_format: None,
-                    use_cpu: false,
-                    antialiasing_support: AaSupport {
-                        area: true,
-                        msaa8: false,
-                        msaa16: false,
-                    },
-                    num_init_threads: None,
-                },
-            )
-            .unwrap(),
-        ));
+        match BevyVelloRenderer::new(device, &options, settings.antialiasing.support()) {
+            Ok(renderer) => {
+                render_app.insert_non_send_resource(renderer);
+            }
+            // Without a renderer, vello content is drawn on the CPU if
+            // possible, and otherwise not at all
+            Err(err) if cfg!(feature = "cpu_fallback") => {
+                warn!("bevy_vello: could not create the renderer, rendering on the CPU: {err}");
+                render_app.insert_resource(VelloCpuFallback);
+                app.insert_resource(VelloCpuFallback);
+            }
+            Err(err) => {
+                error!(
+                    "bevy_vello: could not create the renderer, vello content is not drawn: {err}"
+                );
+            }
+        }
     }
 }
+
+/// Order the canvases drawn outside the main pass around the UI pass, or
+/// after the main pass when the UI isn't rendered.
+fn add_composite_edges(render_app: &mut App) {
+    if has_ui_pass(render_app, Core2d) {
+        render_app.add_render_graph_edges(
+            Core2d,
+            (
+                Node2d::EndMainPassPostProcessing,
+                VelloNode::BeforeUi,
+                NodeUi::UiPass,
+                VelloNode::AfterUi,
+                Node2d::Upscaling,
+            ),
+        );
+    } else {
+        render_app.add_render_graph_edges(
+            Core2d,
+            (
+                Node2d::EndMainPassPostProcessing,
+                VelloNode::BeforeUi,
+                VelloNode::AfterUi,
+                Node2d::Upscaling,
+            ),
+        );
+    }
+    if has_ui_pass(render_app, Core3d) {
+        render_app.add_render_graph_edges(
+            Core3d,
+            (
+                Node3d::EndMainPassPostProcessing,
+                VelloNode::BeforeUi,
+                NodeUi::UiPass,
+                VelloNode::AfterUi,
+                Node3d::Upscaling,
+            ),
+        );
+    } else {
+        render_app.add_render_graph_edges(
+            Core3d,
+            (
+                Node3d::EndMainPassPostProcessing,
+                VelloNode::BeforeUi,
+                VelloNode::AfterUi,
+                Node3d::Upscaling,
+            ),
+        );
+    }
+}
+
+/// Whether a render graph has bevy_ui's pass, which needs the `UiPlugin`.
+fn has_ui_pass(render_app: &App, graph: impl RenderSubGraph) -> bool {
+    render_app
+        .world
+        .resource::<RenderGraph>()
+        .get_sub_graph(graph)
+        .is_some_and(|graph| graph.get_node_state(NodeUi::UiPass).is_ok())
+}
@@ -0,0 +1,247 @@
+use super::extract::SSRenderTarget;
+use super::{VelloCpuFallback, VelloRenderSettings};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponent;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
+use bevy::render::render_resource::{
+    BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+    ColorTargetState, ColorWrites, CommandEncoderDescriptor, Extent3d, FragmentState, LoadOp,
+    MultisampleState, Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderStages, SpecializedRenderPipeline, SpecializedRenderPipelines, StoreOp, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, UniformBuffer,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::utils::{HashMap, HashSet};
+
+/// A fullscreen shader applied to the vello content of a camera before it is
+/// composited, e.g. a CRT warp, a dissolve or pixelation.
+///
+/// The shader's `fragment` entry point takes Bevy's `FullscreenVertexOutput`
+/// and binds:
+/// - `@group(0) @binding(0)`: the canvas, a `texture_2d<f32>`.
+/// - `@group(0) @binding(1)`: a filtering `sampler`.
+/// - `@group(0) @binding(2)`: [`Self::params`], a `var<uniform> vec4<f32>`.
+///
+/// Colors are written back as they were sampled: sRGB encoded in 8-bit
+/// canvases, where `SRGB_CANVAS` is defined, and linear in float canvases,
+/// see [`VelloCanvasFormat`](super::VelloCanvasFormat). Each of the camera's
+/// canvases is processed on its own. Effects usually animate, so cameras
+/// with a post-process redraw their canvases every frame.
+#[derive(Component, ExtractComponent, Debug, Default, Clone, Reflect)]
+#[reflect(Component)]
+pub struct VelloPostProcess {
+    /// The WGSL shader with the `fragment` entry point.
+    pub shader: Handle<Shader>,
+    /// Passed to the shader, e.g. the progress of a dissolve.
+    pub params: Vec4,
+}
+
+#[derive(Resource)]
+pub struct VelloPostProcessPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+impl FromWorld for VelloPostProcessPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout = device.create_bind_group_layout(
+            "vello_post_process_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<Vec4>(false),
+                ),
+            ),
+        );
+        let sampler = device.create_sampler(&SamplerDescriptor::default());
+        Self { layout, sampler }
+    }
+}
+
+/// A variant of the post-process pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VelloPostProcessKey {
+    pub shader: Handle<Shader>,
+    /// The format of the canvases processed.
+    pub format: TextureFormat,
+}
+
+impl SpecializedRenderPipeline for VelloPostProcessPipeline {
+    type Key = VelloPostProcessKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let mut shader_defs = vec![];
+        if key.format == TextureFormat::Rgba8Unorm {
+            shader_defs.push("SRGB_CANVAS".into());
+        }
+        RenderPipelineDescriptor {
+            label: Some("vello_post_process_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: key.shader,
+                shader_defs,
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: key.format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+        }
+    }
+}
+
+/// The post-process pipeline of a camera.
+#[derive(Component)]
+pub struct VelloPostProcessPipelineId(CachedRenderPipelineId);
+
+/// Specialize the post-process pipeline of each camera with a
+/// [`VelloPostProcess`], for the format of its canvases.
+pub fn prepare_post_process_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<VelloPostProcessPipeline>>,
+    post_process_pipeline: Res<VelloPostProcessPipeline>,
+    settings: Res<VelloRenderSettings>,
+    cpu_fallback: Option<Res<VelloCpuFallback>>,
+    views: Query<(Entity, &VelloPostProcess)>,
+) {
+    let format = settings.canvas_texture_format(cpu_fallback.is_some());
+    for (entity, post_process) in views.iter() {
+        let key = VelloPostProcessKey {
+            shader: post_process.shader.clone(),
+            format,
+        };
+        let id = pipelines.specialize(&pipeline_cache, &post_process_pipeline, key);
+        commands
+            .entity(entity)
+            .insert(VelloPostProcessPipelineId(id));
+    }
+}
+
+/// Copies of canvases the post-process samples, by size and format.
+#[derive(Resource, Default)]
+pub struct VelloPostProcessScratch {
+    textures: HashMap<(UVec2, TextureFormat), (Texture, TextureView)>,
+    used: HashSet<(UVec2, TextureFormat)>,
+}
+
+impl VelloPostProcessScratch {
+    fn texture(
+        &mut self,
+        device: &RenderDevice,
+        size: UVec2,
+        format: TextureFormat,
+    ) -> &(Texture, TextureView) {
+        self.used.insert((size, format));
+        self.textures.entry((size, format)).or_insert_with(|| {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some("vello_post_process_source"),
+                size: Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            (texture, view)
+        })
+    }
+}
+
+/// Run the [`VelloPostProcess`] of each camera over its freshly rendered
+/// canvases. Canvases are skipped while the shader compiles.
+#[allow(clippy::too_many_arguments)]
+pub fn post_process_canvases(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    canvases: Query<&SSRenderTarget>,
+    views: Query<(&VelloPostProcess, &VelloPostProcessPipelineId)>,
+    gpu_images: Res<RenderAssets<Image>>,
+    pipeline_cache: Res<PipelineCache>,
+    post_process_pipeline: Res<VelloPostProcessPipeline>,
+    mut scratch: ResMut<VelloPostProcessScratch>,
+) {
+    for target in canvases.iter() {
+        let Ok((post_process, pipeline_id)) = views.get(target.camera) else {
+            continue;
+        };
+        let (Some(pipeline), Some(gpu_image)) = (
+            pipeline_cache.get_render_pipeline(pipeline_id.0),
+            gpu_images.get(&target.image),
+        ) else {
+            continue;
+        };
+        let size = gpu_image.size.as_uvec2();
+        if size.x == 0 || size.y == 0 {
+            continue;
+        }
+
+        // The canvas is drawn to, so the shader samples a copy of it
+        let (source, source_view) = scratch.texture(&device, size, gpu_image.texture_format);
+        let mut params = UniformBuffer::from(post_process.params);
+        params.write_buffer(&device, &queue);
+        let Some(params) = params.binding() else {
+            continue;
+        };
+        let bind_group = device.create_bind_group(
+            "vello_post_process_bind_group",
+            &post_process_pipeline.layout,
+            &BindGroupEntries::sequential((source_view, &post_process_pipeline.sampler, params)),
+        );
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("vello_post_process"),
+        });
+        encoder.copy_texture_to_texture(
+            gpu_image.texture.as_image_copy(),
+            source.as_image_copy(),
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("vello_post_process_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &gpu_image.texture_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(default()),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit([encoder.finish()]);
+    }
+
+    // Drop the copies of sizes which weren't used this frame
+    let used = std::mem::take(&mut scratch.used);
+    scratch.textures.retain(|key, _| used.contains(key));
+}
@@ -1,222 +1,375 @@
 use super::extract::{
-    ExtractedPixelScale, ExtractedRenderAsset, ExtractedRenderScene, ExtractedRenderText,
+    ExtractedRenderAsset, ExtractedRenderScene, ExtractedRenderText, ExtractedVelloView,
 };
 use crate::CoordinateSpace;
 use bevy::prelude::*;
 use bevy::render::camera::ExtractedCamera;
 use bevy::render::view::ExtractedView;
+use bevy::utils::HashMap;
 use vello::kurbo::Affine;
 
-#[derive(Component, Copy, Clone)]
-pub struct PreparedAffine(pub Affine);
+/// The affine of an extracted entity for each camera that draws it.
+#[derive(Component, Clone, Default)]
+pub struct PreparedAffines(pub HashMap<Entity, Affine>);
+
+/// Place a billboard in physical pixels with Y down: at the projection of its
+/// translation, facing the camera, and scaled by its distance. `None` when it
+/// is behind the camera.
+fn billboard_matrix(
+    view: &ExtractedView,
+    world_transform: &GlobalTransform,
+    size_pixels: Vec2,
+) -> Option<Mat4> {
+    let view_proj_matrix = view.projection * view.transform.compute_matrix().inverse();
+    let clip = view_proj_matrix * world_transform.translation().extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.xy() / clip.w;
+    let position = Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) / 2.0 * size_pixels;
+    // Pixels covered by a world unit at the billboard's distance
+    let pixels_per_unit = view.projection.y_axis.y * size_pixels.y / 2.0 / clip.w;
+    let (scale, _, _) = world_transform.to_scale_rotation_translation();
+    Some(Mat4::from_scale_rotation_translation(
+        (scale.xy() * pixels_per_unit).extend(1.0),
+        Quat::IDENTITY,
+        position.extend(0.0),
+    ))
+}
+
+/// Keep [`VelloZoomScaling::Fixed`](crate::VelloZoomScaling::Fixed) content
+/// at one world unit per logical pixel around `pivot`, undoing the zoom of an
+/// orthographic view.
+fn fixed_zoom_matrix(
+    view: &ExtractedView,
+    size_pixels: Vec2,
+    pixel_scale: f32,
+    pivot: Option<Vec3>,
+) -> Mat4 {
+    // Perspective views have no single zoom
+    let (Some(pivot), true) = (pivot, view.projection.w_axis.w == 1.0) else {
+        return Mat4::IDENTITY;
+    };
+    let world_to_ndc = view.projection * view.transform.compute_matrix().inverse();
+    let pixels_per_unit = (world_to_ndc.x_axis.xy() * size_pixels / 2.0).length();
+    if pixels_per_unit <= 0.0 {
+        return Mat4::IDENTITY;
+    }
+    let scale = pixel_scale / pixels_per_unit;
+    Mat4::from_translation(pivot)
+        * Mat4::from_scale(Vec3::new(scale, scale, 1.0))
+        * Mat4::from_translation(-pivot)
+}
 
 pub fn prepare_vector_affines(
     mut commands: Commands,
-    camera: Query<(&ExtractedCamera, &ExtractedView)>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ExtractedView,
+        &ExtractedVelloView,
+    )>,
     mut render_vectors: Query<(Entity, &ExtractedRenderAsset)>,
-    pixel_scale: Res<ExtractedPixelScale>,
 ) {
-    let Ok((camera, view)) = camera.get_single() else {
-        return;
-    };
-    let size_pixels: UVec2 = camera.physical_viewport_size.unwrap();
-    let (pixels_x, pixels_y) = (size_pixels.x as f32, size_pixels.y as f32);
     for (entity, render_vector) in render_vectors.iter_mut() {
-        let ndc_to_pixels_matrix = Mat4::from_cols_array_2d(&[
-            [pixels_x / 2.0, 0.0, 0.0, pixels_x / 2.0],
-            [0.0, pixels_y / 2.0, 0.0, pixels_y / 2.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ])
-        .transpose();
-
-        let world_transform = render_vector.transform;
-
-        let local_center_matrix = render_vector
-            .asset
-            .local_transform_center
-            .compute_matrix()
-            .inverse();
-        let vector_size = Vec2::new(render_vector.asset.width, render_vector.asset.height);
-
-        let raw_transform = match render_vector.render_mode {
-            CoordinateSpace::ScreenSpace => {
-                let mut model_matrix = world_transform.compute_matrix().mul_scalar(pixel_scale.0);
-
-                // Make the screen space vector instance sized to fill the
-                // entire UI Node box if it's bundled with a Node
-                if let Some(node) = &render_vector.ui_node {
-                    let fill_scale = node.size() / vector_size;
-                    model_matrix.x_axis.x *= fill_scale.x;
-                    model_matrix.y_axis.y *= fill_scale.y;
-                }
-
-                let mut local_center_matrix = local_center_matrix;
-                local_center_matrix.w_axis.y *= -1.0;
-                model_matrix * local_center_matrix
+        let mut affines = PreparedAffines::default();
+        for (
+            view_entity,
+            camera,
+            view,
+            ExtractedVelloView {
+                render_layers,
+                pixel_scale,
+            },
+        ) in views.iter()
+        {
+            if !render_layers.intersects(&render_vector.render_layers) {
+                continue;
             }
-            CoordinateSpace::WorldSpace => {
-                let local_matrix = local_center_matrix;
+            let Some(size_pixels) = camera.physical_viewport_size else {
+                continue;
+            };
+            let (pixels_x, pixels_y) = (size_pixels.x as f32, size_pixels.y as f32);
+            let ndc_to_pixels_matrix = Mat4::from_cols_array_2d(&[
+                [pixels_x / 2.0, 0.0, 0.0, pixels_x / 2.0],
+                [0.0, pixels_y / 2.0, 0.0, pixels_y / 2.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+            .transpose();
+
+            let world_transform = render_vector.transform;
+
+            let local_center_matrix = render_vector
+                .asset
+                .local_transform_center
+                .compute_matrix()
+                .inverse();
+            let vector_size = Vec2::new(render_vector.asset.width, render_vector.asset.height);
+
+            let raw_transform = match render_vector.render_mode {
+                CoordinateSpace::ScreenSpace => {
+                    let mut model_matrix =
+                        world_transform.compute_matrix().mul_scalar(*pixel_scale);
+
+                    // Make the screen space vector instance sized to fill the
+                    // entire UI Node box if it's bundled with a Node. Nine
+                    // slices are stretched, and tiles repeated, to fill it
+                    // instead.
+                    let fills_node =
+                        render_vector.nine_slice.is_some() || render_vector.tiling.is_some();
+                    if let (Some(node), false) = (&render_vector.ui_node, fills_node) {
+                        let fill_scale = node.size() / vector_size;
+                        model_matrix.x_axis.x *= fill_scale.x;
+                        model_matrix.y_axis.y *= fill_scale.y;
+                    }
+
+                    let mut local_center_matrix = local_center_matrix;
+                    local_center_matrix.w_axis.y *= -1.0;
+                    model_matrix * local_center_matrix
+                }
+                CoordinateSpace::Billboard3d => {
+                    let Some(model_matrix) =
+                        billboard_matrix(view, &world_transform, size_pixels.as_vec2())
+                    else {
+                        continue;
+                    };
+                    let mut local_center_matrix = local_center_matrix;
+                    local_center_matrix.w_axis.y *= -1.0;
+                    model_matrix * local_center_matrix
+                }
+                CoordinateSpace::WorldSpace => {
+                    let local_matrix = local_center_matrix;
+                    let zoom_matrix = fixed_zoom_matrix(
+                        view,
+                        size_pixels.as_vec2(),
+                        *pixel_scale,
+                        render_vector.zoom_pivot,
+                    );
 
-                let mut model_matrix = world_transform.compute_matrix() * local_matrix;
-                model_matrix.w_axis.y *= -1.0;
+                    let mut model_matrix =
+                        zoom_matrix * world_transform.compute_matrix() * local_matrix;
+                    model_matrix.w_axis.y *= -1.0;
 
-                let (projection_mat, view_mat) = {
-                    let mut view_mat = view.transform.compute_matrix();
-                    view_mat.w_axis.y *= -1.0;
+                    let (projection_mat, view_mat) = {
+                        let mut view_mat = view.transform.compute_matrix();
+                        view_mat.w_axis.y *= -1.0;
 
-                    (view.projection, view_mat)
-                };
+                        (view.projection, view_mat)
+                    };
 
-                let view_proj_matrix = projection_mat * view_mat.inverse();
+                    let view_proj_matrix = projection_mat * view_mat.inverse();
 
-                ndc_to_pixels_matrix * view_proj_matrix * model_matrix
-            }
-        };
-
-        let transform: [f32; 16] = raw_transform.to_cols_array();
-
-        // | a c e |
-        // | b d f |
-        // | 0 0 1 |
-        let transform: [f64; 6] = [
-            transform[0] as f64,  // a
-            -transform[1] as f64, // b
-            -transform[4] as f64, // c
-            transform[5] as f64,  // d
-            transform[12] as f64, // e
-            transform[13] as f64, // f
-        ];
-
-        commands
-            .entity(entity)
-            .insert(PreparedAffine(Affine::new(transform)));
+                    ndc_to_pixels_matrix * view_proj_matrix * model_matrix
+                }
+            };
+
+            let transform: [f32; 16] = raw_transform.to_cols_array();
+
+            // | a c e |
+            // | b d f |
+            // | 0 0 1 |
+            let transform: [f64; 6] = [
+                transform[0] as f64,  // a
+                -transform[1] as f64, // b
+                -transform[4] as f64, // c
+                transform[5] as f64,  // d
+                transform[12] as f64, // e
+                transform[13] as f64, // f
+            ];
+
+            affines.0.insert(view_entity, Affine::new(transform));
+        }
+        commands.entity(entity).insert(affines);
     }
 }
 
 pub fn prepare_scene_affines(
     mut commands: Commands,
-    camera: Query<(&ExtractedCamera, &ExtractedView)>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ExtractedView,
+        &ExtractedVelloView,
+    )>,
     mut render_vectors: Query<(Entity, &ExtractedRenderScene)>,
-    pixel_scale: Res<ExtractedPixelScale>,
 ) {
-    let Ok((camera, view)) = camera.get_single() else {
-        return;
-    };
-    let size_pixels: UVec2 = camera.physical_viewport_size.unwrap();
-    let (pixels_x, pixels_y) = (size_pixels.x as f32, size_pixels.y as f32);
     for (entity, render_vector) in render_vectors.iter_mut() {
-        let ndc_to_pixels_matrix = Mat4::from_cols_array_2d(&[
-            [pixels_x / 2.0, 0.0, 0.0, pixels_x / 2.0],
-            [0.0, pixels_y / 2.0, 0.0, pixels_y / 2.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ])
-        .transpose();
-
-        let world_transform = render_vector.transform;
-
-        let raw_transform = match render_vector.render_mode {
-            CoordinateSpace::ScreenSpace => {
-                let mut model_matrix = world_transform.compute_matrix().mul_scalar(pixel_scale.0);
-                model_matrix.w_axis.y *= -1.0;
-                model_matrix
+        let mut affines = PreparedAffines::default();
+        for (
+            view_entity,
+            camera,
+            view,
+            ExtractedVelloView {
+                render_layers,
+                pixel_scale,
+            },
+        ) in views.iter()
+        {
+            if !render_layers.intersects(&render_vector.render_layers) {
+                continue;
             }
-            CoordinateSpace::WorldSpace => {
-                let mut model_matrix = world_transform.compute_matrix();
-                model_matrix.w_axis.y *= -1.0;
-
-                let (projection_mat, view_mat) = {
-                    let mut view_mat = view.transform.compute_matrix();
-                    view_mat.w_axis.y *= -1.0;
-
-                    (view.projection, view_mat)
-                };
-
-                let view_proj_matrix = projection_mat * view_mat.inverse();
-
-                ndc_to_pixels_matrix * view_proj_matrix * model_matrix
-            }
-        };
-
-        let transform: [f32; 16] = raw_transform.to_cols_array();
-
-        // | a c e |
-        // | b d f |
-        // | 0 0 1 |
-        let transform: [f64; 6] = [
-            transform[0] as f64,  // a
-            -transform[1] as f64, // b
-            -transform[4] as f64, // c
-            transform[5] as f64,  // d
-            transform[12] as f64, // e
-            transform[13] as f64, // f
-        ];
-
-        commands
-            .entity(entity)
-            .insert(PreparedAffine(Affine::new(transform)));
+            let Some(size_pixels) = camera.physical_viewport_size else {
+                continue;
+            };
+            let (pixels_x, pixels_y) = (size_pixels.x as f32, size_pixels.y as f32);
+            let ndc_to_pixels_matrix = Mat4::from_cols_array_2d(&[
+                [pixels_x / 2.0, 0.0, 0.0, pixels_x / 2.0],
+                [0.0, pixels_y / 2.0, 0.0, pixels_y / 2.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+            .transpose();
+
+            let world_transform = render_vector.transform;
+
+            let raw_transform = match render_vector.render_mode {
+                CoordinateSpace::ScreenSpace => {
+                    let mut model_matrix =
+                        world_transform.compute_matrix().mul_scalar(*pixel_scale);
+                    model_matrix.w_axis.y *= -1.0;
+                    model_matrix
+                }
+                CoordinateSpace::Billboard3d => {
+                    let Some(model_matrix) =
+                        billboard_matrix(view, &world_transform, size_pixels.as_vec2())
+                    else {
+                        continue;
+                    };
+                    model_matrix
+                }
+                CoordinateSpace::WorldSpace => {
+                    let zoom_matrix = fixed_zoom_matrix(
+                        view,
+                        size_pixels.as_vec2(),
+                        *pixel_scale,
+                        render_vector.zoom_pivot,
+                    );
+                    let mut model_matrix = zoom_matrix * world_transform.compute_matrix();
+                    model_matrix.w_axis.y *= -1.0;
+
+                    let (projection_mat, view_mat) = {
+                        let mut view_mat = view.transform.compute_matrix();
+                        view_mat.w_axis.y *= -1.0;
+
+                        (view.projection, view_mat)
+                    };
+
+                    let view_proj_matrix = projection_mat * view_mat.inverse();
+
+                    ndc_to_pixels_matrix * view_proj_matrix * model_matrix
+                }
+            };
+
+            let transform: [f32; 16] = raw_transform.to_cols_array();
+
+            // | a c e |
+            // | b d f |
+            // | 0 0 1 |
+            let transform: [f64; 6] = [
+                transform[0] as f64,  // a
+                -transform[1] as f64, // b
+                -transform[4] as f64, // c
+                transform[5] as f64,  // d
+                transform[12] as f64, // e
+                transform[13] as f64, // f
+            ];
+
+            affines.0.insert(view_entity, Affine::new(transform));
+        }
+        commands.entity(entity).insert(affines);
     }
 }
 
 pub fn prepare_text_affines(
     mut commands: Commands,
-    camera: Query<(&ExtractedCamera, &ExtractedView)>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ExtractedView,
+        &ExtractedVelloView,
+    )>,
     render_texts: Query<(Entity, &ExtractedRenderText)>,
-    pixel_scale: Res<ExtractedPixelScale>,
 ) {
-    let Ok((camera, view)) = camera.get_single() else {
-        return;
-    };
-    let size_pixels: UVec2 = camera.physical_viewport_size.unwrap();
-    let (pixels_x, pixels_y) = (size_pixels.x as f32, size_pixels.y as f32);
     for (entity, render_text) in render_texts.iter() {
-        let ndc_to_pixels_matrix = Mat4::from_cols_array_2d(&[
-            [pixels_x / 2.0, 0.0, 0.0, pixels_x / 2.0],
-            [0.0, pixels_y / 2.0, 0.0, pixels_y / 2.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [0.0, 0.0, 0.0, 1.0],
-        ])
-        .transpose();
-
-        let world_transform = render_text.transform;
-
-        let mut model_matrix = world_transform.compute_matrix();
-        model_matrix.w_axis.y *= -1.0;
-
-        let (projection_mat, view_mat) = {
-            let mut view_mat = view.transform.compute_matrix();
-            view_mat.w_axis.y *= -1.0;
-
-            (view.projection, view_mat)
-        };
-
-        let view_proj_matrix = projection_mat * view_mat.inverse();
-        let vello_matrix = ndc_to_pixels_matrix * view_proj_matrix;
-
-        let raw_transform = match render_text.render_mode {
-            CoordinateSpace::ScreenSpace => {
-                world_transform.compute_matrix().mul_scalar(pixel_scale.0)
+        let mut affines = PreparedAffines::default();
+        for (
+            view_entity,
+            camera,
+            view,
+            ExtractedVelloView {
+                render_layers,
+                pixel_scale,
+            },
+        ) in views.iter()
+        {
+            if !render_layers.intersects(&render_text.render_layers) {
+                continue;
             }
-            CoordinateSpace::WorldSpace => vello_matrix * model_matrix,
-        };
-
-        let transform: [f32; 16] = raw_transform.to_cols_array();
-
-        // | a c e |
-        // | b d f |
-        // | 0 0 1 |
-        let transform: [f64; 6] = [
-            transform[0] as f64,  // a
-            -transform[1] as f64, // b
-            -transform[4] as f64, // c
-            transform[5] as f64,  // d
-            transform[12] as f64, // e
-            transform[13] as f64, // f
-        ];
-
-        commands
-            .entity(entity)
-            .insert(PreparedAffine(Affine::new(transform)));
+            let Some(size_pixels) = camera.physical_viewport_size else {
+                continue;
+            };
+            let (pixels_x, pixels_y) = (size_pixels.x as f32, size_pixels.y as f32);
+            let ndc_to_pixels_matrix = Mat4::from_cols_array_2d(&[
+                [pixels_x / 2.0, 0.0, 0.0, pixels_x / 2.0],
+                [0.0, pixels_y / 2.0, 0.0, pixels_y / 2.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+            .transpose();
+
+            let world_transform = render_text.transform;
+            let zoom_matrix = fixed_zoom_matrix(
+                view,
+                size_pixels.as_vec2(),
+                *pixel_scale,
+                render_text.zoom_pivot,
+            );
+
+            let mut model_matrix = zoom_matrix * world_transform.compute_matrix();
+            model_matrix.w_axis.y *= -1.0;
+
+            let (projection_mat, view_mat) = {
+                let mut view_mat = view.transform.compute_matrix();
+                view_mat.w_axis.y *= -1.0;
+
+                (view.projection, view_mat)
+            };
+
+            let view_proj_matrix = projection_mat * view_mat.inverse();
+            let vello_matrix = ndc_to_pixels_matrix * view_proj_matrix;
+
+            let raw_transform = match render_text.render_mode {
+                CoordinateSpace::ScreenSpace => {
+                    world_transform.compute_matrix().mul_scalar(*pixel_scale)
+                }
+                CoordinateSpace::WorldSpace => vello_matrix * model_matrix,
+                CoordinateSpace::Billboard3d => {
+                    let Some(model_matrix) =
+                        billboard_matrix(view, &world_transform, size_pixels.as_vec2())
+                    else {
+                        continue;
+                    };
+                    model_matrix
+                }
+            };
+
+            let transform: [f32; 16] = raw_transform.to_cols_array();
+
+            // | a c e |
+            // | b d f |
+            // | 0 0 1 |
+            let transform: [f64; 6] = [
+                transform[0] as f64,  // a
+                -transform[1] as f64, // b
+                -transform[4] as f64, // c
+                transform[5] as f64,  // d
+                transform[12] as f64, // e
+                transform[13] as f64, // f
+            ];
+
+            affines.0.insert(view_entity, Affine::new(transform));
+        }
+        commands.entity(entity).insert(affines);
     }
 }
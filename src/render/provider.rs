@@ -0,0 +1,57 @@
+use super::z_function::VelloZIndex;
+use crate::{CoordinateSpace, VelloScene};
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+
+/// Vello content for one frame, from a [`VelloSceneProvider`].
+#[derive(Clone, Default)]
+pub struct VelloSceneFragment {
+    pub scene: VelloScene,
+    /// Where the scene is drawn. As for entities, its Z translation orders
+    /// it among other vello content.
+    pub transform: GlobalTransform,
+    pub coordinate_space: CoordinateSpace,
+    /// The explicit draw layer, as a [`VelloZIndex`] on an entity.
+    pub z_index: VelloZIndex,
+    /// The cameras which draw this fragment.
+    pub render_layers: RenderLayers,
+}
+
+/// Contributes vello content every frame without spawning entities, so other
+/// crates (e.g. charts or gizmos) can draw through bevy_vello's canvases.
+/// Fragments are ordered and drawn with every other vello entity.
+///
+/// Register providers with [`VelloSceneProviders::add`].
+pub trait VelloSceneProvider: Send + Sync + 'static {
+    /// Push this frame's fragments. Called in `PostUpdate`, once transforms
+    /// are propagated.
+    fn provide(&mut self, world: &World, fragments: &mut Vec<VelloSceneFragment>);
+}
+
+/// The registered [`VelloSceneProvider`]s. Providers can be added before
+/// the [`VelloPlugin`](crate::VelloPlugin), e.g. by a crate's own plugin,
+/// with `app.world.get_resource_or_insert_with(VelloSceneProviders::default)`.
+#[derive(Resource, Default)]
+pub struct VelloSceneProviders(Vec<Box<dyn VelloSceneProvider>>);
+
+impl VelloSceneProviders {
+    pub fn add(&mut self, provider: impl VelloSceneProvider) -> &mut Self {
+        self.0.push(Box::new(provider));
+        self
+    }
+}
+
+/// The fragments provided this frame, to extract.
+#[derive(Resource, Default)]
+pub struct VelloSceneFragments(pub(crate) Vec<VelloSceneFragment>);
+
+/// Collect this frame's fragments from every provider.
+pub fn collect_fragments(world: &mut World) {
+    world.resource_scope(|world, mut providers: Mut<VelloSceneProviders>| {
+        let mut fragments = Vec::new();
+        for provider in providers.0.iter_mut() {
+            provider.provide(world, &mut fragments);
+        }
+        world.resource_mut::<VelloSceneFragments>().0 = fragments;
+    });
+}
@@ -0,0 +1,15 @@
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::{Image, Mix};
+use vello::Scene;
+
+/// Render a raster image at its pixel size, in the same space as SVG content.
+pub(crate) fn render_raster(image: &Image, transform: Affine, alpha: f64, scene: &mut Scene) {
+    if alpha < 1.0 {
+        let bounds = Rect::new(0.0, 0.0, image.width as f64, image.height as f64);
+        scene.push_layer(Mix::Normal, alpha as f32, transform, &bounds);
+        scene.draw_image(image, transform);
+        scene.pop_layer();
+    } else {
+        scene.draw_image(image, transform);
+    }
+}
@@ -0,0 +1,156 @@
+use super::blend::VelloBlendMode;
+use super::clip::VelloClip;
+use super::effects::VelloEffects;
+use super::nine_slice::NineSlice;
+use super::post_process::VelloPostProcess;
+use super::provider::VelloSceneFragments;
+use super::strokes::VelloStrokeScaling;
+use super::tiling::VelloTiling;
+use super::z_function::{VelloZIndex, ZFunction};
+use super::{VelloCameraStages, VelloCanvasDepths, VelloRenderSettings, VelloView};
+use crate::theme::{Theme, VelloPalette};
+use crate::{
+    CoordinateSpace, PlaybackAlphaOverride, PlaybackColorOverride, Playhead, VelloAnchor,
+    VelloAsset, VelloDefaultUnits, VelloDefaultYAxis, VelloFont, VelloLayers, VelloPlaceholder,
+    VelloScene, VelloSize, VelloSkew, VelloText, VelloUnits, VelloYAxis, VelloZoomScaling,
+};
+use bevy::asset::LoadState;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResource;
+use bevy::render::view::RenderLayers;
+use bevy::ui::CalculatedClip;
+
+/// Whether anything vello draws changed this frame, see
+/// [`VelloRedraw`](super::VelloRedraw).
+#[derive(Resource, ExtractResource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VelloContentChanged(pub bool);
+
+/// Changes to what an entity draws, or how it is placed.
+type ContentChanges = Or<(
+    Changed<GlobalTransform>,
+    Changed<InheritedVisibility>,
+    Changed<CoordinateSpace>,
+    Changed<RenderLayers>,
+    Changed<Handle<VelloAsset>>,
+    Changed<Playhead>,
+    Changed<VelloScene>,
+    Changed<VelloText>,
+    Changed<Handle<VelloFont>>,
+    Changed<Theme>,
+    Changed<Handle<Theme>>,
+    Changed<Node>,
+    Changed<CalculatedClip>,
+    Or<(
+        Changed<PlaybackAlphaOverride>,
+        Changed<PlaybackColorOverride>,
+        Changed<VelloZIndex>,
+        Changed<ZFunction>,
+        Changed<VelloClip>,
+        Changed<VelloBlendMode>,
+        Changed<VelloEffects>,
+        Changed<NineSlice>,
+        Changed<VelloTiling>,
+        Changed<VelloStrokeScaling>,
+        Changed<VelloLayers>,
+        Changed<VelloPlaceholder>,
+    )>,
+    Or<(
+        Changed<VelloAnchor>,
+        Changed<VelloSize>,
+        Changed<VelloUnits>,
+        Changed<VelloYAxis>,
+        Changed<VelloSkew>,
+        Changed<VelloZoomScaling>,
+    )>,
+)>;
+
+/// Changes to cameras, which move or resize everything they draw.
+type CameraChanges = Or<(
+    Changed<Camera>,
+    Changed<GlobalTransform>,
+    Changed<OrthographicProjection>,
+    Changed<Projection>,
+    Changed<RenderLayers>,
+    Changed<VelloView>,
+    Changed<VelloPostProcess>,
+)>;
+
+#[derive(SystemParam)]
+pub struct ContentEvents<'w, 's> {
+    removed_spaces: RemovedComponents<'w, 's, CoordinateSpace>,
+    removed_assets: RemovedComponents<'w, 's, Handle<VelloAsset>>,
+    removed_cameras: RemovedComponents<'w, 's, Camera>,
+    removed_post_processes: RemovedComponents<'w, 's, VelloPostProcess>,
+    removed_alphas: RemovedComponents<'w, 's, PlaybackAlphaOverride>,
+    removed_tints: RemovedComponents<'w, 's, PlaybackColorOverride>,
+    removed_themes: RemovedComponents<'w, 's, Theme>,
+    asset_events: EventReader<'w, 's, AssetEvent<VelloAsset>>,
+    font_events: EventReader<'w, 's, AssetEvent<VelloFont>>,
+    theme_events: EventReader<'w, 's, AssetEvent<Theme>>,
+}
+
+impl ContentEvents<'_, '_> {
+    /// Whether something was removed, or an asset changed. Reads every
+    /// event, so they aren't seen again next frame.
+    fn any(&mut self) -> bool {
+        // Not short-circuited, to drain every reader
+        [
+            self.removed_spaces.read().count(),
+            self.removed_assets.read().count(),
+            self.removed_cameras.read().count(),
+            self.removed_post_processes.read().count(),
+            self.removed_alphas.read().count(),
+            self.removed_tints.read().count(),
+            self.removed_themes.read().count(),
+            self.asset_events.read().count(),
+            self.font_events.read().count(),
+            self.theme_events.read().count(),
+        ]
+        .iter()
+        .any(|count| *count > 0)
+    }
+}
+
+/// Detect whether anything vello draws changed since the last frame.
+/// Placeholders of loading assets, provided fragments and post-processes may
+/// animate, so they always count as changes. Failed loads are settled.
+#[allow(clippy::too_many_arguments)]
+pub fn detect_content_changes(
+    mut changed: ResMut<VelloContentChanged>,
+    content: Query<(), (With<CoordinateSpace>, ContentChanges)>,
+    cameras: Query<(), (With<Camera>, CameraChanges)>,
+    handles: Query<&Handle<VelloAsset>>,
+    assets: Res<Assets<VelloAsset>>,
+    asset_server: Res<AssetServer>,
+    mut events: ContentEvents,
+    fragments: Res<VelloSceneFragments>,
+    post_processed: Query<(), With<VelloPostProcess>>,
+    resources: (
+        Res<VelloRenderSettings>,
+        Res<VelloCanvasDepths>,
+        Res<VelloCameraStages>,
+        Res<VelloPalette>,
+        Res<VelloDefaultUnits>,
+        Res<VelloDefaultYAxis>,
+    ),
+) {
+    let (settings, canvas_depths, stages, palette, default_units, default_y_axis) = resources;
+    let resources_changed = settings.is_changed()
+        || canvas_depths.is_changed()
+        || stages.is_changed()
+        || palette.is_changed()
+        || default_units.is_changed()
+        || default_y_axis.is_changed();
+    let loading = handles.iter().any(|handle| {
+        !assets.contains(handle) && asset_server.get_load_state(handle) == Some(LoadState::Loading)
+    });
+    let any = events.any()
+        || resources_changed
+        || !content.is_empty()
+        || !cameras.is_empty()
+        || loading
+        || !fragments.0.is_empty()
+        || !post_processed.is_empty();
+    changed.set_if_neq(VelloContentChanged(any));
+}
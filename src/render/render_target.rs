@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+
+/// Renders an entity's [`VelloAsset`](crate::VelloAsset) into an [`Image`]
+/// instead of the screen, e.g. to use an animated Lottie as a texture on a
+/// 3D material, a UI image or a sprite. The asset is stretched to the image
+/// size, and its [`Playhead`](crate::Playhead), [`Theme`](crate::Theme) and
+/// [`PlaybackAlphaOverride`](crate::PlaybackAlphaOverride) apply.
+///
+/// The image must be usable as a storage texture, as created by
+/// [`VelloRenderTarget::image`]. Vello writes sRGB-encoded colors to it.
+#[derive(Component, Clone, Debug, Default)]
+pub struct VelloRenderTarget(pub Handle<Image>);
+
+impl VelloRenderTarget {
+    /// Create an image of `size` pixels which vello can render into.
+    pub fn image(size: UVec2) -> Image {
+        let size = Extent3d {
+            width: size.x.max(1),
+            height: size.y.max(1),
+            ..default()
+        };
+        let mut image = Image::default();
+        image.texture_descriptor.size = size;
+        image.texture_descriptor.dimension = TextureDimension::D2;
+        image.texture_descriptor.format = TextureFormat::Rgba8Unorm;
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::STORAGE_BINDING;
+        // Fill the image with transparent pixels
+        image.resize(size);
+        image
+    }
+}
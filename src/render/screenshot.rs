@@ -0,0 +1,375 @@
+use super::bake::{complete, BakeSlot, VelloRasterTask, VelloRasterizer};
+use super::extract::SSRenderTarget;
+use super::image_renderer::{image_from_pixels, read_rows, ImageRenderError};
+use crate::theme::{Theme, VelloPalette};
+use crate::{PlaybackAlphaOverride, PlaybackColorOverride, Playhead, VectorFile, VelloAsset};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+    ImageDataLayout, Maintain, MapMode, TextureFormat,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Cameras whose canvases wait to be copied by the render world, shared by
+/// the main and render worlds.
+#[derive(Resource, Clone, Default)]
+pub struct ScreenshotRequests(Arc<Mutex<Vec<(Entity, BakeSlot)>>>);
+
+/// Copies what vello drew back into an [`Image`], e.g. for share cards and
+/// visual tests.
+///
+/// ```ignore
+/// fn capture(screenshot: VelloScreenshot, camera: Query<Entity, With<Camera>>) {
+///     let task = screenshot.camera(camera.single());
+///     AsyncComputeTaskPool::get()
+///         .spawn(async move {
+///             let image = task.await.unwrap();
+///             VelloScreenshot::save_png(&image, "vello.png").unwrap();
+///         })
+///         .detach();
+/// }
+/// ```
+///
+/// Requires the [`VelloPlugin`](crate::VelloPlugin), which renders the
+/// captures.
+#[derive(SystemParam)]
+pub struct VelloScreenshot<'w, 's> {
+    entities: Query<
+        'w,
+        's,
+        (
+            &'static Handle<VelloAsset>,
+            Option<&'static Playhead>,
+            (Option<&'static Theme>, Option<&'static Handle<Theme>>),
+            Option<&'static PlaybackAlphaOverride>,
+            Option<&'static PlaybackColorOverride>,
+        ),
+    >,
+    assets: Res<'w, Assets<VelloAsset>>,
+    themes: Res<'w, Assets<Theme>>,
+    palette: Res<'w, VelloPalette>,
+    rasterizer: Res<'w, VelloRasterizer>,
+    requests: Res<'w, ScreenshotRequests>,
+}
+
+impl VelloScreenshot<'_, '_> {
+    /// Capture the vello layer of a camera: its canvases composited over each
+    /// other, at the canvas resolution of
+    /// [`VelloRenderSettings::render_scale`](super::VelloRenderSettings::render_scale).
+    ///
+    /// Only vello content is captured, not the rest of the camera's view. The
+    /// canvases are copied after the next frame renders, and read back a
+    /// frame or so later.
+    pub fn camera(&self, camera: Entity) -> VelloRasterTask {
+        let slot = BakeSlot::default();
+        self.requests.0.lock().unwrap().push((camera, slot.clone()));
+        VelloRasterTask(slot)
+    }
+
+    /// Capture a single entity's asset, at its current frame, theme, alpha
+    /// and color override, stretched to `size` pixels.
+    ///
+    /// The asset is drawn alone, without its placement, layers, clipping or
+    /// effects, like [`VelloRasterizer::rasterize`].
+    pub fn entity(&self, entity: Entity, size: UVec2) -> VelloRasterTask {
+        let Some((handle, playhead, (theme, theme_handle), alpha, tint)) =
+            self.entities.get(entity).ok()
+        else {
+            return failed(ImageRenderError::Missing(format!(
+                "{entity:?} has no vello asset"
+            )));
+        };
+        let Some(asset) = self.assets.get(handle) else {
+            return failed(ImageRenderError::Missing(format!(
+                "the asset of {entity:?} isn't loaded"
+            )));
+        };
+        let mut asset = asset.clone();
+        let mut frame = 0.0;
+        if let VectorFile::Lottie { composition, .. } = &mut asset.data {
+            frame = playhead.map_or(composition.frames.start, Playhead::frame);
+            // Entity themes apply first, then the global palette
+            let theme = theme.or_else(|| theme_handle.and_then(|handle| self.themes.get(handle)));
+            if let Some(theme) = theme {
+                *composition = Arc::new(theme.recolor(composition));
+            }
+            if !self.palette.is_empty() {
+                *composition = Arc::new(self.palette.recolor(composition));
+            }
+        }
+        let alpha = alpha.map_or(1.0, |alpha| alpha.0);
+        self.rasterizer
+            .request(asset, frame, size, alpha, tint.copied())
+    }
+
+    /// Save a captured image as a PNG file.
+    pub fn save_png(image: &Image, path: impl AsRef<Path>) -> Result<(), ImageRenderError> {
+        image::save_buffer_with_format(
+            path,
+            &image.data,
+            image.width(),
+            image.height(),
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|err| ImageRenderError::Save(err.to_string()))
+    }
+}
+
+/// A task which already failed.
+fn failed(err: ImageRenderError) -> VelloRasterTask {
+    let slot = BakeSlot::default();
+    complete(&slot, Err(err));
+    VelloRasterTask(slot)
+}
+
+/// A canvas copied into a mappable buffer.
+struct CanvasReadback {
+    buffer: Buffer,
+    format: TextureFormat,
+    mapped: Arc<Mutex<Option<Result<(), String>>>>,
+}
+
+/// The canvases of a camera, bottom first, waiting to be read back.
+struct PendingScreenshot {
+    canvases: Vec<CanvasReadback>,
+    size: UVec2,
+    slot: BakeSlot,
+}
+
+#[derive(Resource, Default)]
+pub struct PendingScreenshots(Vec<PendingScreenshot>);
+
+/// Copy the canvases of requested cameras after they are rendered, and
+/// composite canvases read back earlier into images.
+pub fn capture_canvases(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    canvases: Query<&SSRenderTarget>,
+    gpu_images: Res<RenderAssets<Image>>,
+    requests: Res<ScreenshotRequests>,
+    mut pending: ResMut<PendingScreenshots>,
+) {
+    if !pending.0.is_empty() {
+        device.poll(Maintain::Poll);
+    }
+    pending.0.retain(|shot| {
+        let mut results = Vec::with_capacity(shot.canvases.len());
+        for canvas in shot.canvases.iter() {
+            match canvas.mapped.lock().unwrap().clone() {
+                Some(result) => results.push(result),
+                None => return true,
+            }
+        }
+        let result = match results.into_iter().find_map(Result::err) {
+            Some(err) => Err(ImageRenderError::Readback(err)),
+            None => Ok(composite(&shot.canvases, shot.size)),
+        };
+        complete(&shot.slot, result);
+        false
+    });
+
+    let requests = std::mem::take(&mut *requests.0.lock().unwrap());
+    for (camera, slot) in requests {
+        let mut targets: Vec<_> = canvases
+            .iter()
+            .filter(|target| target.camera == camera)
+            .filter_map(|target| Some((target.canvas, gpu_images.get(&target.image)?)))
+            .collect();
+        targets.sort_by_key(|(canvas, _)| *canvas);
+        let Some((_, first)) = targets.first() else {
+            complete(
+                &slot,
+                Err(ImageRenderError::Missing(format!(
+                    "{camera:?} has no vello canvas"
+                ))),
+            );
+            continue;
+        };
+        let size = first.size.as_uvec2();
+        if size.x == 0 || size.y == 0 {
+            complete(
+                &slot,
+                Err(ImageRenderError::Missing(format!(
+                    "the viewport of {camera:?} is empty"
+                ))),
+            );
+            continue;
+        }
+
+        let extent = Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        };
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("vello_screenshot_copy"),
+        });
+        let mut readbacks = Vec::with_capacity(targets.len());
+        for (_, gpu_image) in targets {
+            let bytes_per_pixel = bytes_per_pixel(gpu_image.texture_format);
+            let padded_bytes_per_row =
+                RenderDevice::align_copy_bytes_per_row(size.x as usize * bytes_per_pixel);
+            let buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("vello_screenshot_readback"),
+                size: (padded_bytes_per_row * size.y as usize) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                gpu_image.texture.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_bytes_per_row as u32),
+                        rows_per_image: None,
+                    },
+                },
+                extent,
+            );
+            readbacks.push(CanvasReadback {
+                buffer,
+                format: gpu_image.texture_format,
+                mapped: Arc::new(Mutex::new(None)),
+            });
+        }
+        queue.submit([encoder.finish()]);
+        for readback in readbacks.iter() {
+            let on_mapped = readback.mapped.clone();
+            device.map_buffer(&readback.buffer.slice(..), MapMode::Read, move |result| {
+                *on_mapped.lock().unwrap() = Some(result.map_err(|err| err.to_string()));
+            });
+        }
+        pending.0.push(PendingScreenshot {
+            canvases: readbacks,
+            size,
+            slot,
+        });
+    }
+}
+
+fn bytes_per_pixel(format: TextureFormat) -> usize {
+    match format {
+        TextureFormat::Rgba16Float => 8,
+        _ => 4,
+    }
+}
+
+/// Blend mapped canvases over each other in linear color, as they are drawn
+/// on screen, into sRGB pixels.
+fn composite(canvases: &[CanvasReadback], size: UVec2) -> Image {
+    // A single 8-bit canvas already holds the sRGB pixels
+    if let [canvas] = canvases {
+        if canvas.format == TextureFormat::Rgba8Unorm {
+            return image_from_pixels(read_rows(&canvas.buffer, size, 4), size);
+        }
+    }
+
+    // Premultiplied linear color
+    let mut layer = vec![Vec4::ZERO; (size.x * size.y) as usize];
+    for canvas in canvases {
+        let bytes = read_rows(&canvas.buffer, size, bytes_per_pixel(canvas.format));
+        for (dst, src) in layer.iter_mut().zip(linear_pixels(&bytes, canvas.format)) {
+            let src = (src.truncate() * src.w).extend(src.w);
+            *dst = src + *dst * (1.0 - src.w);
+        }
+    }
+    let pixels = layer
+        .iter()
+        .flat_map(|color| {
+            let rgb = match color.w > 0.0 {
+                true => color.truncate() / color.w,
+                false => Vec3::ZERO,
+            };
+            let [r, g, b] = rgb.to_array().map(linear_to_srgb);
+            [r, g, b, color.w].map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+        })
+        .collect();
+    image_from_pixels(pixels, size)
+}
+
+/// Non-premultiplied linear colors of a canvas. 8-bit canvases hold sRGB
+/// encoded colors, and float canvases linear ones.
+fn linear_pixels(bytes: &[u8], format: TextureFormat) -> Vec<Vec4> {
+    match format {
+        TextureFormat::Rgba16Float => bytes
+            .chunks_exact(8)
+            .map(|pixel| {
+                let channel = |i: usize| f16_to_f32(u16::from_le_bytes([pixel[i], pixel[i + 1]]));
+                Vec4::new(channel(0), channel(2), channel(4), channel(6))
+            })
+            .collect(),
+        _ => bytes
+            .chunks_exact(4)
+            .map(|pixel| {
+                let channel = |i: usize| pixel[i] as f32 / 255.0;
+                Vec4::new(
+                    srgb_to_linear(channel(0)),
+                    srgb_to_linear(channel(1)),
+                    srgb_to_linear(channel(2)),
+                    channel(3),
+                )
+            })
+            .collect(),
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn srgb_to_linear(channel: f32) -> f32 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(channel: f32) -> f32 {
+    if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_normal_half_floats() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3800), 0.5);
+        assert_eq!(f16_to_f32(0x7bff), 65504.0);
+    }
+
+    #[test]
+    fn decodes_subnormal_half_floats() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x03ff), 1023.0 * 2f32.powi(-24));
+    }
+
+    #[test]
+    fn decodes_infinities_and_nan() {
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+    }
+}
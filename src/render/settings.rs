@@ -0,0 +1,283 @@
+use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResource;
+use bevy::render::render_resource::TextureFormat;
+use bevy::render::renderer::RenderDevice;
+use bevy::render::settings::{WgpuFeatures, WgpuLimits};
+use bevy::utils::thiserror::{self, Error};
+use bevy::utils::HashMap;
+use std::num::NonZeroUsize;
+use vello::{AaConfig, AaSupport};
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum VelloRendererError {
+    #[error("The render device is missing required features: {0:?}")]
+    MissingFeatures(WgpuFeatures),
+    #[error("The render device doesn't satisfy required limits: {0}")]
+    Limits(String),
+    #[error("Could not create renderer: {0}")]
+    Renderer(String),
+}
+
+/// Options for building the vello renderer, read once at startup. Insert
+/// this before adding the plugin.
+///
+/// If the render device doesn't satisfy them, a [`VelloRendererError`] is
+/// logged and vello content is rendered on the CPU with the `cpu_fallback`
+/// feature, or not at all. Bevy's `WgpuSettings` decide what the device is created
+/// with, so request the same features and limits there.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct VelloRendererOptions {
+    /// The number of threads used to compile vello's shaders at startup.
+    /// `None` uses vello's default, and 1 compiles on the calling thread.
+    pub num_init_threads: Option<NonZeroUsize>,
+    /// Run vello's pipeline stages on the CPU, for debugging.
+    pub use_cpu: bool,
+    /// Features the render device must have.
+    pub required_features: WgpuFeatures,
+    /// Limits the render device must satisfy, e.g. larger buffers for dense
+    /// scenes.
+    pub required_limits: Option<WgpuLimits>,
+}
+
+impl VelloRendererOptions {
+    /// Check that the render device satisfies the required features and
+    /// limits.
+    pub fn check(&self, device: &RenderDevice) -> Result<(), VelloRendererError> {
+        let missing = self.required_features - device.features();
+        if !missing.is_empty() {
+            return Err(VelloRendererError::MissingFeatures(missing));
+        }
+        if let Some(required) = &self.required_limits {
+            let mut failures = vec![];
+            required.check_limits_with_fail_fn(
+                &device.limits(),
+                false,
+                |name, required, allowed| {
+                    failures.push(format!(
+                        "{name} needs {required}, but the device allows {allowed}"
+                    ));
+                },
+            );
+            if !failures.is_empty() {
+                return Err(VelloRendererError::Limits(failures.join(", ")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings for rendering vello content.
+///
+/// Insert this before adding the plugin to choose what the renderer is built
+/// with, or change it at runtime. Switching to an antialiasing method the
+/// renderer wasn't built with rebuilds it, which compiles shaders and may
+/// hitch, so prefer choosing up front.
+#[derive(Resource, ExtractResource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct VelloRenderSettings {
+    /// The antialiasing method used for canvases and render targets.
+    pub antialiasing: VelloAntialiasing,
+    /// Where canvases are drawn in each camera's render graph.
+    pub canvas_stage: VelloCanvasStage,
+    /// The resolution of canvases relative to their viewport. Above 1.0
+    /// supersamples for smoother curves on low-DPI displays, and below 1.0
+    /// trades sharpness for speed on high-DPI ones. Canvases are resampled
+    /// to the viewport when drawn.
+    pub render_scale: f32,
+    /// The texture format of canvases. Changing it recreates them.
+    pub canvas_format: VelloCanvasFormat,
+    /// Whether canvases are redrawn every frame, or only when their content
+    /// changes.
+    pub redraw: VelloRedraw,
+}
+
+impl Default for VelloRenderSettings {
+    fn default() -> Self {
+        Self {
+            antialiasing: default(),
+            canvas_stage: default(),
+            render_scale: 1.0,
+            canvas_format: default(),
+            redraw: default(),
+        }
+    }
+}
+
+impl VelloRenderSettings {
+    /// The size of a canvas for a viewport of `viewport` physical pixels.
+    pub(crate) fn canvas_size(&self, viewport: UVec2) -> UVec2 {
+        (viewport.as_vec2() * self.render_scale.max(0.0))
+            .ceil()
+            .as_uvec2()
+    }
+
+    /// The texture format of canvases. The CPU fallback uploads 8-bit
+    /// pixels, so it always uses [`VelloCanvasFormat::Rgba8Srgb`].
+    pub(crate) fn canvas_texture_format(&self, cpu_fallback: bool) -> TextureFormat {
+        match self.canvas_format {
+            _ if cpu_fallback => TextureFormat::Rgba8Unorm,
+            VelloCanvasFormat::Rgba8Srgb => TextureFormat::Rgba8Unorm,
+            VelloCanvasFormat::Rgba16Float => TextureFormat::Rgba16Float,
+        }
+    }
+}
+
+/// The texture format of vello canvases.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum VelloCanvasFormat {
+    /// 8-bit sRGB encoded color in an `Rgba8Unorm` texture, which is what
+    /// vello writes. Decoded when the canvas is drawn.
+    #[default]
+    Rgba8Srgb,
+    /// Linear color in an `Rgba16Float` texture, matching HDR view targets,
+    /// so canvases are stored and sampled the way HDR pipelines blend them.
+    ///
+    /// Vello only writes 8-bit color, so it renders into an 8-bit texture
+    /// which an extra pass per canvas decodes into this one. This doesn't add
+    /// precision to what vello draws, and takes twice the memory.
+    Rgba16Float,
+}
+
+/// When vello canvases are redrawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum VelloRedraw {
+    /// Every frame.
+    Always,
+    /// Only on frames where something vello draws changed, e.g. a transform,
+    /// playhead, theme, asset or camera, which saves power on idle screens.
+    /// Loading placeholders, [`VelloCanvas`](crate::VelloCanvas) content and
+    /// [`VelloSceneProvider`](crate::VelloSceneProvider)s redraw every
+    /// frame.
+    ///
+    /// Removing a styling component such as `VelloClip` from an entity isn't
+    /// detected. Mark this resource changed to force a redraw, or use
+    /// [`VelloRedraw::Always`] if content changes in ways that aren't
+    /// tracked.
+    #[default]
+    OnChange,
+}
+
+/// Where vello canvases are drawn, relative to post-processing and
+/// `bevy_ui`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum VelloCanvasStage {
+    /// In the main pass, before post-processing. Canvases sort with other 2D
+    /// content by [`VelloCanvasDepths`](crate::VelloCanvasDepths), and
+    /// `bevy_ui` draws over them. 3D cameras have no 2D main pass, so they
+    /// draw canvases as for [`VelloCanvasStage::BeforeUi`].
+    #[default]
+    MainPass,
+    /// After post-processing such as tonemapping and bloom, with `bevy_ui`
+    /// drawn over them. [`VelloEmissive`](crate::VelloEmissive) has no
+    /// effect, and canvases draw over all other 2D content.
+    BeforeUi,
+    /// After `bevy_ui`, so vector content draws over the UI.
+    /// [`VelloEmissive`](crate::VelloEmissive) has no effect.
+    AfterUi,
+}
+
+/// Where each camera's canvases are drawn, overriding
+/// [`VelloRenderSettings::canvas_stage`]. Change it at runtime, e.g. to draw
+/// world space content before `bevy_ui` so a modal dialog covers it, while
+/// screen space HUD content draws over UI panels.
+///
+/// A camera with different stages for world and screen space content draws
+/// screen space content into a canvas of its own, an extra vello render.
+#[derive(Resource, ExtractResource, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct VelloCameraStages(pub HashMap<Entity, VelloCameraStage>);
+
+impl VelloCameraStages {
+    /// Whether a camera draws screen space content into a canvas of its own.
+    pub(crate) fn is_split(&self, camera: Entity) -> bool {
+        self.0
+            .get(&camera)
+            .is_some_and(|stage| stage.world != stage.screen)
+    }
+
+    /// The stage of a camera's world space or screen space canvases.
+    pub(crate) fn stage(
+        &self,
+        settings: &VelloRenderSettings,
+        camera: Entity,
+        screen: bool,
+    ) -> VelloCanvasStage {
+        match self.0.get(&camera) {
+            Some(stage) if screen => stage.screen,
+            Some(stage) => stage.world,
+            None => settings.canvas_stage,
+        }
+    }
+}
+
+/// Where a camera's canvases are drawn, see [`VelloCameraStages`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct VelloCameraStage {
+    /// The stage of world space content.
+    pub world: VelloCanvasStage,
+    /// The stage of screen space content and billboards.
+    pub screen: VelloCanvasStage,
+}
+
+impl VelloCameraStage {
+    /// Draw all of a camera's content at one stage.
+    pub fn all(stage: VelloCanvasStage) -> Self {
+        Self {
+            world: stage,
+            screen: stage,
+        }
+    }
+}
+
+/// An antialiasing method for vello.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum VelloAntialiasing {
+    /// Analytic area antialiasing. Fast, but may show conflation artifacts
+    /// where shapes share an edge.
+    #[default]
+    Area,
+    /// 8x multisampling.
+    Msaa8,
+    /// 16x multisampling. The highest quality, and the most expensive.
+    Msaa16,
+}
+
+impl VelloAntialiasing {
+    pub(crate) fn config(self) -> AaConfig {
+        match self {
+            VelloAntialiasing::Area => AaConfig::Area,
+            VelloAntialiasing::Msaa8 => AaConfig::Msaa8,
+            VelloAntialiasing::Msaa16 => AaConfig::Msaa16,
+        }
+    }
+
+    /// Whether a renderer built with `support` can use this method.
+    pub(crate) fn is_supported(self, support: &AaSupport) -> bool {
+        match self {
+            VelloAntialiasing::Area => support.area,
+            VelloAntialiasing::Msaa8 => support.msaa8,
+            VelloAntialiasing::Msaa16 => support.msaa16,
+        }
+    }
+
+    /// Support for only this method.
+    pub(crate) fn support(self) -> AaSupport {
+        let mut support = AaSupport {
+            area: false,
+            msaa8: false,
+            msaa16: false,
+        };
+        self.add_support(&mut support);
+        support
+    }
+
+    /// Add this method to `support`.
+    pub(crate) fn add_support(self, support: &mut AaSupport) {
+        match self {
+            VelloAntialiasing::Area => support.area = true,
+            VelloAntialiasing::Msaa8 => support.msaa8 = true,
+            VelloAntialiasing::Msaa16 => support.msaa16 = true,
+        }
+    }
+}
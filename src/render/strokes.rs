@@ -0,0 +1,133 @@
+use crate::assets::encode_tree;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex, Weak};
+use velato::model::Shape;
+use velato::Composition;
+use vello::kurbo::Affine;
+use vello::Scene;
+use vello_svg::usvg;
+
+/// The most SVG scenes kept re-encoded at other stroke widths. The cache is
+/// cleared when full, as widths change with every zoom.
+const MAX_STROKE_SCENES: usize = 64;
+
+/// SVG scenes re-encoded with scaled strokes, by tree and scale.
+#[allow(clippy::type_complexity)]
+static STROKE_SCENES: Lazy<Mutex<HashMap<(usize, u64), (Weak<usvg::Tree>, Arc<Scene>)>>> =
+    Lazy::new(Default::default);
+
+/// Whether an asset's strokes scale with its entity, e.g. to keep outlines
+/// crisp on skewed and stretched isometric maps.
+#[derive(PartialEq, Eq, Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub enum VelloStrokeScaling {
+    /// Strokes scale with the entity and camera, like the rest of the asset.
+    #[default]
+    Scale,
+    /// Strokes keep the width, in logical pixels, they have at the asset's
+    /// own size, however the entity and camera scale it. Scaling inside the
+    /// asset itself still applies.
+    NonScaling,
+}
+
+/// The factor to scale stroke widths by, so strokes drawn with `transform`
+/// keep their width on a canvas of `pixel_scale`, or `None` for a degenerate
+/// transform.
+pub(crate) fn stroke_scale(transform: Affine, pixel_scale: f32) -> Option<f64> {
+    let scale = transform.determinant().abs().sqrt();
+    (scale > f64::EPSILON).then(|| pixel_scale as f64 / scale)
+}
+
+/// An SVG encoded with its stroke widths scaled by `stroke_scale`.
+pub(crate) fn svg_with_stroke_scale(tree: &Arc<usvg::Tree>, stroke_scale: f64) -> Arc<Scene> {
+    let key = (Arc::as_ptr(tree) as usize, stroke_scale.to_bits());
+    let mut scenes = STROKE_SCENES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached, scene)) = scenes.get(&key) {
+        if cached.strong_count() > 0 {
+            return scene.clone();
+        }
+    }
+    if scenes.len() >= MAX_STROKE_SCENES {
+        scenes.clear();
+    }
+    let mut scene = Scene::new();
+    encode_tree(&mut scene, tree, stroke_scale);
+    let scene = Arc::new(scene);
+    scenes.insert(key, (Arc::downgrade(tree), scene.clone()));
+    scene
+}
+
+/// A copy of a composition with its stroke widths scaled by `factor`.
+pub(crate) fn scale_lottie_strokes(composition: &Composition, factor: f64) -> Composition {
+    let mut composition = composition.clone();
+    for layer in composition
+        .layers
+        .iter_mut()
+        .chain(composition.assets.values_mut().flatten())
+    {
+        if let velato::model::Content::Shape(shapes) = &mut layer.content {
+            for shape in shapes.iter_mut() {
+                scale_shape_strokes(shape, factor);
+            }
+        }
+    }
+    composition
+}
+
+fn scale_shape_strokes(shape: &mut Shape, factor: f64) {
+    match shape {
+        velato::model::Shape::Group(shapes, _) => {
+            for shape in shapes.iter_mut() {
+                scale_shape_strokes(shape, factor);
+            }
+        }
+        velato::model::Shape::Draw(draw) => match &mut draw.stroke {
+            Some(velato::model::Stroke::Fixed(stroke)) => stroke.width *= factor,
+            Some(velato::model::Stroke::Animated(stroke)) => match &mut stroke.width {
+                velato::model::Value::Fixed(width) => *width *= factor,
+                velato::model::Value::Animated(keyframes) => {
+                    for width in keyframes.values.iter_mut() {
+                        *width *= factor;
+                    }
+                }
+            },
+            None => {}
+        },
+        velato::model::Shape::Repeater(_) | velato::model::Shape::Geometry(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strokes_are_scaled_against_the_transform() {
+        assert_eq!(stroke_scale(Affine::scale(4.0), 2.0), Some(0.5));
+        // A stretch keeps the area's scale
+        assert_eq!(
+            stroke_scale(Affine::scale_non_uniform(8.0, 2.0), 1.0),
+            Some(0.25)
+        );
+        assert_eq!(stroke_scale(Affine::scale(0.0), 1.0), None);
+    }
+
+    #[test]
+    fn scaled_svgs_are_shared() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10" stroke="black" stroke-width="2"/>
+        </svg>"#;
+        let tree = usvg::Tree::from_str(
+            svg,
+            &usvg::Options::default(),
+            &usvg::fontdb::Database::new(),
+        )
+        .unwrap();
+        let tree = Arc::new(tree);
+        let first = svg_with_stroke_scale(&tree, 0.5);
+        assert!(Arc::ptr_eq(&first, &svg_with_stroke_scale(&tree, 0.5)));
+        assert!(!Arc::ptr_eq(&first, &svg_with_stroke_scale(&tree, 2.0)));
+    }
+}
@@ -1,25 +1,60 @@
 use crate::render::extract::ExtractedRenderScene;
-use crate::{CoordinateSpace, VectorFile, VelloCanvasMaterial, VelloFont};
+use crate::theme::VelloPalette;
+use crate::{
+    ColorOverrideMode, CoordinateSpace, PlaybackColorOverride, ToPeniko, VectorFile,
+    VelloCanvasDepths, VelloCanvasMaterial, VelloFont, VelloZIndex,
+};
 use bevy::prelude::*;
 use bevy::render::mesh::Indices;
 use bevy::render::render_asset::{RenderAssetUsages, RenderAssets};
 use bevy::render::render_resource::{
-    Extent3d, PrimitiveTopology, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    Extent3d, PipelineCache, PrimitiveTopology, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages,
 };
 use bevy::render::renderer::{RenderDevice, RenderQueue};
-use bevy::render::view::NoFrustumCulling;
+use bevy::render::view::{ExtractedView, NoFrustumCulling, RenderLayers, VisibleEntities};
 use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
-use bevy::window::{WindowResized, WindowResolution};
+use bevy::utils::{HashMap, HashSet};
+use std::sync::Arc;
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::{BlendMode, Compose, Fill, Mix};
 use vello::{RenderParams, Scene};
 
-use super::extract::{ExtractedRenderAsset, ExtractedRenderText, SSRenderTarget};
-use super::prepare::PreparedAffine;
-use super::{BevyVelloRenderer, LottieRenderer};
+use super::composite::{VelloCanvasScratch, VelloCompositePipeline};
+use super::effects::VelloShadows;
+use super::extract::{
+    ExtractedRenderAsset, ExtractedRenderTarget, ExtractedRenderText, ExtractedVelloView,
+    SSRenderTarget,
+};
+use super::image_renderer::render_asset;
+use super::lottie::render_lottie;
+use super::prepare::PreparedAffines;
+use super::provider::VelloSceneFragments;
+use super::raster::render_raster;
+use super::redraw::VelloContentChanged;
+use super::strokes::{
+    scale_lottie_strokes, stroke_scale, svg_with_stroke_scale, VelloStrokeScaling,
+};
+use super::view::{vello_views, VelloEmissive, VelloView};
+use super::{
+    BevyVelloRenderer, LottieRenderer, VelloCameraStages, VelloCanvas, VelloCanvasStage,
+    VelloCpuFallback, VelloRedraw, VelloRenderSettings, VelloRendererOptions,
+};
 
-pub fn setup_image(images: &mut Assets<Image>, window: &WindowResolution) -> Handle<Image> {
+/// Create a canvas image. Vello renders into 8-bit canvases as a storage
+/// texture, and the CPU fallback copies into them. Float canvases are drawn
+/// to by the pass decoding vello's output. Canvases can be copied from, for
+/// [`VelloScreenshot`](super::VelloScreenshot), and drawn to by a
+/// [`VelloPostProcess`](super::VelloPostProcess).
+pub fn setup_image(
+    images: &mut Assets<Image>,
+    size: UVec2,
+    format: TextureFormat,
+    cpu_fallback: bool,
+) -> Handle<Image> {
     let size = Extent3d {
-        width: window.physical_width(),
-        height: window.physical_height(),
+        width: size.x,
+        height: size.y,
         ..default()
     };
 
@@ -29,116 +64,407 @@ pub fn setup_image(images: &mut Assets<Image>, window: &WindowResolution) -> Han
             label: None,
             size,
             dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
+            format,
             mip_level_count: 1,
             sample_count: 1,
             usage: TextureUsages::TEXTURE_BINDING
                 | TextureUsages::COPY_DST
-                | TextureUsages::STORAGE_BINDING,
+                | TextureUsages::COPY_SRC
+                | TextureUsages::RENDER_ATTACHMENT,
             view_formats: &[],
         },
         ..default()
     };
+    if format == TextureFormat::Rgba8Unorm && !cpu_fallback {
+        image.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING;
+    }
 
     // fill image.data with zeroes
     image.resize(size);
     images.add(image)
 }
 
+/// Rebuild the renderer when the antialiasing method in
+/// [`VelloRenderSettings`] changes to one it wasn't built with, keeping the
+/// methods it already supports.
+pub fn prepare_renderer(
+    settings: Res<VelloRenderSettings>,
+    options: Res<VelloRendererOptions>,
+    device: Res<RenderDevice>,
+    vello_renderer: Option<NonSendMut<BevyVelloRenderer>>,
+) {
+    let Some(mut renderer) = vello_renderer else {
+        return;
+    };
+    if settings.antialiasing.is_supported(&renderer.1) {
+        return;
+    }
+    let supported = &renderer.1;
+    let mut antialiasing_support = vello::AaSupport {
+        area: supported.area,
+        msaa8: supported.msaa8,
+        msaa16: supported.msaa16,
+    };
+    settings.antialiasing.add_support(&mut antialiasing_support);
+    match BevyVelloRenderer::new(&device, &options, antialiasing_support) {
+        Ok(rebuilt) => *renderer = rebuilt,
+        Err(err) => error!(
+            "failed to rebuild vello renderer for {:?}: {err}",
+            settings.antialiasing
+        ),
+    }
+}
+
 /// Transforms all the vectors extracted from the game world and places them in
 /// a scene, and renders the scene to a texture with WGPU
 #[allow(clippy::complexity)]
 pub fn render_scene(
     ss_render_target: Query<&SSRenderTarget>,
-    render_vectors: Query<(&PreparedAffine, &ExtractedRenderAsset)>,
-    query_render_texts: Query<(&PreparedAffine, &ExtractedRenderText)>,
-    query_render_scenes: Query<(&PreparedAffine, &ExtractedRenderScene)>,
+    render_vectors: Query<(&PreparedAffines, &ExtractedRenderAsset)>,
+    query_render_texts: Query<(&PreparedAffines, &ExtractedRenderText)>,
+    query_render_scenes: Query<(&PreparedAffines, &ExtractedRenderScene)>,
     mut font_render_assets: ResMut<RenderAssets<VelloFont>>,
     gpu_images: Res<RenderAssets<Image>>,
     device: Res<RenderDevice>,
     queue: Res<RenderQueue>,
-    vello_renderer: Option<NonSendMut<BevyVelloRenderer>>,
+    mut vello_renderer: Option<NonSendMut<BevyVelloRenderer>>,
     mut velottie_renderer: ResMut<LottieRenderer>,
+    palette: Res<VelloPalette>,
+    views: Query<(&ExtractedVelloView, &ExtractedView)>,
+    (canvas_depths, stages, mut custom): (
+        Res<VelloCanvasDepths>,
+        Res<VelloCameraStages>,
+        ResMut<VelloCanvas>,
+    ),
+    (pipeline_cache, composite_pipeline, mut scratch): (
+        Res<PipelineCache>,
+        Res<VelloCompositePipeline>,
+        ResMut<VelloCanvasScratch>,
+    ),
+    (settings, content_changed, mut drawn, mut shadows): (
+        Res<VelloRenderSettings>,
+        Res<VelloContentChanged>,
+        Local<HashSet<AssetId<Image>>>,
+        ResMut<VelloShadows>,
+    ),
+    cpu_fallback: Option<Res<VelloCpuFallback>>,
 ) {
-    let mut renderer = if let Some(renderer) = vello_renderer {
-        renderer
-    } else {
+    // Without a vello renderer, content is only drawn by the CPU fallback
+    if vello_renderer.is_none() && cpu_fallback.is_none() {
         return;
-    };
+    }
+
+    // Canvases keep what was drawn into them, so unchanged ones are skipped,
+    // unless a drop shadow was just rendered
+    let shadows_finished = shadows.poll();
+    let unchanged =
+        settings.redraw == VelloRedraw::OnChange && !content_changed.0 && !shadows_finished;
+    let mut still_drawn = HashSet::new();
+    let mut skipped_any = false;
 
-    if let Ok(SSRenderTarget(render_target_image)) = ss_render_target.get_single() {
-        let gpu_image = gpu_images.get(render_target_image).unwrap();
+    // Each canvas draws the content at its depths, for its camera
+    for SSRenderTarget {
+        image: render_target_image,
+        canvas,
+        camera,
+    } in ss_render_target.iter()
+    {
+        // Collapsed viewports have nothing to draw
+        let Some(gpu_image) = gpu_images
+            .get(render_target_image)
+            .filter(|gpu_image| gpu_image.size.x > 0.0 && gpu_image.size.y > 0.0)
+        else {
+            continue;
+        };
+        let custom_content = custom.0.contains_key(camera);
+        if unchanged && !custom_content && drawn.contains(&render_target_image.id()) {
+            still_drawn.insert(render_target_image.id());
+            skipped_any = true;
+            continue;
+        }
+        let Ok((view, extracted_view)) = views.get(*camera) else {
+            continue;
+        };
+        let camera_position = extracted_view.transform.translation();
+        // Affines are in viewport pixels, and canvases may be scaled from it
+        // by the render scale
+        let viewport = extracted_view.viewport;
+        let viewport = Vec2::new(viewport.z as f32, viewport.w as f32).max(Vec2::ONE);
+        let canvas_scale = Vec2::new(gpu_image.size.x, gpu_image.size.y) / viewport;
+        let to_canvas = Affine::scale_non_uniform(canvas_scale.x as f64, canvas_scale.y as f64);
+        let split = stages.is_split(*camera);
         let mut scene = Scene::new();
+        let placed = |affines: &PreparedAffines,
+                      render_mode: CoordinateSpace,
+                      transform: &GlobalTransform| {
+            let z = transform.translation().z;
+            if canvas_depths.canvas(render_mode, z, split) != *canvas {
+                return None;
+            }
+            affines.0.get(camera).copied()
+        };
+        // Billboards are sorted by distance instead, far ones first
+        let depth =
+            |render_mode: CoordinateSpace, transform: &GlobalTransform, z: f32| match render_mode {
+                CoordinateSpace::Billboard3d => -camera_position.distance(transform.translation()),
+                _ => z,
+            };
 
         enum RenderItem<'a> {
             Asset(&'a ExtractedRenderAsset),
             Scene(&'a ExtractedRenderScene),
             Text(&'a ExtractedRenderText),
         }
-        let mut render_queue: Vec<(f32, CoordinateSpace, (&PreparedAffine, RenderItem))> =
-            render_vectors
-                .iter()
-                .map(|(a, b)| (b.z_index, b.render_mode, (a, RenderItem::Asset(b))))
-                .collect();
-        render_queue.extend(query_render_texts.iter().map(|(a, b)| {
-            (
-                b.transform.translation().z,
-                b.render_mode,
-                (a, RenderItem::Text(b)),
-            )
+        type SortKey = (CoordinateSpace, VelloZIndex, f32, Entity);
+        let mut render_queue: Vec<(SortKey, (Affine, RenderItem))> = render_vectors
+            .iter()
+            .filter_map(|(a, b)| {
+                let affine = placed(a, b.render_mode, &b.transform)?;
+                Some((
+                    (
+                        b.render_mode,
+                        b.z_layer,
+                        depth(b.render_mode, &b.transform, b.z_index),
+                        b.entity,
+                    ),
+                    (affine, RenderItem::Asset(b)),
+                ))
+            })
+            .collect();
+        render_queue.extend(query_render_texts.iter().filter_map(|(a, b)| {
+            let affine = placed(a, b.render_mode, &b.transform)?;
+            Some((
+                (
+                    b.render_mode,
+                    b.z_layer,
+                    depth(b.render_mode, &b.transform, b.transform.translation().z),
+                    b.entity,
+                ),
+                (affine, RenderItem::Text(b)),
+            ))
         }));
-        render_queue.extend(query_render_scenes.iter().map(|(a, b)| {
-            (
-                b.transform.translation().z,
-                b.render_mode,
-                (a, RenderItem::Scene(b)),
-            )
+        render_queue.extend(query_render_scenes.iter().filter_map(|(a, b)| {
+            let affine = placed(a, b.render_mode, &b.transform)?;
+            Some((
+                (
+                    b.render_mode,
+                    b.z_layer,
+                    depth(b.render_mode, &b.transform, b.transform.translation().z),
+                    b.entity,
+                ),
+                (affine, RenderItem::Scene(b)),
+            ))
         }));
 
-        // Sort by render mode with screen space on top, then by z-index
+        // Sort by render mode with screen space on top, then by explicit
+        // layer, then by z-index, then by entity so ties are stable. The sort
+        // is stable, so the layers of one asset keep their spawn order.
         render_queue.sort_by(
-            |(a_z_index, a_render_mode, _), (b_z_index, b_render_mode, _)| {
+            |((a_render_mode, a_layer, a_z_index, a_entity), _),
+             ((b_render_mode, b_layer, b_z_index, b_entity), _)| {
                 let z_index = a_z_index
                     .partial_cmp(b_z_index)
                     .unwrap_or(std::cmp::Ordering::Equal);
-                let render_mode = a_render_mode.cmp(b_render_mode);
 
-                render_mode.then(z_index)
+                a_render_mode
+                    .cmp(b_render_mode)
+                    .then(a_layer.cmp(b_layer))
+                    .then(z_index)
+                    .then(a_entity.cmp(b_entity))
             },
         );
 
         // Apply transforms to the respective fragments and add them to the
         // scene to be rendered
-        for (_, _, (&PreparedAffine(affine), render_item)) in render_queue.iter_mut() {
+        let mut bucket_encodings: HashMap<(usize, u64, u32, u64), Scene> = HashMap::default();
+        let canvas_rect = Rect::new(0.0, 0.0, gpu_image.size.x as f64, gpu_image.size.y as f64);
+        for (_, (&mut affine, render_item)) in render_queue.iter_mut() {
+            let affine = to_canvas * affine;
+            let (clip, blend_mode) = match *render_item {
+                RenderItem::Asset(a) => (&a.clip, a.blend_mode),
+                RenderItem::Scene(s) => (&s.clip, s.blend_mode),
+                RenderItem::Text(t) => (&t.clip, t.blend_mode),
+            };
+            // Blended with what is already on the canvas when popped
+            if let Some(blend_mode) = blend_mode {
+                scene.push_layer(blend_mode, 1.0, Affine::IDENTITY, &canvas_rect);
+            }
+            if let Some(clip) = clip {
+                clip.push_layer(&mut scene, affine);
+            }
             match render_item {
                 RenderItem::Asset(ExtractedRenderAsset {
                     asset,
                     theme,
                     alpha,
                     playhead,
+                    bucketed,
+                    ui_clip,
+                    effects,
+                    tint,
+                    nine_slice,
+                    tiling,
+                    strokes,
                     ..
-                }) => match &asset.data {
-                    VectorFile::Svg { scene: svg, .. } => {
-                        scene.append(svg, Some(affine));
+                }) => {
+                    if let Some(clip) = ui_clip {
+                        let clip = Rect::new(
+                            clip.min.x as f64,
+                            clip.min.y as f64,
+                            clip.max.x as f64,
+                            clip.max.y as f64,
+                        );
+                        let to_pixels = to_canvas * Affine::scale(view.pixel_scale as f64);
+                        scene.push_layer(Mix::Clip, 1.0, to_pixels, &clip);
                     }
-                    VectorFile::Lottie { composition } => {
-                        debug!("playhead: {playhead}");
-
-                        velottie_renderer.0.render(
-                            {
-                                theme
-                                    .as_ref()
-                                    .map(|cs| cs.recolor(composition))
-                                    .as_ref()
-                                    .unwrap_or(composition)
-                            },
-                            *playhead as f64,
-                            affine,
-                            *alpha as f64,
+                    let content_size = Vec2::new(asset.width, asset.height) / asset.baked_scale();
+                    if let Some(shadow) = effects.and_then(|effects| effects.drop_shadow) {
+                        // The shadow follows the stretched or tiled bounds
+                        let size = match (nine_slice, tiling) {
+                            (Some(nine_slice), _) => nine_slice.stretched_size(),
+                            (None, Some(tiling)) => tiling.size,
+                            (None, None) => content_size,
+                        };
+                        let origin = (content_size - size) / 2.0;
+                        let to_origin = Affine::translate((origin.x as f64, origin.y as f64));
+                        let scale = affine.determinant().abs().sqrt() as f32;
+                        shadows.render(
+                            &shadow,
+                            asset,
+                            *playhead,
                             &mut scene,
+                            affine * to_origin,
+                            size,
+                            scale,
                         );
                     }
-                },
+                    if tint.is_some() {
+                        push_tint_layer(&mut scene, &canvas_rect);
+                    }
+                    // Non-scaling strokes are widened or narrowed while
+                    // encoding, against the scale they're drawn at
+                    let stroke_scale = match strokes {
+                        VelloStrokeScaling::Scale => None,
+                        VelloStrokeScaling::NonScaling => {
+                            stroke_scale(affine, view.pixel_scale * canvas_scale.max_element())
+                        }
+                    };
+                    // Nine-sliced and tiled assets are drawn untransformed,
+                    // then sliced or repeated. Nine slices win over tiling.
+                    let mut unsliced = (nine_slice.is_some() || tiling.is_some()).then(Scene::new);
+                    let (target, content_affine) = match unsliced.as_mut() {
+                        Some(unsliced) => (unsliced, Affine::IDENTITY),
+                        None => (&mut scene, affine),
+                    };
+                    match &asset.data {
+                        VectorFile::Svg { scene: svg, tree } => match stroke_scale {
+                            Some(stroke_scale) => target.append(
+                                &svg_with_stroke_scale(tree, stroke_scale),
+                                Some(content_affine),
+                            ),
+                            None => target.append(svg, Some(content_affine)),
+                        },
+                        VectorFile::Image { image } => {
+                            render_raster(image, content_affine, *alpha as f64, target);
+                        }
+                        VectorFile::Lottie {
+                            composition,
+                            images,
+                            texts,
+                            ..
+                        } if *bucketed => {
+                            // Share one encoding per composition and frame
+                            let key = (
+                                Arc::as_ptr(composition) as usize,
+                                playhead.to_bits(),
+                                alpha.to_bits(),
+                                stroke_scale.map_or(0, f64::to_bits),
+                            );
+                            let fragment = bucket_encodings.entry(key).or_insert_with(|| {
+                                let mut fragment = Scene::new();
+                                render_lottie(
+                                    &mut velottie_renderer.0,
+                                    composition,
+                                    images,
+                                    texts,
+                                    *playhead,
+                                    Affine::IDENTITY,
+                                    *alpha as f64,
+                                    &mut fragment,
+                                    |layers| {
+                                        let recolored =
+                                            (!palette.is_empty()).then(|| palette.recolor(layers));
+                                        stroke_scale
+                                            .map(|factor| {
+                                                let recolored = recolored.as_ref();
+                                                scale_lottie_strokes(
+                                                    recolored.unwrap_or(layers),
+                                                    factor,
+                                                )
+                                            })
+                                            .or(recolored)
+                                    },
+                                );
+                                fragment
+                            });
+                            target.append(fragment, Some(content_affine));
+                        }
+                        VectorFile::Lottie {
+                            composition,
+                            images,
+                            texts,
+                            ..
+                        } => {
+                            debug!("playhead: {playhead}");
+
+                            render_lottie(
+                                &mut velottie_renderer.0,
+                                composition,
+                                images,
+                                texts,
+                                *playhead as f64,
+                                content_affine,
+                                *alpha as f64,
+                                target,
+                                |layers| {
+                                    // Entity themes apply first, then the
+                                    // global palette
+                                    let themed = theme.as_ref().map(|cs| cs.recolor(layers));
+                                    let recolored = match palette.is_empty() {
+                                        true => themed,
+                                        false => {
+                                            Some(palette.recolor(themed.as_ref().unwrap_or(layers)))
+                                        }
+                                    };
+                                    stroke_scale
+                                        .map(|factor| {
+                                            let recolored = recolored.as_ref();
+                                            scale_lottie_strokes(
+                                                recolored.unwrap_or(layers),
+                                                factor,
+                                            )
+                                        })
+                                        .or(recolored)
+                                },
+                            );
+                        }
+                    }
+                    match (nine_slice, tiling, &unsliced) {
+                        (Some(nine_slice), _, Some(unsliced)) => {
+                            nine_slice.render(&mut scene, unsliced, content_size, affine);
+                        }
+                        (None, Some(tiling), Some(unsliced)) => {
+                            tiling.render(&mut scene, unsliced, content_size, affine);
+                        }
+                        _ => {}
+                    }
+                    if let Some(tint) = tint {
+                        pop_tint_layer(&mut scene, tint, &canvas_rect);
+                    }
+                    if ui_clip.is_some() {
+                        scene.pop_layer();
+                    }
+                }
                 RenderItem::Scene(ExtractedRenderScene { scene: scn, .. }) => {
                     scene.append(scn, Some(affine));
                 }
@@ -148,16 +474,23 @@ pub fn render_scene(
                     }
                 }
             }
+            if clip.is_some() {
+                scene.pop_layer();
+            }
+            if blend_mode.is_some() {
+                scene.pop_layer();
+            }
         }
 
         // TODO: Vello should be ignoring 0-sized buffers in the future, so this could go away.
         // Prevent a panic in the vello renderer if all the items contain empty encoding data
         let empty_encodings = render_queue
             .iter()
-            .filter(|(_, _, (_, item))| match item {
+            .filter(|(_, (_, item))| match item {
                 RenderItem::Asset(a) => match &a.asset.data {
                     VectorFile::Svg { scene: svg, .. } => svg.encoding().is_empty(),
-                    VectorFile::Lottie { composition } => composition.layers.is_empty(),
+                    VectorFile::Lottie { composition, .. } => composition.layers.is_empty(),
+                    VectorFile::Image { image } => image.width == 0 || image.height == 0,
                 },
                 RenderItem::Scene(s) => s.scene.encoding().is_empty(),
                 RenderItem::Text(t) => t.text.content.is_empty(),
@@ -165,67 +498,265 @@ pub fn render_scene(
             .count()
             == render_queue.len();
 
-        if !render_queue.is_empty() && !empty_encodings {
-            renderer
-                .0
-                .render_to_texture(
-                    device.wgpu_device(),
-                    &queue,
-                    &scene,
-                    &gpu_image.texture_view,
-                    &RenderParams {
-                        base_color: vello::peniko::Color::TRANSPARENT,
-                        width: gpu_image.size.x as u32,
-                        height: gpu_image.size.y as u32,
-                        antialiasing_method: vello::AaConfig::Area,
-                    },
-                )
-                .unwrap();
+        if render_queue.is_empty() || empty_encodings {
+            // Clear the canvas, with an invisible fill since vello panics on
+            // empty encodings
+            scene = Scene::new();
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                vello::peniko::Color::TRANSPARENT,
+                None,
+                &Rect::new(0.0, 0.0, 1.0, 1.0),
+            );
+        }
+        // Custom content goes over everything, in the top canvas
+        if *canvas + 1 == canvas_depths.canvases(split) {
+            if let Some(custom) = custom.0.get(camera) {
+                scene.append(custom, Some(to_canvas));
+            }
+        }
+        let skipped_decode = match vello_renderer.as_mut() {
+            Some(renderer) => {
+                // Vello writes 8-bit color, which float canvases decode
+                let size = gpu_image.size.as_uvec2();
+                let float_canvas = gpu_image.texture_format != TextureFormat::Rgba8Unorm;
+                let target = match float_canvas {
+                    true => scratch.texture(&device, size),
+                    false => gpu_image.texture_view.clone(),
+                };
+                renderer
+                    .0
+                    .render_to_texture(
+                        device.wgpu_device(),
+                        &queue,
+                        &scene,
+                        &target,
+                        &RenderParams {
+                            base_color: vello::peniko::Color::TRANSPARENT,
+                            width: size.x,
+                            height: size.y,
+                            antialiasing_method: settings.antialiasing.config(),
+                        },
+                    )
+                    .unwrap();
+                float_canvas
+                    && !scratch.decode(
+                        (&device, &queue),
+                        (&pipeline_cache, &composite_pipeline),
+                        size,
+                        &gpu_image.texture_view,
+                    )
+            }
+            None => {
+                #[cfg(feature = "cpu_fallback")]
+                super::cpu::render_to_texture(&scene, gpu_image, &queue);
+                false
+            }
+        };
+        // Custom content is only drawn for a frame, so is cleared next frame
+        if !skipped_decode && !custom_content {
+            still_drawn.insert(render_target_image.id());
         }
     }
+    *drawn = still_drawn;
+    scratch.retain_used();
+    // Shadows of skipped canvases weren't drawn, but are still needed
+    if !skipped_any {
+        shadows.prune();
+    }
+    custom.0.clear();
+}
+
+/// Isolate what is drawn next, to tint it with [`pop_tint_layer`].
+pub(super) fn push_tint_layer(scene: &mut Scene, rect: &Rect) {
+    scene.push_layer(Mix::Normal, 1.0, Affine::IDENTITY, rect);
 }
 
+/// Tint what was drawn since [`push_tint_layer`], keeping its alpha.
+pub(super) fn pop_tint_layer(scene: &mut Scene, tint: &PlaybackColorOverride, rect: &Rect) {
+    let mix = match tint.mode {
+        ColorOverrideMode::Multiply => Mix::Multiply,
+        ColorOverrideMode::Replace => Mix::Normal,
+    };
+    scene.push_layer(
+        BlendMode::new(mix, Compose::SrcAtop),
+        1.0,
+        Affine::IDENTITY,
+        rect,
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        tint.color.to_peniko(),
+        None,
+        rect,
+    );
+    scene.pop_layer();
+    scene.pop_layer();
+}
+
+/// Render each [`VelloRenderTarget`](super::VelloRenderTarget) asset into its
+/// image, stretched to the image size.
+pub fn render_targets(
+    render_targets: Query<&ExtractedRenderTarget>,
+    gpu_images: Res<RenderAssets<Image>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    mut vello_renderer: Option<NonSendMut<BevyVelloRenderer>>,
+    mut velottie_renderer: ResMut<LottieRenderer>,
+    settings: Res<VelloRenderSettings>,
+    cpu_fallback: Option<Res<VelloCpuFallback>>,
+) {
+    // Without a vello renderer, content is only drawn by the CPU fallback
+    if vello_renderer.is_none() && cpu_fallback.is_none() {
+        return;
+    }
+    for target in render_targets.iter() {
+        // The image may not be on the GPU yet
+        let Some(gpu_image) = gpu_images.get(&target.target) else {
+            continue;
+        };
+        let asset = &target.asset;
+        let baked = asset.baked_scale();
+        let transform = Affine::scale_non_uniform(
+            gpu_image.size.x as f64 / asset.width as f64 * baked.x as f64,
+            gpu_image.size.y as f64 / asset.height as f64 * baked.y as f64,
+        );
+        let size = gpu_image.size.as_dvec2();
+        let image_rect = Rect::new(0.0, 0.0, size.x, size.y);
+        let mut scene = Scene::new();
+        if target.tint.is_some() {
+            push_tint_layer(&mut scene, &image_rect);
+        }
+        render_asset(
+            &mut velottie_renderer.0,
+            asset,
+            target.playhead,
+            transform,
+            target.theme.as_ref(),
+            &mut scene,
+        );
+        if let Some(tint) = &target.tint {
+            pop_tint_layer(&mut scene, tint, &image_rect);
+        }
+        // Vello panics on empty encodings
+        if scene.encoding().is_empty() {
+            continue;
+        }
+        if target.alpha < 1.0 {
+            let mut faded = Scene::new();
+            faded.push_layer(Mix::Normal, target.alpha, Affine::IDENTITY, &image_rect);
+            faded.append(&scene, None);
+            faded.pop_layer();
+            scene = faded;
+        }
+        let result = match vello_renderer.as_mut() {
+            Some(renderer) => renderer.0.render_to_texture(
+                device.wgpu_device(),
+                &queue,
+                &scene,
+                &gpu_image.texture_view,
+                &RenderParams {
+                    base_color: vello::peniko::Color::TRANSPARENT,
+                    width: gpu_image.size.x as u32,
+                    height: gpu_image.size.y as u32,
+                    antialiasing_method: settings.antialiasing.config(),
+                },
+            ),
+            None => {
+                #[cfg(feature = "cpu_fallback")]
+                super::cpu::render_to_texture(&scene, gpu_image, &queue);
+                Ok(())
+            }
+        };
+        if let Err(err) = result {
+            error!("failed to render vello asset into image: {err}");
+        }
+    }
+}
+
+/// Resize canvases to the viewport of their camera, scaled by the render
+/// scale, e.g. when its window is resized, and recreate them when the canvas
+/// format changes. Canvases are drawn into the
+/// viewport, so content is clipped to it, and screen space content is placed
+/// relative to its top left corner.
 pub fn resize_rendertargets(
-    mut window_resize_events: EventReader<WindowResized>,
     mut query: Query<(&mut SSRenderTarget, &Handle<VelloCanvasMaterial>)>,
     mut images: ResMut<Assets<Image>>,
     mut target_materials: ResMut<Assets<VelloCanvasMaterial>>,
-    windows: Query<&Window>,
+    cameras: Query<&Camera>,
+    cpu_fallback: Option<Res<VelloCpuFallback>>,
+    settings: Res<VelloRenderSettings>,
 ) {
-    let Ok(window) = windows.get_single() else {
-        return;
-    };
-    if window_resize_events.read().last().is_some() {
-        let size = Extent3d {
-            width: window.resolution.physical_width(),
-            height: window.resolution.physical_height(),
-            ..default()
+    let cpu_fallback = cpu_fallback.is_some();
+    let format = settings.canvas_texture_format(cpu_fallback);
+    for (mut target, target_mat_handle) in query.iter_mut() {
+        let Some(size) = cameras
+            .get(target.camera)
+            .ok()
+            .and_then(Camera::physical_viewport_size)
+            .map(|viewport| settings.canvas_size(viewport))
+        else {
+            continue;
         };
-        for (mut target, target_mat_handle) in query.iter_mut() {
-            let image = setup_image(&mut images, &window.resolution);
-            if let Some(mat) = target_materials.get_mut(target_mat_handle) {
-                target.0 = image.clone();
-                mat.texture = image;
-            }
-            debug!(
-                size = format!(
-                    "Resized Vello render image to {:?}",
-                    (size.width, size.height)
-                )
-            );
+        if images
+            .get(&target.image)
+            .is_some_and(|image| image.size() == size && image.texture_descriptor.format == format)
+        {
+            continue;
         }
+        let image = setup_image(&mut images, size, format, cpu_fallback);
+        if let Some(mat) = target_materials.get_mut(target_mat_handle) {
+            target.image = image.clone();
+            mat.texture = image;
+            mat.srgb = (format == TextureFormat::Rgba8Unorm).into();
+        }
+        debug!(size = format!("Resized Vello render image to {:?}", (size.x, size.y)));
     }
 }
 
+/// Spawn a canvas for each of the [`VelloCanvasDepths`] and each camera that
+/// renders vello content, replacing the previous canvases when either
+/// changes. Cameras whose [`VelloCameraStages`] differ for world and screen
+/// space content get another canvas for screen space content.
+#[allow(clippy::too_many_arguments)]
 pub fn setup_ss_rendertarget(
     mut commands: Commands,
+    canvas_depths: Res<VelloCanvasDepths>,
+    stages: Res<VelloCameraStages>,
+    canvases: Query<Entity, With<SSRenderTarget>>,
+    cameras: Query<(Entity, &Camera, Option<&RenderLayers>, Has<VelloView>)>,
+    mut spawned_views: Local<Vec<(Entity, RenderLayers, bool)>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut images: ResMut<Assets<Image>>,
     mut custom_materials: ResMut<Assets<VelloCanvasMaterial>>,
-    windows: Query<&Window>,
     mut render_target_mesh_handle: Local<Option<Handle<Mesh>>>,
+    cpu_fallback: Option<Res<VelloCpuFallback>>,
+    settings: Res<VelloRenderSettings>,
 ) {
-    let Ok(window) = windows.get_single() else {
+    let views = vello_views(
+        cameras
+            .iter()
+            .filter(|(_, camera, ..)| camera.is_active)
+            .map(|(entity, _, layers, marked)| (entity, layers, marked)),
+    )
+    .into_iter()
+    .map(|(camera, layers)| (camera, layers, stages.is_split(camera)))
+    .collect::<Vec<_>>();
+    if !canvas_depths.is_changed() && views == *spawned_views {
+        return;
+    }
+    // Canvases are the size of their camera's viewport, which is known
+    // once the camera has been updated
+    let Some(sizes) = views
+        .iter()
+        .map(|(camera, ..)| {
+            let viewport = cameras.get(*camera).ok()?.1.physical_viewport_size()?;
+            Some(settings.canvas_size(viewport))
+        })
+        .collect::<Option<Vec<UVec2>>>()
+    else {
         return;
     };
 
@@ -252,31 +783,96 @@ pub fn setup_ss_rendertarget(
 
         meshes.add(rendertarget_quad)
     });
-    let texture_image = setup_image(&mut images, &window.resolution);
-    let render_target = SSRenderTarget(texture_image.clone());
-    let mesh = Mesh2dHandle(mesh_handle.clone());
-    let material = custom_materials.add(VelloCanvasMaterial {
-        texture: texture_image,
-    });
+    for canvas in canvases.iter() {
+        commands.entity(canvas).despawn();
+    }
+    let cpu_fallback = cpu_fallback.is_some();
+    let format = settings.canvas_texture_format(cpu_fallback);
+    for (&(camera, render_layers, split), &size) in views.iter().zip(sizes.iter()) {
+        for canvas in 0..canvas_depths.canvases(split) {
+            let texture_image = setup_image(&mut images, size, format, cpu_fallback);
+            let render_target = SSRenderTarget {
+                image: texture_image.clone(),
+                canvas,
+                camera,
+            };
+            let mesh = Mesh2dHandle(mesh_handle.clone());
+            let material = custom_materials.add(VelloCanvasMaterial {
+                texture: texture_image,
+                intensity: 1.0,
+                srgb: (format == TextureFormat::Rgba8Unorm).into(),
+            });
 
-    commands
-        .spawn(MaterialMesh2dBundle {
-            mesh,
-            material,
-            transform: Transform::from_translation(0.001 * Vec3::NEG_Z), // Make sure the vello canvas renders behind Gizmos
-            ..Default::default()
-        })
-        .insert(NoFrustumCulling)
-        .insert(render_target);
+            commands
+                .spawn(MaterialMesh2dBundle {
+                    mesh,
+                    material,
+                    // Sorted among other 2D content in the transparent phase
+                    transform: Transform::from_xyz(0.0, 0.0, canvas_depths.canvas_z(canvas)),
+                    ..Default::default()
+                })
+                .insert(NoFrustumCulling)
+                // Only drawn by its camera
+                .insert((render_target, render_layers));
+        }
+    }
+    *spawned_views = views;
+}
+
+/// Keep each camera from drawing other cameras' canvases, which share its
+/// render layers when split-screen cameras show the same content.
+pub fn hide_foreign_canvases(
+    mut cameras: Query<(Entity, &mut VisibleEntities), With<Camera>>,
+    canvases: Query<&SSRenderTarget>,
+) {
+    for (camera, mut visible) in cameras.iter_mut() {
+        visible.entities.retain(|entity| {
+            canvases
+                .get(*entity)
+                .map_or(true, |target| target.camera == camera)
+        });
+    }
+}
+
+/// Apply the [`VelloEmissive`] intensity of each camera to its canvases.
+pub fn update_canvas_intensity(
+    canvases: Query<(&SSRenderTarget, &Handle<VelloCanvasMaterial>)>,
+    cameras: Query<Option<&VelloEmissive>, With<Camera>>,
+    mut materials: ResMut<Assets<VelloCanvasMaterial>>,
+) {
+    for (target, material) in canvases.iter() {
+        let Ok(emissive) = cameras.get(target.camera) else {
+            continue;
+        };
+        let intensity = emissive.copied().unwrap_or_default().0;
+        // Only touch the material when it changes, since that rebuilds it
+        if materials
+            .get(material)
+            .is_some_and(|material| material.intensity != intensity)
+        {
+            if let Some(material) = materials.get_mut(material) {
+                material.intensity = intensity;
+            }
+        }
+    }
 }
 
-/// Hide the RenderTarget canvas if there is nothing to render
+/// Hide the RenderTarget canvases if there is nothing to render
 pub fn clear_when_empty(
-    mut query_render_target: Query<&mut Visibility, With<SSRenderTarget>>,
+    mut query_render_target: Query<(&SSRenderTarget, &mut Visibility)>,
     render_items: Query<(&mut CoordinateSpace, &ViewVisibility)>,
+    fragments: Res<VelloSceneFragments>,
+    canvas_depths: Res<VelloCanvasDepths>,
+    stages: Res<VelloCameraStages>,
+    settings: Res<VelloRenderSettings>,
 ) {
-    if let Ok(mut visibility) = query_render_target.get_single_mut() {
-        if render_items.is_empty() {
+    for (target, mut visibility) in query_render_target.iter_mut() {
+        // Outside the main pass, canvases are drawn by the composite nodes
+        let screen = canvas_depths.is_screen_canvas(target.canvas);
+        let main_pass =
+            stages.stage(&settings, target.camera, screen) == VelloCanvasStage::MainPass;
+        let empty = render_items.is_empty() && fragments.0.is_empty();
+        if empty || !main_pass {
             *visibility = Visibility::Hidden;
         } else {
             *visibility = Visibility::Inherited;
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::Mix;
+use vello::Scene;
+
+/// Repeats an asset across a region of `size`, e.g. for backgrounds, stripes
+/// and pattern fills, instead of spawning a copy per tile.
+///
+/// Lengths are in the units the asset is drawn in, before its transform. The
+/// region stays centered where the asset would be, and tiles start from its
+/// top left. On a UI node, `size` is replaced by the node's size. Ignored
+/// with a [`NineSlice`](super::NineSlice). At most
+/// [`VelloTiling::MAX_TILES`] tiles are drawn.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct VelloTiling {
+    /// The size of the tiled region.
+    pub size: Vec2,
+    /// Mirror every other column (`x`) or row (`y`), so edges line up
+    /// seamlessly.
+    pub mirror: BVec2,
+}
+
+impl VelloTiling {
+    /// The most tiles drawn for one entity.
+    pub const MAX_TILES: usize = 4096;
+
+    /// Draw `content`, an asset of `content_size`, repeated across the region.
+    pub(crate) fn render(
+        &self,
+        scene: &mut Scene,
+        content: &Scene,
+        content_size: Vec2,
+        transform: Affine,
+    ) {
+        let Some((region, tiles)) = self.tiles(content_size) else {
+            return;
+        };
+        scene.push_layer(Mix::Clip, 1.0, transform, &region);
+        for tile in tiles {
+            scene.append(content, Some(transform * tile));
+        }
+        scene.pop_layer();
+    }
+
+    /// The region tiles are clipped to, and the transform of each tile of an
+    /// asset of `content_size`. Returns `None` if nothing can be tiled.
+    fn tiles(&self, content_size: Vec2) -> Option<(Rect, Vec<Affine>)> {
+        if content_size.x <= 0.0 || content_size.y <= 0.0 {
+            return None;
+        }
+        let origin = (content_size - self.size) / 2.0;
+        let region = Rect::new(
+            origin.x as f64,
+            origin.y as f64,
+            (origin.x + self.size.x) as f64,
+            (origin.y + self.size.y) as f64,
+        );
+        let tiles = (self.size / content_size).ceil().max(Vec2::ZERO);
+        let (columns, rows) = (tiles.x as usize, tiles.y as usize);
+        if columns.saturating_mul(rows) > Self::MAX_TILES {
+            warn!("bevy_vello: not tiling {columns}x{rows} tiles, above the maximum");
+            return None;
+        }
+
+        let (width, height) = (content_size.x as f64, content_size.y as f64);
+        let mut tiles = vec![];
+        for column in 0..columns {
+            for row in 0..rows {
+                let mut tile = Affine::translate((
+                    region.x0 + column as f64 * width,
+                    region.y0 + row as f64 * height,
+                ));
+                if self.mirror.x && column % 2 == 1 {
+                    tile = tile * Affine::translate((width, 0.0)) * Affine::FLIP_X;
+                }
+                if self.mirror.y && row % 2 == 1 {
+                    tile = tile * Affine::translate((0.0, height)) * Affine::FLIP_Y;
+                }
+                tiles.push(tile);
+            }
+        }
+        Some((region, tiles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vello::kurbo::Point;
+
+    const CONTENT_SIZE: Vec2 = Vec2::new(10.0, 10.0);
+
+    #[test]
+    fn covers_the_region_from_its_top_left() {
+        let tiling = VelloTiling {
+            size: Vec2::new(25.0, 10.0),
+            mirror: BVec2::FALSE,
+        };
+        let (region, tiles) = tiling.tiles(CONTENT_SIZE).unwrap();
+        assert_eq!(region, Rect::new(-7.5, 0.0, 17.5, 10.0));
+        let origins: Vec<Point> = tiles.iter().map(|tile| *tile * Point::ZERO).collect();
+        assert_eq!(
+            origins,
+            [
+                Point::new(-7.5, 0.0),
+                Point::new(2.5, 0.0),
+                Point::new(12.5, 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn mirrors_every_other_column() {
+        let tiling = VelloTiling {
+            size: Vec2::new(20.0, 10.0),
+            mirror: BVec2::new(true, false),
+        };
+        let (region, tiles) = tiling.tiles(CONTENT_SIZE).unwrap();
+        assert_eq!(tiles[0] * Point::ZERO, Point::new(region.x0, 0.0));
+        // The mirrored tile's left edge meets the first tile's right edge
+        assert_eq!(
+            tiles[1] * Point::new(10.0, 0.0),
+            Point::new(region.x0 + 10.0, 0.0)
+        );
+        assert_eq!(tiles[1] * Point::ZERO, Point::new(region.x0 + 20.0, 0.0));
+    }
+
+    #[test]
+    fn skips_empty_content_and_too_many_tiles() {
+        let tiling = VelloTiling {
+            size: Vec2::new(1000.0, 1000.0),
+            mirror: BVec2::FALSE,
+        };
+        assert!(tiling.tiles(Vec2::ZERO).is_none());
+        assert!(tiling.tiles(Vec2::ONE).is_none());
+        assert!(tiling.tiles(Vec2::splat(100.0)).is_some());
+    }
+}
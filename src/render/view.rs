@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+
+/// Marks a camera that renders vello content.
+///
+/// Each marked camera draws the vello entities whose [`RenderLayers`]
+/// intersect its own (layer 0 when absent), e.g. a world camera and a HUD
+/// camera each with their own vector content. Cameras may share layers,
+/// e.g. split-screen cameras showing the same content, and each only draws
+/// its own canvases. Canvases are sized to the camera's viewport, so cameras
+/// may render to different windows or split one window.
+///
+/// When no camera is marked, the only camera renders vello content, as long
+/// as there is exactly one.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct VelloView;
+
+/// Makes the vello content drawn by a camera emissive, by multiplying its
+/// color.
+///
+/// Vello content is drawn in the 2D main pass, before tonemapping. On an HDR
+/// camera (`Camera::hdr`), an intensity above 1 pushes it past the white
+/// point, so vector effects pick up bloom (`BloomSettings`) and color grading
+/// like the rest of the scene. Without HDR, colors are clamped and this only
+/// brightens them.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct VelloEmissive(pub f32);
+
+impl Default for VelloEmissive {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// The cameras which render vello content, with their render layers.
+pub(crate) fn vello_views<'a>(
+    cameras: impl Iterator<Item = (Entity, Option<&'a RenderLayers>, bool)>,
+) -> Vec<(Entity, RenderLayers)> {
+    let cameras: Vec<_> = cameras
+        .map(|(entity, layers, marked)| (entity, layers.copied().unwrap_or_default(), marked))
+        .collect();
+    let marked: Vec<_> = cameras
+        .iter()
+        .filter(|(_, _, marked)| *marked)
+        .map(|(entity, layers, _)| (*entity, *layers))
+        .collect();
+    match cameras.as_slice() {
+        [(entity, layers, _)] if marked.is_empty() => vec![(*entity, *layers)],
+        _ => marked,
+    }
+}
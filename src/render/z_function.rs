@@ -1,5 +1,85 @@
-use crate::VelloAsset;
+use crate::{CoordinateSpace, VelloAsset};
 use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResource;
+
+/// An explicit draw layer for vello entities, independent of spawn order.
+///
+/// Entities are drawn by coordinate space first (screen space on top of world
+/// space), then by `VelloZIndex`, then by Z: [`ZFunction`] for assets, and
+/// `Transform::translation.z` for scenes and text. Entities without this
+/// component are at index 0, so `VelloZIndex(1)` draws above all of them
+/// whatever their translation Z. Remaining ties are drawn in entity order,
+/// which is stable from frame to frame.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Reflect)]
+#[reflect(Component)]
+pub struct VelloZIndex(pub i32);
+
+/// The depths of other 2D content (sprites, meshes) that vello content
+/// interleaves with.
+///
+/// Vello content is drawn into full screen canvases in Bevy's 2D transparent
+/// phase. With no depths, there is a single canvas just behind Z 0. Each
+/// depth splits off another canvas: world space content with
+/// `GlobalTransform` Z below the first depth is drawn just below it, and
+/// content between two depths is drawn just above the lower one. A sprite at
+/// Z 5 with `VelloCanvasDepths(vec![5.0])` is drawn over vello content below
+/// Z 5, and under vello content at or above it. Screen space content is
+/// always in the top canvas.
+///
+/// Every canvas is a separate vello render, so keep the list short. Within a
+/// canvas, content is ordered as described on [`VelloZIndex`].
+#[derive(Resource, ExtractResource, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct VelloCanvasDepths(pub Vec<f32>);
+
+impl VelloCanvasDepths {
+    /// The sorted depths, without NaNs.
+    fn depths(&self) -> Vec<f32> {
+        let mut depths: Vec<f32> = self.0.iter().copied().filter(|z| !z.is_nan()).collect();
+        depths.sort_by(f32::total_cmp);
+        depths.dedup();
+        depths
+    }
+
+    /// The number of canvases.
+    pub(crate) fn canvases(&self, split: bool) -> usize {
+        self.depths().len() + 1 + split as usize
+    }
+
+    /// Whether a canvas is only for screen space content, see
+    /// [`VelloCameraStages`](super::VelloCameraStages).
+    pub(crate) fn is_screen_canvas(&self, canvas: usize) -> bool {
+        canvas > self.depths().len()
+    }
+
+    /// The canvas content at depth `z` is drawn in. With `split`, screen
+    /// space content has a canvas of its own on top.
+    pub(crate) fn canvas(&self, render_mode: CoordinateSpace, z: f32, split: bool) -> usize {
+        let depths = self.depths();
+        match render_mode {
+            CoordinateSpace::Billboard3d | CoordinateSpace::ScreenSpace => {
+                depths.len() + split as usize
+            }
+            CoordinateSpace::WorldSpace => depths.partition_point(|depth| *depth <= z),
+        }
+    }
+
+    /// The Z of a canvas in the 2D transparent phase.
+    pub(crate) fn canvas_z(&self, canvas: usize) -> f32 {
+        // Behind Gizmos, or just off the depths of the content around it
+        const OFFSET: f32 = 0.001;
+        let depths = self.depths();
+        if canvas > depths.len() {
+            return self.canvas_z(depths.len()) + OFFSET;
+        }
+        match (canvas.checked_sub(1).map(|i| depths[i]), depths.get(canvas)) {
+            (None, None) => -OFFSET,
+            (None, Some(above)) => above - OFFSET,
+            (Some(below), None) => below + OFFSET,
+            (Some(below), Some(above)) => (below + OFFSET).min((below + above) / 2.0),
+        }
+    }
+}
 
 #[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
 pub enum ZFunction {
@@ -49,3 +129,54 @@ impl ZFunction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depths() -> VelloCanvasDepths {
+        VelloCanvasDepths(vec![5.0, f32::NAN, -2.0, 5.0])
+    }
+
+    #[test]
+    fn ignores_nan_and_duplicate_depths() {
+        assert_eq!(depths().depths(), [-2.0, 5.0]);
+        assert_eq!(depths().canvases(false), 3);
+        assert_eq!(depths().canvases(true), 4);
+    }
+
+    #[test]
+    fn world_space_content_is_drawn_above_lower_depths() {
+        let canvas = |z| depths().canvas(CoordinateSpace::WorldSpace, z, false);
+        assert_eq!(canvas(-3.0), 0);
+        assert_eq!(canvas(-2.0), 1);
+        assert_eq!(canvas(0.0), 1);
+        assert_eq!(canvas(5.0), 2);
+        assert_eq!(canvas(10.0), 2);
+    }
+
+    #[test]
+    fn screen_space_content_is_drawn_on_top() {
+        let depths = depths();
+        assert_eq!(depths.canvas(CoordinateSpace::ScreenSpace, -10.0, false), 2);
+        assert_eq!(depths.canvas(CoordinateSpace::ScreenSpace, -10.0, true), 3);
+        assert!(!depths.is_screen_canvas(2));
+        assert!(depths.is_screen_canvas(3));
+    }
+
+    #[test]
+    fn canvases_are_placed_just_off_the_depths() {
+        let depths = depths();
+        assert_eq!(depths.canvas_z(0), -2.0 - 0.001);
+        assert_eq!(depths.canvas_z(1), -2.0 + 0.001);
+        assert_eq!(depths.canvas_z(2), 5.0 + 0.001);
+        assert_eq!(depths.canvas_z(3), 5.0 + 0.001 + 0.001);
+        assert_eq!(VelloCanvasDepths::default().canvas_z(0), -0.001);
+    }
+
+    #[test]
+    fn canvases_between_close_depths_are_centered() {
+        let depths = VelloCanvasDepths(vec![0.0, 0.001]);
+        assert_eq!(depths.canvas_z(1), 0.001 / 2.0);
+    }
+}
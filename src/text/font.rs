@@ -70,6 +70,34 @@ impl VelloFont {
         Vec2::new(width, height)
     }
 
+    /// Lay out a single line of text from the origin, returning its glyphs
+    /// and width.
+    pub(crate) fn layout_line(&self, line: &str, size: f32) -> (Vec<Glyph>, f32) {
+        let Ok(font) = FontRef::from_index(self.font.data.data(), self.font.index) else {
+            return (vec![], 0.0);
+        };
+        let font_size = vello::skrifa::instance::Size::new(size);
+        let charmap = font.charmap();
+        let var_loc = font.axes().location(VARIATIONS);
+        let glyph_metrics = font.glyph_metrics(font_size, &var_loc);
+
+        let mut pen_x = 0f32;
+        let glyphs = line
+            .chars()
+            .map(|ch| {
+                let gid = charmap.map(ch).unwrap_or_default();
+                let x = pen_x;
+                pen_x += glyph_metrics.advance_width(gid).unwrap_or_default();
+                Glyph {
+                    id: gid.to_u16() as u32,
+                    x,
+                    y: 0.0,
+                }
+            })
+            .collect();
+        (glyphs, pen_x)
+    }
+
     pub(crate) fn render(&self, scene: &mut Scene, transform: Affine, text: &VelloText) {
         let font = FontRef::new(self.font.data.data()).expect("Vello font creation error");
 
@@ -1,8 +1,9 @@
 use super::font::VelloFont;
-use crate::assets::VectorLoaderError;
+use crate::assets::VelloAssetError;
 use bevy::asset::io::Reader;
 use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
 use bevy::utils::BoxedFuture;
+use vello::glyph::skrifa::FontRef;
 
 #[derive(Default)]
 pub struct VelloFontLoader;
@@ -12,7 +13,7 @@ impl AssetLoader for VelloFontLoader {
 
     type Settings = ();
 
-    type Error = VectorLoaderError;
+    type Error = VelloAssetError;
 
     fn load<'a>(
         &'a self,
@@ -23,6 +24,9 @@ impl AssetLoader for VelloFontLoader {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
+            // Reject malformed fonts here, rather than when rendering text
+            FontRef::new(&bytes)
+                .map_err(|err| VelloAssetError::Parse(format!("Invalid font: {err}")))?;
             let vello_font = VelloFont::new(bytes.to_vec());
 
             Ok(vello_font)
@@ -30,6 +34,47 @@ impl AssetLoader for VelloFontLoader {
     }
 
     fn extensions(&self) -> &[&str] {
-        &["vttf"]
+        // `ttf` and `otf` are shared with Bevy's own font loader, which is
+        // picked by the handle's type, so typed loads resolve either way.
+        // Untyped loads (e.g. `load_untyped` or `load_folder`) use whichever
+        // loader was registered last, so `vttf` is kept to be unambiguous.
+        &["ttf", "otf", "vttf"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::*;
+    use bevy::text::FontLoader;
+    use std::time::Duration;
+
+    #[test]
+    fn typed_loads_of_shared_extensions_resolve_to_either_font() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: "examples/text/src/assets".to_owned(),
+                ..default()
+            },
+        ))
+        .init_asset::<Font>()
+        .init_asset_loader::<FontLoader>()
+        .init_asset::<VelloFont>()
+        .init_asset_loader::<VelloFontLoader>();
+        let asset_server = app.world.resource::<AssetServer>().clone();
+        let font: Handle<Font> = asset_server.load("Rubik-Medium.ttf");
+        let vello_font: Handle<VelloFont> = asset_server.load("Rubik-Medium.ttf");
+        for _ in 0..500 {
+            app.update();
+            let fonts = app.world.resource::<Assets<Font>>();
+            let vello_fonts = app.world.resource::<Assets<VelloFont>>();
+            if fonts.contains(&font) && vello_fonts.contains(&vello_font) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("both fonts should load from the same .ttf file");
     }
 }
@@ -2,18 +2,29 @@
 //!
 //! A long-term vision here is a selector-styled language, but now is just color swapping by layer name.
 
+use crate::assets::VelloAssetError;
+use crate::convert::{ToBevy, ToPeniko};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
 use bevy::prelude::*;
-use bevy::utils::HashMap;
+use bevy::render::extract_resource::ExtractResource;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::{Deserialize, Serialize};
 use velato::model::{Brush, Shape};
 use velato::Composition;
+use vello::peniko;
 
-#[derive(PartialEq, Component, Default, Clone, Debug, Reflect)]
+#[derive(PartialEq, Component, Asset, Default, Clone, Debug, Reflect, Serialize, Deserialize)]
 #[reflect(Component)]
 /// Add this component to a `VelloAssetBundle` entity to enable runtime color
 /// editing. This interface allows swapping colors in a lottie composition by
 /// selecting the desired layer and shape and overriding the original color with
 /// a new color.
 ///
+/// Themes can also be loaded from `.theme.json` and `.theme.ron` files, and
+/// shared between entities by adding the `Handle<Theme>` instead. A `Theme`
+/// component takes priority over a handle.
+///
 /// Only works for layer shapes with fill or stroke elements.
 pub struct Theme {
     pub(crate) colors: HashMap<String, Color>,
@@ -62,78 +73,78 @@ impl Theme {
                     continue 'layers;
                 }
             };
-            let target_color = vello::peniko::Color::rgba(
-                target_color.r().into(),
-                target_color.g().into(),
-                target_color.b().into(),
-                target_color.a().into(),
-            );
+            let target_color = target_color.to_peniko();
             for shape in shapes.iter_mut() {
-                recolor_shape(shape, target_color);
+                recolor_shape(shape, &|_| target_color);
             }
         }
         composition
     }
 }
 
-/// A helper method to recolor a shape with a target color.
-fn recolor_shape(shape: &mut Shape, target_color: vello::peniko::Color) {
+/// A helper method to recolor a shape by mapping its colors.
+fn recolor_shape(shape: &mut Shape, map: &impl Fn(peniko::Color) -> peniko::Color) {
     match shape {
         velato::model::Shape::Group(shapes, _) => {
             for shape in shapes.iter_mut() {
-                recolor_shape(shape, target_color);
+                recolor_shape(shape, map);
             }
         }
         velato::model::Shape::Draw(draw) => {
-            recolor_brush(&mut draw.brush, target_color);
+            recolor_brush(&mut draw.brush, map);
         }
         velato::model::Shape::Repeater(_) | velato::model::Shape::Geometry(_) => {}
     }
 }
 
-/// A helper method to recolor a brush with a target color.
-fn recolor_brush(brush: &mut Brush, target_color: vello::peniko::Color) {
+/// A helper method to recolor a brush by mapping its colors.
+fn recolor_brush(brush: &mut Brush, map: &impl Fn(peniko::Color) -> peniko::Color) {
     match brush {
         velato::model::Brush::Fixed(brush) => match brush {
-            vello::peniko::Brush::Solid(solid) => {
-                *solid = target_color;
+            peniko::Brush::Solid(solid) => {
+                *solid = map(*solid);
             }
-            vello::peniko::Brush::Gradient(gradient) => {
+            peniko::Brush::Gradient(gradient) => {
                 for stop in gradient.stops.iter_mut() {
-                    stop.color = target_color;
+                    stop.color = map(stop.color);
                 }
             }
-            vello::peniko::Brush::Image(_) => {}
+            peniko::Brush::Image(_) => {}
         },
         velato::model::Brush::Animated(brush) => match brush {
             velato::model::animated::Brush::Solid(brush) => match brush {
                 velato::model::Value::Fixed(solid) => {
-                    *solid = target_color;
+                    *solid = map(*solid);
                 }
                 velato::model::Value::Animated(keyframes) => {
                     for solid in keyframes.values.iter_mut() {
-                        *solid = target_color;
+                        *solid = map(*solid);
                     }
                 }
             },
             velato::model::animated::Brush::Gradient(gr) => match &mut gr.stops {
                 velato::model::ColorStops::Fixed(stops) => {
                     for stop in stops.iter_mut() {
-                        stop.color = target_color;
+                        stop.color = map(stop.color);
                     }
                 }
                 velato::model::ColorStops::Animated(stops) => {
-                    for _ in 0..stops.count {
-                        for stop in stops.values.iter_mut() {
-                            let _offset = stop[0];
-                            let r = &mut stop[1];
-                            *r = target_color.r as f64;
-                            let g = &mut stop[2];
-                            *g = target_color.g as f64;
-                            let b = &mut stop[3];
-                            *b = target_color.b as f64;
-                            let a = &mut stop[4];
-                            *a = target_color.a as f64;
+                    let count = stops.count;
+                    for values in stops.values.iter_mut() {
+                        // Each keyframe holds `count` stops of [offset, r, g, b],
+                        // then optional [offset, a] pairs, with channels in 0..1
+                        let (colors, alphas) = values.split_at_mut((count * 4).min(values.len()));
+                        let mut alphas = alphas.chunks_exact_mut(2);
+                        for stop in colors.chunks_exact_mut(4) {
+                            let alpha = alphas.next();
+                            let a = alpha.as_ref().map_or(1.0, |alpha| alpha[1]);
+                            let color = map(peniko::Color::rgba(stop[1], stop[2], stop[3], a));
+                            stop[1] = color.r as f64 / 255.0;
+                            stop[2] = color.g as f64 / 255.0;
+                            stop[3] = color.b as f64 / 255.0;
+                            if let Some(alpha) = alpha {
+                                alpha[1] = color.a as f64 / 255.0;
+                            }
                         }
                     }
                 }
@@ -141,3 +152,108 @@ fn recolor_brush(brush: &mut Brush, target_color: vello::peniko::Color) {
         },
     }
 }
+
+/// A resource swapping colors in every rendered Lottie, applied after
+/// per-entity [`Theme`]s. Useful for colorblind-friendly or seasonal palettes.
+///
+/// SVGs are encoded when loaded, so their colors are not swapped.
+#[derive(Resource, ExtractResource, PartialEq, Default, Clone, Debug)]
+pub struct VelloPalette {
+    swaps: Vec<(Color, Color)>,
+}
+
+impl VelloPalette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace a color with another.
+    pub fn swap(mut self, from: Color, to: Color) -> Self {
+        self.swaps.push((from, to));
+        self
+    }
+
+    /// Replace a color with another. This will overwrite a previous swap of
+    /// the same color.
+    pub fn edit(&mut self, from: Color, to: Color) -> &mut Self {
+        self.swaps.retain(|(color, _)| !same_color(*color, from));
+        self.swaps.push((from, to));
+        self
+    }
+
+    /// Remove all swaps.
+    pub fn clear(&mut self) {
+        self.swaps.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.swaps.is_empty()
+    }
+
+    pub fn recolor(&self, composition: &Composition) -> Composition {
+        let mut composition = composition.clone();
+        let map = |color: peniko::Color| {
+            self.swaps
+                .iter()
+                .find(|(from, _)| same_color(*from, color.to_bevy()))
+                .map(|(_, to)| to.to_peniko())
+                .unwrap_or(color)
+        };
+        for layer in composition
+            .layers
+            .iter_mut()
+            .chain(composition.assets.values_mut().flatten())
+        {
+            if let velato::model::Content::Shape(shapes) = &mut layer.content {
+                for shape in shapes.iter_mut() {
+                    recolor_shape(shape, &map);
+                }
+            }
+        }
+        composition
+    }
+}
+
+/// Whether two colors are equal when quantized to 8 bits per channel.
+fn same_color(a: Color, b: Color) -> bool {
+    a.as_rgba_u8() == b.as_rgba_u8()
+}
+
+#[derive(Default)]
+pub struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    type Asset = Theme;
+
+    type Settings = ();
+
+    type Error = VelloAssetError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let is_ron = load_context
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == "ron");
+            let theme = if is_ron {
+                ron::de::from_bytes(&bytes)
+                    .map_err(|err| VelloAssetError::Parse(format!("Invalid theme: {err}")))?
+            } else {
+                serde_json::from_slice(&bytes)
+                    .map_err(|err| VelloAssetError::Parse(format!("Invalid theme: {err}")))?
+            };
+            Ok(theme)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.json", "theme.ron"]
+    }
+}
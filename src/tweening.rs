@@ -0,0 +1,85 @@
+//! [`Lens`] implementations to animate vector entities with `bevy_tweening`.
+//!
+//! Requires the `tweening` feature.
+
+use crate::{PlaybackAlphaOverride, PlaybackColorOverride, Playhead, VelloLayers};
+use bevy::prelude::*;
+use bevy_tweening::Lens;
+
+/// A lens to animate the [`PlaybackAlphaOverride`] of an entity.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlaybackAlphaLens {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl Lens<PlaybackAlphaOverride> for PlaybackAlphaLens {
+    fn lerp(&mut self, target: &mut PlaybackAlphaOverride, ratio: f32) {
+        target.0 = self.start + (self.end - self.start) * ratio;
+    }
+}
+
+/// A lens to animate the color of a [`PlaybackColorOverride`], e.g. to fade a
+/// damage flash out.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlaybackColorLens {
+    pub start: Color,
+    pub end: Color,
+}
+
+impl Lens<PlaybackColorOverride> for PlaybackColorLens {
+    fn lerp(&mut self, target: &mut PlaybackColorOverride, ratio: f32) {
+        let start: Vec4 = self.start.into();
+        let end: Vec4 = self.end.into();
+        target.color = start.lerp(end, ratio).into();
+    }
+}
+
+/// A lens to scrub the [`Playhead`] of an entity between two frames. The
+/// player should be paused while tweening, or playback will fight the lens.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlayheadLens {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Lens<Playhead> for PlayheadLens {
+    fn lerp(&mut self, target: &mut Playhead, ratio: f32) {
+        target.seek(self.start + (self.end - self.start) * ratio as f64);
+    }
+}
+
+/// A lens to animate the offset of a [`VelloLayer`](crate::VelloLayer),
+/// relative to its entity's transform.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VelloLayerOffsetLens {
+    /// The index of the layer in [`VelloLayers`].
+    pub layer: usize,
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl Lens<VelloLayers> for VelloLayerOffsetLens {
+    fn lerp(&mut self, target: &mut VelloLayers, ratio: f32) {
+        if let Some(layer) = target.0.get_mut(self.layer) {
+            layer.offset = self.start.lerp(self.end, ratio);
+        }
+    }
+}
+
+/// A lens to animate the alpha of a [`VelloLayer`](crate::VelloLayer).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VelloLayerAlphaLens {
+    /// The index of the layer in [`VelloLayers`].
+    pub layer: usize,
+    pub start: f32,
+    pub end: f32,
+}
+
+impl Lens<VelloLayers> for VelloLayerAlphaLens {
+    fn lerp(&mut self, target: &mut VelloLayers, ratio: f32) {
+        if let Some(layer) = target.0.get_mut(self.layer) {
+            layer.alpha = self.start + (self.end - self.start) * ratio;
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{CoordinateSpace, DebugVisualizations, VelloAsset, ZFunction};
+use bevy::prelude::*;
+use bevy::ui::{AvailableSpace, ContentSize, FocusPolicy, Measure, UiScale};
+use bevy::window::PrimaryWindow;
+
+/// Lays out a vector asset as a `bevy_ui` node. Nodes without an explicit
+/// size are measured from the asset, keeping its aspect ratio. The asset is
+/// stretched to fill the node, clipped to it and to clipping ancestors, and
+/// drawn in UI stack order.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct VelloUiNode {
+    /// The size the node was last measured with, in physical pixels.
+    measured: Vec2,
+}
+
+#[derive(Bundle)]
+pub struct UiVelloAssetBundle {
+    /// Asset data to render
+    pub vector: Handle<VelloAsset>,
+    /// Measures the node from its asset
+    pub ui_node: VelloUiNode,
+    /// The coordinate space, which is screen space for UI nodes
+    pub coordinate_space: CoordinateSpace,
+    /// Describes the logical size of the node
+    pub node: Node,
+    /// Styles which control the layout (size and position) of the node and
+    /// its children
+    pub style: Style,
+    /// The size of the asset, used by the layout
+    pub content_size: ContentSize,
+    /// Whether this node should block interaction with lower nodes
+    pub focus_policy: FocusPolicy,
+    /// Indicates the depth at which the node should appear in the UI
+    pub z_index: ZIndex,
+    /// The depth-sorting function used for vector content outside the UI.
+    /// UI nodes are drawn in UI stack order.
+    pub z_function: ZFunction,
+    /// Whether to render debug visualizations
+    pub debug_visualizations: DebugVisualizations,
+    /// The transform of the node, managed by the UI layout
+    pub transform: Transform,
+    /// The global transform of the node, managed by the UI layout
+    pub global_transform: GlobalTransform,
+    /// User indication of whether an entity is visible. Propagates down the entity hierarchy.
+    pub visibility: Visibility,
+    /// Whether or not an entity is visible in the hierarchy.
+    pub inherited_visibility: InheritedVisibility,
+    /// Algorithmically-computed indication of whether an entity is visible. Should be extracted for rendering.
+    pub view_visibility: ViewVisibility,
+}
+
+impl Default for UiVelloAssetBundle {
+    fn default() -> Self {
+        Self {
+            vector: default(),
+            ui_node: default(),
+            coordinate_space: CoordinateSpace::ScreenSpace,
+            node: default(),
+            style: default(),
+            content_size: default(),
+            focus_policy: default(),
+            z_index: default(),
+            z_function: default(),
+            debug_visualizations: default(),
+            transform: default(),
+            global_transform: default(),
+            visibility: default(),
+            inherited_visibility: default(),
+            view_visibility: default(),
+        }
+    }
+}
+
+/// Measures a node from its asset's size, keeping the aspect ratio when
+/// only one dimension is set.
+struct VelloAssetMeasure {
+    size: Vec2,
+}
+
+impl Measure for VelloAssetMeasure {
+    fn measure(
+        &self,
+        width: Option<f32>,
+        height: Option<f32>,
+        _: AvailableSpace,
+        _: AvailableSpace,
+    ) -> Vec2 {
+        let size = self.size;
+        match (width, height) {
+            (None, None) => size,
+            (Some(width), None) if size.x > 0.0 => Vec2::new(width, size.y * width / size.x),
+            (None, Some(height)) if size.y > 0.0 => Vec2::new(size.x * height / size.y, height),
+            (Some(width), None) => Vec2::new(width, size.y),
+            (None, Some(height)) => Vec2::new(size.x, height),
+            (Some(width), Some(height)) => Vec2::new(width, height),
+        }
+    }
+}
+
+/// Update the content size of UI nodes when their asset loads or changes.
+pub(crate) fn update_ui_content_size(
+    mut query: Query<(&mut VelloUiNode, &mut ContentSize, &Handle<VelloAsset>)>,
+    assets: Res<Assets<VelloAsset>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    ui_scale: Res<UiScale>,
+) {
+    // Layout is computed in physical pixels
+    let scale_factor = windows
+        .get_single()
+        .map(|window| window.resolution.scale_factor())
+        .unwrap_or(1.0)
+        * ui_scale.0;
+    for (mut ui_node, mut content_size, handle) in query.iter_mut() {
+        let Some(asset) = assets.get(handle) else {
+            continue;
+        };
+        let size = Vec2::new(asset.width, asset.height) * scale_factor;
+        if size != ui_node.measured {
+            ui_node.measured = size;
+            content_size.set(VelloAssetMeasure { size });
+        }
+    }
+}
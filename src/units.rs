@@ -0,0 +1,154 @@
+use crate::VelloAsset;
+use bevy::math::{Affine3A, Vec3A};
+use bevy::prelude::*;
+
+/// How an asset's coordinate units map to Bevy world units. Entities without
+/// this component use [`VelloDefaultUnits`].
+#[derive(PartialEq, Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub enum VelloUnits {
+    /// One asset unit is one world unit.
+    #[default]
+    AssetUnits,
+    /// Asset units are pixels, with this many pixels per world unit.
+    PixelsPerUnit(f32),
+    /// Stretch the asset to this size in world units.
+    FitToSize(Vec2),
+}
+
+impl VelloUnits {
+    /// The scale from asset units to world units for an asset.
+    pub fn scale(&self, asset: &VelloAsset) -> Vec2 {
+        match self {
+            VelloUnits::AssetUnits => Vec2::ONE,
+            VelloUnits::PixelsPerUnit(pixels) => Vec2::splat(pixels.recip()),
+            VelloUnits::FitToSize(size) => *size / Vec2::new(asset.width, asset.height),
+        }
+    }
+
+    /// Apply the scale for an asset to a transform.
+    pub fn apply(&self, asset: &VelloAsset, transform: &GlobalTransform) -> GlobalTransform {
+        *transform * Transform::from_scale(self.scale(asset).extend(1.0))
+    }
+}
+
+/// The [`VelloUnits`] used by entities without the component.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct VelloDefaultUnits(pub VelloUnits);
+
+/// Constrains an asset to a size in world units, keeping its aspect ratio
+/// unless stretched. Replaces [`VelloUnits`] on the entity.
+#[derive(PartialEq, Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct VelloSize {
+    /// The size to fit the asset to, in world units.
+    pub target: Vec2,
+    /// How the asset's aspect ratio is handled.
+    pub mode: VelloSizeMode,
+}
+
+/// How a [`VelloSize`] scales an asset to its target.
+#[derive(PartialEq, Eq, Default, Clone, Copy, Debug, Reflect)]
+pub enum VelloSizeMode {
+    /// Scale uniformly until the asset fits inside the target.
+    #[default]
+    Fit,
+    /// Scale uniformly until the asset covers the target. The overflow is not
+    /// clipped, see [`VelloClip`](crate::prelude::VelloClip).
+    Fill,
+    /// Scale each axis to match the target exactly.
+    Stretch,
+    /// Keep the asset's own size, as with [`VelloUnits::AssetUnits`].
+    None,
+}
+
+impl VelloSize {
+    /// The scale from asset units to world units for an asset.
+    pub fn scale(&self, asset: &VelloAsset) -> Vec2 {
+        let stretch = self.target / Vec2::new(asset.width, asset.height);
+        match self.mode {
+            VelloSizeMode::Fit => Vec2::splat(stretch.min_element()),
+            VelloSizeMode::Fill => Vec2::splat(stretch.max_element()),
+            VelloSizeMode::Stretch => stretch,
+            VelloSizeMode::None => Vec2::ONE,
+        }
+    }
+
+    /// Apply the scale for an asset to a transform.
+    pub fn apply(&self, asset: &VelloAsset, transform: &GlobalTransform) -> GlobalTransform {
+        *transform * Transform::from_scale(self.scale(asset).extend(1.0))
+    }
+}
+
+/// The direction of the Y axis for an entity's transform. Entities without
+/// this component use [`VelloDefaultYAxis`].
+///
+/// SVG and Lottie content is authored with Y pointing down, while Bevy's Y
+/// points up. With [`VelloYAxis::Down`], an entity's translation and rotation
+/// follow the authoring convention: positive Y moves down, and positive
+/// rotations are clockwise. Content itself is never mirrored. Rendering and
+/// pointer input both use the same convention.
+#[derive(PartialEq, Eq, Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub enum VelloYAxis {
+    /// Y points up, as in Bevy.
+    #[default]
+    Up,
+    /// Y points down, as in SVG and Lottie.
+    Down,
+}
+
+impl VelloYAxis {
+    /// Convert a transform in this convention to Bevy's convention.
+    pub fn apply(&self, transform: &GlobalTransform) -> GlobalTransform {
+        match self {
+            VelloYAxis::Up => *transform,
+            VelloYAxis::Down => {
+                let mirror = Affine3A::from_scale(Vec3::new(1.0, -1.0, 1.0));
+                GlobalTransform::from(mirror * transform.affine() * mirror)
+            }
+        }
+    }
+}
+
+/// Skews an entity's asset or scene in its local space, by angles in
+/// radians along X and Y, e.g. for isometric vector maps. Applied between
+/// the entity's transform and its [`VelloUnits`].
+#[derive(PartialEq, Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct VelloSkew(pub Vec2);
+
+impl VelloSkew {
+    /// The skew as a matrix, shearing X by Y and Y by X.
+    pub fn affine(&self) -> Affine3A {
+        Affine3A::from_cols(
+            Vec3A::new(1.0, self.0.y.tan(), 0.0),
+            Vec3A::new(self.0.x.tan(), 1.0, 0.0),
+            Vec3A::Z,
+            Vec3A::ZERO,
+        )
+    }
+
+    /// Apply the skew to a transform.
+    pub fn apply(&self, transform: &GlobalTransform) -> GlobalTransform {
+        GlobalTransform::from(transform.affine() * self.affine())
+    }
+}
+
+/// The [`VelloYAxis`] used by entities without the component.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct VelloDefaultYAxis(pub VelloYAxis);
+
+/// How world space content responds to the zoom of an orthographic camera.
+#[derive(PartialEq, Eq, Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub enum VelloZoomScaling {
+    /// Zoom with the camera. Content is re-rendered at every zoom, so it
+    /// stays crisp at any magnification.
+    #[default]
+    Scale,
+    /// Keep one world unit per logical pixel at any zoom, scaled around the
+    /// entity's translation, like screen space UI pinned to a world position,
+    /// e.g. a map marker or a name tag. Such entities aren't frustum culled.
+    Fixed,
+}